@@ -0,0 +1,443 @@
+//! Derive macros for converting Rust types to and from Erlang terms.
+//!
+//! Use these through the `derive` feature of `rust_eterm`, which re-exports
+//! them as `rust_eterm::terms::{ ToETerm, FromETerm }`.
+//!
+//! `#[derive(ToETerm)]` implements `ToExternalBinary` and `Display`, which
+//! makes the type an `ETerm`; `#[derive(FromETerm)]` implements the
+//! `FromETerm` trait.
+//! Types are represented the way Erlang and Gleam would represent them:
+//!
+//! | Rust                                  | Term                      |
+//! |---------------------------------------|---------------------------|
+//! | `struct Unit;`                        | `unit`                    |
+//! | `struct Point(i32, i32);`             | `{point, X, Y}`           |
+//! | `struct Person { name: N, age: A }`   | `{person, Name, Age}`     |
+//! | `#[eterm(map)] struct Person { .. }`  | `#{age => Age, name => Name}` |
+//! | `#[eterm(transparent)] struct Id(T);` | The term of `T`           |
+//! | `enum E { Ok, Error(R) }`             | `ok` or `{error, Reason}` |
+//!
+//! Names are converted to snake case to form atoms.
+//! `#[eterm(rename = "...")]` on a type, variant or field (for maps) chooses
+//! a different atom instead.
+//!
+//! Type parameters are required to implement the same traits as the type
+//! that is being derived.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro2::{ Span, TokenStream };
+use quote::{ format_ident, quote };
+use syn::{ parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, Generics, Ident, LitStr };
+
+#[proc_macro_derive(ToETerm, attributes(eterm))]
+pub fn derive_to_eterm(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    to_eterm(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromETerm, attributes(eterm))]
+pub fn derive_from_eterm(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    from_eterm(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The options given through `#[eterm(...)]` attributes.
+#[derive(Default)]
+struct Options {
+    rename: Option<String>,
+    map: bool,
+    transparent: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Options> {
+        let mut options = Options::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("eterm")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("map") {
+                    options.map = true;
+                } else if meta.path.is_ident("transparent") {
+                    options.transparent = true;
+                } else {
+                    return Err(meta.error("Unknown eterm attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(options)
+    }
+
+    /// The atom for something called `ident` in Rust.
+    fn atom(&self, ident: &Ident) -> String {
+        self.rename.clone().unwrap_or_else(|| snake_case(&ident.to_string()))
+    }
+
+    /// Rejects the options that are only meaningful for structs.
+    fn only_rename(&self, span: Span) -> syn::Result<()> {
+        if self.map || self.transparent {
+            Err(syn::Error::new(span, "Only rename is supported here"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// How a struct or enum variant is represented.
+enum Shape {
+    /// An atom.
+    Atom(String),
+    /// A tuple that starts with an atom, followed by all fields.
+    Record(String, Vec<Field>),
+    /// A map from atoms to all fields, sorted by key.
+    Map(Vec<Field>),
+    /// The term of the only field.
+    Transparent(Field),
+}
+
+struct Field {
+    /// The binding used for the field when destructuring.
+    binding: Ident,
+    /// The name or index of the field.
+    member: syn::Member,
+    /// The map key of the field.
+    key: String,
+}
+
+/// A struct or enum variant, with how to construct or destructure it.
+struct Variant {
+    path: TokenStream,
+    fields: Fields,
+    shape: Shape,
+}
+
+impl Variant {
+    fn new(path: TokenStream, ident: &Ident, fields: &Fields, options: &Options) -> syn::Result<Variant> {
+        let mut all = Vec::new();
+
+        for (i, field) in fields.iter().enumerate() {
+            let field_options = Options::parse(&field.attrs)?;
+            field_options.only_rename(field.ident.as_ref().map_or_else(Span::call_site, Ident::span))?;
+
+            let member = match &field.ident {
+                Some(name) => syn::Member::Named(name.clone()),
+                None => syn::Member::Unnamed(i.into()),
+            };
+            let key = match (&field_options.rename, &field.ident) {
+                (Some(rename), _) => rename.clone(),
+                (None, Some(name)) => name.to_string(),
+                (None, None) => i.to_string(),
+            };
+
+            all.push(Field { binding: format_ident!("__field{}", i), member, key });
+        }
+
+        let shape = if options.transparent {
+            if all.len() != 1 {
+                return Err(syn::Error::new(ident.span(), "A transparent struct must have exactly one field"));
+            }
+            Shape::Transparent(all.remove(0))
+        } else if options.map {
+            if let Fields::Unnamed(_) = fields {
+                return Err(syn::Error::new(ident.span(), "Only structs with named fields can be represented as maps"));
+            }
+            all.sort_by(|a, b| a.key.cmp(&b.key));
+            Shape::Map(all)
+        } else if all.is_empty() {
+            Shape::Atom(options.atom(ident))
+        } else {
+            Shape::Record(options.atom(ident), all)
+        };
+
+        Ok(Variant { path, fields: fields.clone(), shape })
+    }
+
+    fn fields(&self) -> &[Field] {
+        match &self.shape {
+            Shape::Atom(_) => &[],
+            Shape::Record(_, fields) | Shape::Map(fields) => fields,
+            Shape::Transparent(field) => std::slice::from_ref(field),
+        }
+    }
+
+    /// This variant with every field bound to its binding, as a pattern
+    /// that destructures it or an expression that constructs it.
+    fn bound(&self) -> TokenStream {
+        let path = &self.path;
+        let members = self.fields().iter().map(|f| &f.member);
+        let bindings = self.fields().iter().map(|f| &f.binding);
+
+        match self.fields {
+            Fields::Unit => quote!(#path),
+            _ => quote!(#path { #(#members: #bindings,)* }),
+        }
+    }
+
+    /// A statement binding `term` to a `&dyn Field` representing this
+    /// variant, of which the fields are bound by `pattern`.
+    fn representation(&self) -> TokenStream {
+        match &self.shape {
+            Shape::Atom(name) => quote! {
+                let atom = ::rust_eterm::terms::EAtom::new(#name);
+                let term: &dyn ::rust_eterm::terms::derive::Field = &atom;
+            },
+            Shape::Record(name, fields) => {
+                let bindings = fields.iter().map(|f| &f.binding);
+
+                quote! {
+                    let tag = ::rust_eterm::terms::EAtom::new(#name);
+                    let tuple = ::rust_eterm::terms::derive::Tuple(&[&tag, #(#bindings,)*]);
+                    let term: &dyn ::rust_eterm::terms::derive::Field = &tuple;
+                }
+            },
+            Shape::Map(fields) => {
+                let keys = fields.iter().map(|f| format_ident!("{}_key", f.binding));
+                let names = fields.iter().map(|f| &f.key);
+                let entries = fields.iter().map(|f| {
+                    let key = format_ident!("{}_key", f.binding);
+                    let binding = &f.binding;
+                    quote!((&#key, #binding))
+                });
+
+                quote! {
+                    #(let #keys = ::rust_eterm::terms::EAtom::new(#names);)*
+                    let map = ::rust_eterm::terms::derive::Map(&[#(#entries,)*]);
+                    let term: &dyn ::rust_eterm::terms::derive::Field = &map;
+                }
+            },
+            Shape::Transparent(field) => {
+                let binding = &field.binding;
+
+                quote! {
+                    let term: &dyn ::rust_eterm::terms::derive::Field = #binding;
+                }
+            },
+        }
+    }
+
+    /// An expression extracting this variant from `term`.
+    fn extract(&self) -> TokenStream {
+        let construct = self.bound();
+
+        match &self.shape {
+            Shape::Atom(name) => quote! {{
+                ::rust_eterm::terms::derive::atom(term, #name)?;
+                Ok(#construct)
+            }},
+            Shape::Record(name, fields) => {
+                let arity = fields.len();
+                let bindings = fields.iter().map(|f| &f.binding);
+                let indices = 0..arity;
+
+                quote! {{
+                    let elements = ::rust_eterm::terms::derive::record(term, #name, #arity)?;
+                    #(let #bindings = ::rust_eterm::terms::FromETerm::from_eterm_with(&*elements[#indices], options)?;)*
+                    Ok(#construct)
+                }}
+            },
+            Shape::Map(fields) => {
+                let bindings = fields.iter().map(|f| &f.binding);
+                let keys = fields.iter().map(|f| &f.key);
+
+                quote! {{
+                    let map = ::rust_eterm::terms::derive::map(term)?;
+                    #(let #bindings = ::rust_eterm::terms::FromETerm::from_eterm_with(::rust_eterm::terms::derive::map_field(map, #keys)?, options)?;)*
+                    Ok(#construct)
+                }}
+            },
+            Shape::Transparent(field) => {
+                let binding = &field.binding;
+
+                quote! {{
+                    let #binding = ::rust_eterm::terms::FromETerm::from_eterm_with(term, options)?;
+                    Ok(#construct)
+                }}
+            },
+        }
+    }
+
+    /// How this variant is written in error messages.
+    fn describe(&self) -> String {
+        match &self.shape {
+            Shape::Atom(name) => name.clone(),
+            Shape::Record(name, fields) => format!("{{{}{}}}", name, ", _".repeat(fields.len())),
+            Shape::Map(_) => "a map".to_string(),
+            Shape::Transparent(_) => "a term".to_string(),
+        }
+    }
+}
+
+fn variants(input: &DeriveInput) -> syn::Result<Vec<Variant>> {
+    let options = Options::parse(&input.attrs)?;
+    let name = &input.ident;
+
+    match &input.data {
+        Data::Struct(data) => Ok(vec![Variant::new(quote!(#name), name, &data.fields, &options)?]),
+        Data::Enum(data) => {
+            options.only_rename(name.span())?;
+
+            data.variants.iter()
+                .map(|variant| {
+                    let variant_options = Options::parse(&variant.attrs)?;
+                    variant_options.only_rename(variant.ident.span())?;
+
+                    let ident = &variant.ident;
+                    Variant::new(quote!(#name::#ident), ident, &variant.fields, &variant_options)
+                })
+                .collect()
+        },
+        Data::Union(_) => Err(syn::Error::new(name.span(), "Unions can not be converted to terms")),
+    }
+}
+
+/// Requires every type parameter to implement `bounds`.
+fn with_bounds(generics: &Generics, bounds: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics.type_params().map(|param| param.ident.clone()).collect();
+
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bounds));
+    }
+
+    generics
+}
+
+fn to_eterm(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let variants = variants(input)?;
+    let generics = with_bounds(&input.generics, quote!(::rust_eterm::terms::encode::ToExternalBinary + ::std::fmt::Display));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let with_term = |action: TokenStream| {
+        let arms = variants.iter().map(|variant| {
+            let pattern = variant.bound();
+            let representation = variant.representation();
+
+            quote!(#pattern => { #representation #action })
+        });
+
+        quote! {
+            #[allow(unreachable_code)]
+            match self {
+                #(#arms)*
+            }
+        }
+    };
+
+    let to_writer = with_term(quote!(::rust_eterm::terms::encode::ToExternalBinary::to_writer_with(term, writer, options)));
+    let encoded_size = with_term(quote!(::rust_eterm::terms::encode::ToExternalBinary::encoded_size_with(term, options)));
+    let display = with_term(quote!(::std::fmt::Display::fmt(term, f)));
+
+    Ok(quote! {
+        impl #impl_generics ::rust_eterm::terms::encode::ToExternalBinary for #name #type_generics #where_clause {
+            fn to_writer_with(&self, writer: &mut dyn ::std::io::Write, options: &::rust_eterm::terms::encode::EncodeOptions) -> ::std::result::Result<usize, ::rust_eterm::error::Error> {
+                #to_writer
+            }
+
+            fn encoded_size_with(&self, options: &::rust_eterm::terms::encode::EncodeOptions) -> ::std::result::Result<usize, ::rust_eterm::error::Error> {
+                #encoded_size
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Display for #name #type_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                #display
+            }
+        }
+    })
+}
+
+fn from_eterm(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let variants = variants(input)?;
+    let generics = with_bounds(&input.generics, quote!(::rust_eterm::terms::FromETerm));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let body = if let Data::Struct(_) = input.data {
+        variants[0].extract()
+    } else {
+        let arms = variants.iter().map(|variant| {
+            let extract = variant.extract();
+
+            match &variant.shape {
+                Shape::Atom(tag) => quote! {
+                    ::std::option::Option::Some(#tag) if term.is::<::rust_eterm::terms::EAtom>() => #extract,
+                },
+                Shape::Record(tag, _) => quote! {
+                    ::std::option::Option::Some(#tag) if term.is::<::rust_eterm::terms::ETuple>() => #extract,
+                },
+                Shape::Map(_) | Shape::Transparent(_) => unreachable!("Enum variants are atoms or records"),
+            }
+        });
+        let expected = variants.iter().map(Variant::describe).collect::<Vec<_>>().join(" or ");
+
+        quote! {
+            match ::rust_eterm::terms::derive::tag(term) {
+                #(#arms)*
+                _ => Err(::rust_eterm::terms::convert::mismatch(#expected, term)),
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::rust_eterm::terms::FromETerm for #name #type_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_eterm_with(term: &dyn ::rust_eterm::terms::ETerm, options: &::rust_eterm::terms::decode::DecodeOptions) -> ::std::result::Result<Self, ::rust_eterm::error::Error> {
+                #body
+            }
+        }
+    })
+}
+
+/// Converts a Rust type or variant name like `HttpRequest` or `HTTPRequest`
+/// to `http_request`.
+///
+/// This is a copy of the function that `gleam::constructor` uses, which this
+/// crate cannot depend on; `tests/derive.rs` checks that both agree.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            if previous.is_lowercase() || previous.is_numeric() || (previous.is_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_lowercase());
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snake_case;
+
+    #[test]
+    fn snake_cases() {
+        assert_eq!("person", snake_case("Person"));
+        assert_eq!("http_request", snake_case("HttpRequest"));
+        assert_eq!("http_request", snake_case("HTTPRequest"));
+        assert_eq!("point3_d", snake_case("Point3D"));
+        assert_eq!("already_snake", snake_case("already_snake"));
+    }
+}
@@ -0,0 +1,143 @@
+extern crate rust_eterm;
+
+use rust_eterm::terms::{ EAtom, EBinary, EMap, ETerm, ETuple, FromETerm, ToETerm };
+use rust_eterm::terms::decode::{ decode, DecodeOptions };
+use rust_eterm::terms::encode::EncodeOptions;
+use rust_eterm::terms::gleam;
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+struct Unit;
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+struct Pair(EAtom, EBinary);
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+#[eterm(rename = "user")]
+struct HttpUser {
+    name: EAtom,
+    avatar: EBinary,
+}
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+#[eterm(map)]
+struct Settings {
+    theme: EAtom,
+    #[eterm(rename = "avatar")]
+    picture: EBinary,
+}
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+#[eterm(transparent)]
+struct Name(EAtom);
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+enum Shape {
+    Empty,
+    Dot(Pair),
+    #[eterm(rename = "box")]
+    Rectangle { corner: Pair, size: Pair },
+    Empty2(),
+}
+
+#[derive(Debug, PartialEq, ToETerm, FromETerm)]
+enum Tagged<T> {
+    Tagged(EAtom, T),
+    Untagged,
+}
+
+fn roundtrip<T: ETerm + FromETerm + PartialEq + std::fmt::Debug>(value: T, expected: &str) {
+    assert_eq!(expected, value.to_string());
+
+    let bytes = value.to_external_binary().unwrap();
+    assert_eq!(bytes.len(), value.encoded_size().unwrap());
+
+    let decoded = decode(&mut &bytes[..], &DecodeOptions::default()).unwrap();
+    assert_eq!(expected, decoded.to_string());
+    assert_eq!(value, T::from_eterm(&*decoded).unwrap());
+}
+
+fn atom(name: &str) -> EAtom {
+    EAtom::new(name)
+}
+
+fn pair(name: &str, byte: u8) -> Pair {
+    Pair(atom(name), EBinary::new(vec![byte]))
+}
+
+#[test]
+fn structs() {
+    roundtrip(Unit, "unit");
+    roundtrip(pair("a", 1), "{pair,a,<<1>>}");
+    roundtrip(HttpUser { name: atom("joe"), avatar: EBinary::new(vec![1]) }, "{user,joe,<<1>>}");
+    roundtrip(Name(atom("joe")), "joe");
+}
+
+#[test]
+fn maps_have_sorted_keys() {
+    let settings = Settings { theme: atom("dark"), picture: EBinary::new(vec![]) };
+
+    roundtrip(Settings { theme: atom("dark"), picture: EBinary::new(vec![]) }, "#{avatar=><<>>,theme=>dark}");
+    assert_eq!(
+        settings.to_external_binary().unwrap(),
+        settings.to_external_binary_with(&EncodeOptions::default().with_deterministic(true)).unwrap(),
+    );
+}
+
+#[test]
+fn enums() {
+    roundtrip(Shape::Empty, "empty");
+    roundtrip(Shape::Empty2(), "empty2");
+    roundtrip(Shape::Dot(pair("a", 1)), "{dot,{pair,a,<<1>>}}");
+    roundtrip(Shape::Rectangle { corner: pair("a", 0), size: pair("b", 4) }, "{box,{pair,a,<<0>>},{pair,b,<<4>>}}");
+}
+
+#[test]
+fn generics() {
+    roundtrip(Tagged::Tagged(atom("a"), pair("b", 1)), "{tagged,a,{pair,b,<<1>>}}");
+    roundtrip(Tagged::<Pair>::Untagged, "untagged");
+    roundtrip(Tagged::Tagged(atom("nested"), Tagged::<Unit>::Untagged), "{tagged,nested,untagged}");
+}
+
+#[test]
+fn names_match_gleam_constructors() {
+    // The derive macros can't use the snake case conversion of the gleam
+    // module, so both are checked against the same names.
+    #[derive(ToETerm)]
+    struct Person;
+    #[derive(ToETerm)]
+    struct HttpRequest;
+    #[derive(ToETerm)]
+    #[allow(clippy::upper_case_acronyms)]
+    struct HTTPRequest;
+    #[derive(ToETerm)]
+    struct Point3D;
+    #[derive(ToETerm)]
+    #[allow(non_camel_case_types)]
+    struct already_snake;
+
+    let derived: Vec<(&str, Box<dyn ETerm>)> = vec![
+        ("Person", Box::new(Person)),
+        ("HttpRequest", Box::new(HttpRequest)),
+        ("HTTPRequest", Box::new(HTTPRequest)),
+        ("Point3D", Box::new(Point3D)),
+        ("already_snake", Box::new(already_snake)),
+    ];
+
+    for (name, term) in derived {
+        assert_eq!(gleam::constructor(name, vec![]).to_string(), term.to_string(), "{}", name);
+    }
+}
+
+#[test]
+fn mismatches() {
+    let message = |term: &dyn ETerm| Shape::from_eterm(term).unwrap_err().to_string();
+
+    assert_eq!("Expected empty or {dot, _} or {box, _, _} or empty2, got other", message(&atom("other")));
+    assert_eq!("Expected a tuple {dot, ...} of 2 elements, got {dot,a,unit}", message(&ETuple::from((atom("dot"), atom("a"), Unit))));
+    assert!(Pair::from_eterm(&Unit).is_err());
+    // The tag must be the atom itself, not a tuple that starts with it.
+    let nested_tag = ETuple::from((ETuple::from((atom("pair"), atom("x"))), atom("a"), EBinary::new(vec![1])));
+    assert_eq!("Expected a tuple {pair, ...} of 3 elements, got {{pair,x},a,<<1>>}", Pair::from_eterm(&nested_tag).unwrap_err().to_string());
+    assert!(Settings::from_eterm(&Unit).is_err());
+    assert_eq!("Missing key avatar in #{}", Settings::from_eterm(&EMap::new(vec![])).unwrap_err().to_string());
+}
@@ -0,0 +1,277 @@
+//! `etf` prints files in the external term format as text, and converts text
+//! to the external term format.
+//!
+//! Run `etf --help` for its options.
+
+use rust_eterm::error::Error;
+use rust_eterm::terms::{ borrowed, disasm, elixir, gleam, json, parse, pretty, TermTag, ETF_VERSION };
+use rust_eterm::terms::decode::{ message_body, DecodeOptions };
+use rust_eterm::terms::encode::EncodeOptions;
+use rust_eterm::terms::validate::validate;
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io::{ self, Read, Write };
+use std::process;
+
+const USAGE: &str = "\
+Usage: etf [OPTIONS] [FILE]
+
+Reads terms in the external term format from FILE, or from standard input,
+and prints them. Terms may be compressed, and need not start with the
+version byte.
+
+Options:
+  -f, --format FORMAT       Print terms as erlang (the default), pretty,
+                            elixir, gleam, json or hex (a
+                            disassembly of the bytes)
+  -e, --encode              Read terms in Erlang syntax, which each end
+                            with a '.', and write them in the external
+                            term format
+  -c, --compressed[=LEVEL]  Compress the terms written with --encode, at
+                            zlib level 6 unless LEVEL is given
+  -p, --packet N            Read or write frames that start with an N byte
+                            length, like {packet, N} (N is 1, 2 or 4)
+      --validate            Only check that the input contains valid terms
+  -o, --output FILE         Write to FILE instead of standard output
+  -h, --help                Print this help
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Erlang,
+    Pretty,
+    Elixir,
+    Gleam,
+    Json,
+    Hex,
+}
+
+struct Options {
+    format: Format,
+    encode: bool,
+    compression: u32,
+    packet: Option<usize>,
+    validate: bool,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Erlang,
+        encode: false,
+        compression: 0,
+        packet: None,
+        validate: false,
+        input: None,
+        output: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |inline: Option<String>| inline.or_else(|| args.next())
+            .ok_or_else(|| format!("Missing value for {}", name));
+
+        match name.as_str() {
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            },
+            "-f" | "--format" => options.format = match value(inline)?.as_str() {
+                "erlang" => Format::Erlang,
+                "pretty" => Format::Pretty,
+                "elixir" => Format::Elixir,
+                "gleam" => Format::Gleam,
+                "json" => Format::Json,
+                "hex" => Format::Hex,
+                other => return Err(format!("Unknown format {}", other)),
+            },
+            "-e" | "--encode" => options.encode = true,
+            "-c" | "--compressed" => options.compression = match inline {
+                Some(level) => level.parse().ok().filter(|level| *level <= 9)
+                    .ok_or_else(|| format!("Invalid compression level {}", level))?,
+                None => 6,
+            },
+            "-p" | "--packet" => {
+                let packet = value(inline)?;
+                options.packet = Some(packet.parse().ok().filter(|n| [1, 2, 4].contains(n))
+                    .ok_or_else(|| format!("Invalid packet size {}", packet))?);
+            },
+            "--validate" => options.validate = true,
+            "-o" | "--output" => options.output = Some(value(inline)?),
+            "-" => options.input = None,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ if options.input.is_some() => return Err(format!("Unexpected argument {}", arg)),
+            _ => options.input = Some(arg),
+        }
+    }
+
+    if options.compression > 0 && !options.encode {
+        return Err("--compressed can only be used with --encode".to_string());
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("etf: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+
+    if let Err(error) = run(&options) {
+        eprintln!("etf: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> Result<(), Error> {
+    let mut input = Vec::new();
+    match &options.input {
+        Some(path) => input = fs::read(path)?,
+        None => { io::stdin().read_to_end(&mut input)?; },
+    }
+
+    let mut output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(io::BufWriter::new(io::stdout())),
+    };
+
+    if options.encode {
+        encode(&input, options, &mut *output)?;
+    } else {
+        let mut count = 0;
+
+        for mut frame in frames(&input, options.packet)? {
+            while !frame.is_empty() {
+                let offset = frame.as_ptr() as usize - input.as_ptr() as usize;
+                let body = read_term(&mut frame)?;
+                print_term(&body, offset, count, options, &mut *output)?;
+                count += 1;
+
+                if options.packet.is_some() && !frame.is_empty() {
+                    return Err(Error::Message(format!("Frame at offset {} contains more than one term", offset)));
+                }
+            }
+        }
+    }
+
+    output.flush()?;
+    Ok(())
+}
+
+/// Splits `input` into frames that start with a `packet` byte length, or
+/// returns all of `input` if there is no `packet` size.
+fn frames(input: &[u8], packet: Option<usize>) -> Result<Vec<&[u8]>, Error> {
+    let packet = match packet {
+        Some(packet) => packet,
+        None => return Ok(vec![input]),
+    };
+
+    let mut frames = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        if rest.len() < packet {
+            return Err(Error::Message(format!("Incomplete frame length at offset {}", input.len() - rest.len())));
+        }
+
+        let length = rest[..packet].iter().fold(0, |length, &byte| length << 8 | byte as usize);
+        let frame = rest.get(packet..packet + length)
+            .ok_or_else(|| Error::Message(format!("Frame at offset {} is {} bytes, but only {} are left", input.len() - rest.len(), length, rest.len() - packet)))?;
+
+        frames.push(frame);
+        rest = &rest[packet + length..];
+    }
+
+    Ok(frames)
+}
+
+/// Reads the term at the start of `input`, which may be a complete message
+/// or an unversioned term, and advances `input` past it.
+fn read_term<'a>(input: &mut &'a [u8]) -> Result<Cow<'a, [u8]>, Error> {
+    if input.first() == Some(&ETF_VERSION) {
+        return message_body(input);
+    }
+
+    let size = validate(input, &DecodeOptions::default())?;
+    let (term, rest) = input.split_at(size);
+    *input = rest;
+
+    Ok(Cow::Borrowed(term))
+}
+
+fn print_term(body: &[u8], offset: usize, index: usize, options: &Options, output: &mut dyn Write) -> Result<(), Error> {
+    if options.validate {
+        let size = validate(body, &DecodeOptions::default())
+            .map_err(|e| Error::Message(format!("Term {} at offset {} is invalid: {}", index, offset, e)))?;
+        writeln!(output, "Term {} at offset {}: valid, {} bytes", index, offset, size)?;
+
+        return Ok(());
+    }
+
+    if options.format == Format::Hex {
+        return hex_dump(body, offset, index, output);
+    }
+
+    let term = borrowed::decode(&mut &*body)?.to_owned()?;
+    let text = match options.format {
+        Format::Erlang => term.to_string(),
+        Format::Pretty => pretty::pretty(&*term, &pretty::PrettyOptions::default()),
+        Format::Elixir => elixir::inspect(&*term),
+        Format::Gleam => gleam::inspect(&*term),
+        Format::Json => {
+            let value = json::to_json(&*term, &json::JsonOptions::default())?;
+            serde_json::to_string_pretty(&value).map_err(|e| Error::Message(e.to_string()))?
+        },
+        Format::Hex => unreachable!(),
+    };
+
+    writeln!(output, "{}", text)?;
+    Ok(())
+}
+
+/// Writes a disassembly of a term, after a line with its tag and size.
+fn hex_dump(body: &[u8], offset: usize, index: usize, output: &mut dyn Write) -> Result<(), Error> {
+    let tag = body.first().and_then(|&tag| TermTag::try_from(tag).ok());
+    writeln!(output, "Term {} at offset {}: {}, {} bytes", index, offset, tag.map_or("unknown", TermTag::name), body.len())?;
+    write!(output, "{}", disasm::disassemble(body))?;
+
+    Ok(())
+}
+
+fn encode(input: &[u8], options: &Options, output: &mut dyn Write) -> Result<(), Error> {
+    let text = std::str::from_utf8(input).map_err(|e| Error::Message(format!("Input is not UTF-8: {}", e)))?;
+    let terms = parse::parse_all(text)
+        .or_else(|e| parse::parse(text).map(|term| vec![term]).map_err(|_| e))?;
+
+    let encode_options = EncodeOptions::default()
+        .with_version_byte(true)
+        .with_compression(options.compression);
+
+    for term in terms {
+        let bytes = term.to_external_binary_with(&encode_options)?;
+
+        if let Some(packet) = options.packet {
+            let length = bytes.len() as u64;
+            if length >= 1 << (8 * packet) {
+                return Err(Error::Message(format!("Term of {} bytes does not fit in a frame with a {} byte length", length, packet)));
+            }
+
+            output.write_all(&length.to_be_bytes()[8 - packet..])?;
+        }
+
+        output.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
@@ -1,13 +1,32 @@
-#[macro_use]
-extern crate lazy_static;
-
 #[cfg(feature="bigint")]
 extern crate num_bigint;
 
 #[cfg(feature="bigint")]
 extern crate num_traits;
 
-extern crate regex;
+#[cfg(feature="tokio")]
+extern crate tokio;
+
+#[cfg(feature="tokio")]
+extern crate tokio_util;
+
+#[cfg(feature="tokio")]
+extern crate bytes;
+
+#[cfg(feature="compression")]
+extern crate flate2;
 
+#[cfg(feature="derive")]
+extern crate rust_eterm_derive;
+
+#[cfg(feature="json")]
+extern crate serde_json;
+
+#[cfg(feature="json")]
+extern crate base64;
+
+#[doc(hidden)]
+#[macro_use]
+pub mod macros;
 pub mod error;
 pub mod terms;
@@ -0,0 +1,373 @@
+//! Support for the [`eterm!`] and [`assert_term_eq!`] macros.
+//!
+//! [`eterm!`]: ../macro.eterm.html
+//! [`assert_term_eq!`]: ../macro.assert_term_eq.html
+
+use super::terms::{ EString, ETerm };
+use super::terms::diff::diff;
+use super::terms::pretty::single_line;
+
+use std::fmt;
+
+/// Builds a term from Erlang-like syntax.
+///
+/// | Syntax                   | Term                                          |
+/// |--------------------------|-----------------------------------------------|
+/// | `ok`                     | [`EAtom`]                                     |
+/// | `1`, `-1`, `2.5`, `7u64` | The corresponding Rust number                 |
+/// | `'a'`                    | The code point of the character, as a `u32`   |
+/// | `"text"`                 | [`EString`]                                   |
+/// | `[]`                     | [`ENil`]                                      |
+/// | `[1, 2]`                 | [`EList`]                                     |
+/// | `[1, 2 \| tail]`         | [`ENonProperList`]                            |
+/// | `{ok, 1}`                | [`ETuple`]                                    |
+/// | `#{key => value}`        | [`EMap`]                                      |
+/// | `<<1, 2, "text">>`       | [`EBinary`], with strings encoded as UTF-8    |
+/// | `(expression)`           | The value of a Rust expression                |
+///
+/// Unsuffixed integers are `i32`s and unsuffixed floats `f64`s, just like in
+/// Rust.
+/// Atoms that are not valid Rust identifiers can be written as
+/// `(EAtom::new("..."))`, and parenthesized expressions in binaries can be
+/// `u8`s, `i32`s (truncated to 8 bits, like in Erlang), `&str`s or `&[u8]`s.
+///
+/// ```
+/// # #[macro_use] extern crate rust_eterm;
+/// # fn main() {
+/// let value = 42u8;
+/// let term = eterm!({ok, [1, 2.5, "str", <<1, 2>>], #{key => (value)}});
+///
+/// assert_eq!("{ok,[1,2.5,\"str\",<<1,2>>],#{key=>42}}", term.to_string());
+/// # }
+/// ```
+///
+/// Every element of a list, tuple or map is expanded recursively, so very
+/// long literals may require raising the `recursion_limit`.
+///
+/// [`EAtom`]: terms/struct.EAtom.html
+/// [`EString`]: terms/struct.EString.html
+/// [`ENil`]: terms/struct.ENil.html
+/// [`EList`]: terms/struct.EList.html
+/// [`ENonProperList`]: terms/struct.ENonProperList.html
+/// [`ETuple`]: terms/struct.ETuple.html
+/// [`EMap`]: terms/struct.EMap.html
+/// [`EBinary`]: terms/struct.EBinary.html
+#[macro_export]
+macro_rules! eterm {
+    // Splits the elements of a list, tuple or map on top-level commas.
+    (@seq $kind:ident [$($done:tt)*] [] , $($rest:tt)*) => {
+        $crate::eterm!(@seq $kind [$($done)*] [] $($rest)*)
+    };
+    (@seq $kind:ident [$($done:tt)*] [$($cur:tt)+] , $($rest:tt)*) => {
+        $crate::eterm!(@seq $kind [$($done)* ($($cur)+)] [] $($rest)*)
+    };
+    (@seq list [$($done:tt)*] [$($cur:tt)+] | $($tail:tt)+) => {
+        $crate::terms::ENonProperList::new(
+            $crate::eterm!(@elements [$($done)* ($($cur)+)]),
+            ::std::boxed::Box::new($crate::eterm!($($tail)+)),
+        )
+    };
+    (@seq $kind:ident [$($done:tt)*] [$($cur:tt)*] << >> $($rest:tt)*) => {
+        $crate::eterm!(@seq $kind [$($done)*] [$($cur)* << >>] $($rest)*)
+    };
+    (@seq $kind:ident [$($done:tt)*] [$($cur:tt)*] << $($segment:tt),* >> $($rest:tt)*) => {
+        $crate::eterm!(@seq $kind [$($done)*] [$($cur)* << $($segment),* >>] $($rest)*)
+    };
+    (@seq $kind:ident [$($done:tt)*] [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::eterm!(@seq $kind [$($done)*] [$($cur)* $next] $($rest)*)
+    };
+    (@seq $kind:ident [$($done:tt)*] [$($cur:tt)+]) => {
+        $crate::eterm!(@seq $kind [$($done)* ($($cur)+)] [])
+    };
+    (@seq list [$($done:tt)*] []) => {
+        $crate::terms::EList::new($crate::eterm!(@elements [$($done)*]))
+    };
+    (@seq tuple [$($done:tt)*] []) => {
+        $crate::terms::ETuple::new($crate::eterm!(@elements [$($done)*]))
+    };
+    (@seq map [$(($($done:tt)*))*] []) => {
+        $crate::terms::EMap::new(::std::vec![$($crate::eterm!(@pair [] $($done)*)),*])
+    };
+
+    (@elements [$(($($element:tt)*))*]) => {
+        ::std::vec![$(::std::boxed::Box::new($crate::eterm!($($element)*)) as ::std::boxed::Box<dyn $crate::terms::ETerm>),*]
+    };
+
+    (@segment ($value:expr)) => {
+        $value
+    };
+    (@segment $literal:literal) => {
+        $literal
+    };
+
+    // Splits a map association on its `=>`.
+    (@pair [$($key:tt)+] => $($value:tt)+) => {
+        (
+            ::std::boxed::Box::new($crate::eterm!($($key)+)) as ::std::boxed::Box<dyn $crate::terms::ETerm>,
+            ::std::boxed::Box::new($crate::eterm!($($value)+)) as ::std::boxed::Box<dyn $crate::terms::ETerm>,
+        )
+    };
+    (@pair [$($key:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::eterm!(@pair [$($key)* $next] $($rest)*)
+    };
+
+    ([]) => {
+        $crate::terms::ENil
+    };
+    ([$($elements:tt)+]) => {
+        $crate::eterm!(@seq list [] [] $($elements)+)
+    };
+    ({$($elements:tt)*}) => {
+        $crate::eterm!(@seq tuple [] [] $($elements)*)
+    };
+    (#{$($associations:tt)*}) => {
+        $crate::eterm!(@seq map [] [] $($associations)*)
+    };
+    (<<>>) => {
+        $crate::terms::EBinary::new(::std::vec::Vec::new())
+    };
+    (<<$($segment:tt),*>>) => {{
+        let mut bytes = ::std::vec::Vec::new();
+        $($crate::macros::Segment::extend_binary($crate::eterm!(@segment $segment), &mut bytes);)*
+        $crate::terms::EBinary::new(bytes)
+    }};
+    (($value:expr)) => {
+        $value
+    };
+    ($atom:ident) => {
+        $crate::terms::EAtom::new(::std::stringify!($atom))
+    };
+    (- $number:literal) => {
+        $crate::macros::literal(-$number)
+    };
+    ($literal:literal) => {
+        $crate::macros::literal($literal)
+    };
+}
+
+/// A Rust literal that can be used in [`eterm!`].
+///
+/// [`eterm!`]: ../macro.eterm.html
+pub trait Literal {
+    type Term: ETerm;
+
+    fn into_term(self) -> Self::Term;
+}
+
+pub fn literal<L: Literal>(literal: L) -> L::Term {
+    literal.into_term()
+}
+
+macro_rules! number_literal {
+    ($($t:ty),*) => {
+        $(
+            impl Literal for $t {
+                type Term = $t;
+
+                fn into_term(self) -> $t {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+number_literal!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize, f32, f64);
+
+impl Literal for &str {
+    type Term = EString;
+
+    fn into_term(self) -> EString {
+        EString::new(self)
+    }
+}
+
+impl Literal for char {
+    type Term = u32;
+
+    fn into_term(self) -> u32 {
+        self as u32
+    }
+}
+
+/// A segment of a binary in [`eterm!`].
+///
+/// [`eterm!`]: ../macro.eterm.html
+pub trait Segment {
+    fn extend_binary(self, bytes: &mut Vec<u8>);
+}
+
+impl Segment for u8 {
+    fn extend_binary(self, bytes: &mut Vec<u8>) {
+        bytes.push(self);
+    }
+}
+
+impl Segment for i32 {
+    fn extend_binary(self, bytes: &mut Vec<u8>) {
+        bytes.push(self as u8);
+    }
+}
+
+impl Segment for &str {
+    fn extend_binary(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Segment for &[u8] {
+    fn extend_binary(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(self);
+    }
+}
+
+/// Asserts that two terms are exactly equal, like `=:=`, and prints the
+/// places where they differ if they are not.
+///
+/// Both terms can be anything that implements [`ETerm`], a
+/// `Box<dyn ETerm>`, or a dereferenced `&dyn ETerm`.
+/// Like `assert_eq!`, a message can follow the terms.
+///
+/// ```should_panic
+/// # #[macro_use] extern crate rust_eterm;
+/// # fn main() {
+/// let reply = eterm!({ok, #{count => 2.0}});
+///
+/// // Panics with:
+/// // assertion `left == right` failed: bad reply
+/// //   left: {ok,#{count => 2.0}}
+/// //  right: {ok,#{count => 2}}
+/// // differences:
+/// //   2.count: 2.0 != 2 (float vs integer)
+/// assert_term_eq!(reply, eterm!({ok, #{count => 2}}), "bad reply");
+/// # }
+/// ```
+///
+/// [`ETerm`]: terms/trait.ETerm.html
+#[macro_export]
+macro_rules! assert_term_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::macros::assert_term_eq(
+            $crate::macros::AsTerm::as_term(&$left),
+            $crate::macros::AsTerm::as_term(&$right),
+            ::std::option::Option::None,
+        )
+    };
+    ($left:expr, $right:expr, $($message:tt)+) => {
+        $crate::macros::assert_term_eq(
+            $crate::macros::AsTerm::as_term(&$left),
+            $crate::macros::AsTerm::as_term(&$right),
+            ::std::option::Option::Some(::std::format_args!($($message)+)),
+        )
+    };
+}
+
+/// A term that can be compared with [`assert_term_eq!`].
+///
+/// [`assert_term_eq!`]: ../macro.assert_term_eq.html
+pub trait AsTerm {
+    fn as_term(&self) -> &dyn ETerm;
+}
+
+impl<T: ETerm> AsTerm for T {
+    fn as_term(&self) -> &dyn ETerm {
+        self
+    }
+}
+
+impl AsTerm for dyn ETerm {
+    fn as_term(&self) -> &dyn ETerm {
+        self
+    }
+}
+
+impl AsTerm for Box<dyn ETerm> {
+    fn as_term(&self) -> &dyn ETerm {
+        &**self
+    }
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_term_eq(left: &dyn ETerm, right: &dyn ETerm, message: Option<fmt::Arguments>) {
+    let differences = match diff(left, right) {
+        Ok(differences) if differences.is_empty() => return,
+        Ok(differences) => differences,
+        Err(e) => panic!("Cannot compare {} and {}: {}", left, right, e),
+    };
+
+    let message = message.map(|message| format!(": {}", message)).unwrap_or_default();
+    let differences: Vec<String> = differences.iter().map(|difference| format!("  {}", difference)).collect();
+
+    panic!(
+        "assertion `left == right` failed{}\n  left: {}\n right: {}\ndifferences:\n{}",
+        message, single_line(left), single_line(right), differences.join("\n"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::terms::{ EAtom, EBinary, ENil, ETerm, ETuple };
+    use super::super::terms::order::exactly_equal;
+
+    fn same<T: ETerm>(expected: &dyn ETerm, actual: &T) {
+        assert!(exactly_equal(expected, actual).unwrap(), "Expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn scalars() {
+        same(&EAtom::new("ok"), &eterm!(ok));
+        same(&EAtom::new("true"), &eterm!(true));
+        same(&1i32, &eterm!(1));
+        same(&-1i32, &eterm!(-1));
+        same(&-2.5f64, &eterm!(-2.5));
+        same(&u64::MAX, &eterm!(18446744073709551615u64));
+        same(&97u32, &eterm!('a'));
+        same(&ENil, &eterm!([]));
+        same(&EAtom::new("hello world"), &eterm!((EAtom::new("hello world"))));
+        assert_eq!("\"str\"", eterm!("str").to_string());
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!("[1,a,[]]", eterm!([1, a, []]).to_string());
+        assert_eq!("[1,a|b]", eterm!([1, a | b]).to_string());
+        assert_eq!("[1|[2]]", eterm!([1 | [2]]).to_string());
+        assert_eq!("[-1,-2]", eterm!([-1, -2,]).to_string());
+        assert_eq!("{}", eterm!({}).to_string());
+        assert_eq!("{a,{b,-1.5}}", eterm!({a, {b, -1.5}}).to_string());
+        assert_eq!("#{}", eterm!(#{}).to_string());
+        assert_eq!("#{a=>1,{b}=>#{}}", eterm!(#{a => 1, {b} => #{}}).to_string());
+        assert_eq!("#{<<1,2>>=><<>>}", eterm!(#{<<1, 2>> => <<>>}).to_string());
+    }
+
+    #[test]
+    fn binaries() {
+        let tail: &[u8] = &[5, 6];
+        same(&EBinary::new(vec![]), &eterm!(<<>>));
+        same(&EBinary::new(vec![1, 0, b'a', b'b', 4, 5, 6]), &eterm!(<<1, 256, "ab", (4u8), (tail)>>));
+        assert_eq!("[<<1,2>>,<<3>>]", eterm!([<<1, 2>>, <<3>>]).to_string());
+    }
+
+    #[test]
+    fn interpolation() {
+        let name = "joe";
+        let children = ETuple::from((1u8, 2u8));
+
+        let term = eterm!({person, (EAtom::new(name)), (children), [(name.len())]});
+        assert_eq!("{person,joe,{1,2},[3]}", term.to_string());
+    }
+
+    #[test]
+    fn assert_term_eq() {
+        let boxed: Box<dyn ETerm> = Box::new(eterm!([1, 2]));
+        assert_term_eq!(boxed, eterm!("\x01\x02"));
+        assert_term_eq!(*boxed, eterm!([1, 2]), "with a {}", "message");
+
+        let panic = std::panic::catch_unwind(|| assert_term_eq!(eterm!({a, [1, 2]}), eterm!({b, [1]}), "reply {}", 1)).unwrap_err();
+        assert_eq!(
+            "assertion `left == right` failed: reply 1\n  left: {a,[1,2]}\n right: {b,[1]}\ndifferences:\n  1: a != b\n  2: [1,2] != [1] (length 2 vs 1)",
+            panic.downcast_ref::<String>().unwrap(),
+        );
+    }
+}
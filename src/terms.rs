@@ -1,6 +1,6 @@
 //! ## Currently implemented term types (into binary, String):
 //! * [`INTEGER_EXT`], [`SMALL_INTEGER_EXT`] (for `{i,u}{8,16,32,64,128,size}`)
-//! * [`FLOAT_EXT`] (for `f32`, `f64`)
+//! * [`NEW_FLOAT_EXT`], [`FLOAT_EXT`] (for `f32`, `f64`)
 //! * [`NIL_EXT`] (for [`ENil`])
 //! * [`LIST_EXT`] (for [`EList`], [`ENonProperList`])
 //! * [`EXPORT_EXT`] (for [`EExport`])
@@ -13,7 +13,10 @@
 //! * [`NEW_PORT_EXT`] (for [`EPort`])
 //! * [`NEW_PID_EXT`] (for [`EPid`])
 //! * [`ATOM_UTF8_EXT`], [`SMALL_ATOM_UTF8_EXT`] (for [`EAtom`])
-//! * [`STRING_EXT`] (for [`EString`])
+//! * [`ATOM_EXT`], [`SMALL_ATOM_EXT`] (for [`EAtom`], when requested through
+//!   [`EncodeOptions`])
+//! * [`STRING_EXT`] (for [`EString`], and for [`EList`] of bytes when
+//!   encoding deterministically through [`EncodeOptions`])
 //!
 //! ## Currently implemented term types (from binary, String)
 //! None
@@ -29,8 +32,6 @@
 //! * [`NEW_FUN_EXT`] (seems unnecessary for now, maybe in the future?)
 //! * [`REFERENCE_EXT`] (deprecated, decoding support will be added)
 //! * [`NEW_REFERENCE_EXT`] (decoding support will be added)
-//! * [`ATOM_EXT`] (deprecated, decoding support will be added)
-//! * [`SMALL_ATOM_EXT`] (deprecated, decoding support will be added)
 //! * [`PORT_EXT`] (decoding support will be added)
 //! * [`PID_EXT`] (decoding support will be added)
 //! * [`ETF_VERSION`]
@@ -84,23 +85,52 @@
 //! [`EPid`]: struct.EPid.html
 //! [`EMap`]: struct.EMap.html
 //! [`EBinary`]: struct.EBinary.html
+//! [`EncodeOptions`]: encode/struct.EncodeOptions.html
 //! 
 //! [`ETerm`]: trait.ETerm.html
 //! [`To`]: trait.To.html
 //! [`TryTo`]: trait.TryTo.html
 
-mod encode;
+pub mod encode;
+mod layout;
 pub mod decode;
+pub mod borrowed;
+pub mod lazy;
+pub mod validate;
+pub mod disasm;
+pub mod order;
+pub mod path;
+pub mod diff;
+pub mod pattern;
+pub mod parse;
+pub mod pretty;
+mod printing;
+pub mod elixir;
+pub mod gleam;
+pub mod convert;
+#[doc(hidden)]
+pub mod derive;
+#[cfg(feature="tokio")]
+pub mod async_io;
+#[cfg(feature="json")]
+pub mod json;
 
 use std::fmt;
 use std::io::Write;
 use std::any::Any;
 use std::convert::TryFrom;
-
-use regex::Regex;
+use std::iter::FromIterator;
+use std::mem;
+use std::slice;
+use std::vec;
 
 use super::error::Error;
 
+pub use self::convert::FromETerm;
+
+#[cfg(feature="derive")]
+pub use rust_eterm_derive::{ FromETerm, ToETerm };
+
 /// This is the code of the start of a message.
 /// This message will contain an encoded tag or a distribution header.
 pub const ETF_VERSION:         u8 =131;
@@ -117,7 +147,7 @@ pub enum DistHeaderTag {
     /// * `NumberOfAtomCacheRefs` is the amount of atom cache references in
     ///   this message.
     /// * `Flags` is a list of 4-byte values containig flags in the following
-    ///    format:
+    ///   format:
     ///   
     ///   | 1 bit           | 3 bits         |
     ///   | --------------- | -------------- |
@@ -225,9 +255,24 @@ pub enum DistHeaderTag {
     ///   | ------ | ------- |
     ///   | `Tag`  | `Data`  |
     Compressed = 80,
+
+    /// Marks a message in the local external term format of
+    /// `term_to_binary(Term, [local])`, which is only meant to be decoded by
+    /// the instance that encoded it.
+    ///
+    /// The format that follows this tag is unspecified; this crate writes
+    /// and reads the ordinary, possibly compressed, encoding after it.
+    ///
+    /// ### Binary representation
+    ///
+    /// | 1 byte | N bytes |
+    /// | ------ | ------- |
+    /// | `121`  | `Data`  |
+    Local = 121,
 }
 
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TermTag {
     /// Refers to the atom with AtomCacheReferenceIndex in the
     /// [distribution header].
@@ -1000,26 +1045,236 @@ impl TryFrom<u8> for TermTag {
     }
 }
 
+impl TermTag {
+    /// The name of this tag in the documentation of the external term
+    /// format, like `SMALL_TUPLE_EXT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            TermTag::AtomCacheRef => "ATOM_CACHE_REF",
+            TermTag::SmallInteger => "SMALL_INTEGER_EXT",
+            TermTag::Integer => "INTEGER_EXT",
+            TermTag::Float => "FLOAT_EXT",
+            TermTag::Port => "PORT_EXT",
+            TermTag::NewPort => "NEW_PORT_EXT",
+            TermTag::Pid => "PID_EXT",
+            TermTag::NewPid => "NEW_PID_EXT",
+            TermTag::SmallTuple => "SMALL_TUPLE_EXT",
+            TermTag::LargeTuple => "LARGE_TUPLE_EXT",
+            TermTag::Map => "MAP_EXT",
+            TermTag::Nil => "NIL_EXT",
+            TermTag::String => "STRING_EXT",
+            TermTag::List => "LIST_EXT",
+            TermTag::Binary => "BINARY_EXT",
+            TermTag::SmallBig => "SMALL_BIG_EXT",
+            TermTag::LargeBig => "LARGE_BIG_EXT",
+            TermTag::Reference => "REFERENCE_EXT",
+            TermTag::NewReference => "NEW_REFERENCE_EXT",
+            TermTag::NewerReference => "NEWER_REFERENCE_EXT",
+            TermTag::Fun => "FUN_EXT",
+            TermTag::NewFun => "NEW_FUN_EXT",
+            TermTag::Export => "EXPORT_EXT",
+            TermTag::BitBinary => "BIT_BINARY_EXT",
+            TermTag::NewFloat => "NEW_FLOAT_EXT",
+            TermTag::AtomUtf8 => "ATOM_UTF8_EXT",
+            TermTag::SmallAtomUtf8 => "SMALL_ATOM_UTF8_EXT",
+            TermTag::Atom => "ATOM_EXT",
+            TermTag::SmallAtom => "SMALL_ATOM_EXT",
+        }
+    }
+}
+
 /// A type that can be converted to an Erlang Binary Term format and two valid
 /// Erlang String Term representations.
 pub trait ETerm: encode::ToExternalBinary + fmt::Display + Any {
+    #[doc(hidden)]
+    fn as_any(&self) -> &dyn Any;
+
+    #[doc(hidden)]
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    #[doc(hidden)]
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+
+    /// Returns the term at `path` in this term, like `2.users[0].#name`.
+    ///
+    /// See [`path`] for the syntax of paths.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rust_eterm;
+    /// # fn main() {
+    /// use rust_eterm::terms::ETerm;
+    ///
+    /// let term = eterm!({reply, #{users => [#{<<"name">> => joe}]}});
+    /// assert_eq!("joe", term.at("2.users[0].#name").unwrap().to_string());
+    /// assert!(term.at("2.users[1]").is_err());
+    /// # }
+    /// ```
+    ///
+    /// [`path`]: path/index.html
+    fn at(&self, path: &str) -> Result<&dyn ETerm, Error>;
+
+    /// Returns the term at `path` in this term, which can be changed in place.
+    fn at_mut(&mut self, path: &str) -> Result<&mut dyn ETerm, Error>;
+
     fn write_to(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        self.to_writer(writer)
+        self.write_to_with(writer, &encode::EncodeOptions::default())
+    }
+
+    /// Writes this term as a complete message, which, depending on
+    /// `options`, starts with the [`ETF_VERSION`] byte and is compressed.
+    ///
+    /// [`ETF_VERSION`]: constant.ETF_VERSION.html
+    fn write_to_with(&self, writer: &mut dyn Write, options: &encode::EncodeOptions) -> Result<usize, Error> {
+        encode::write_message(writer, options, &|w| self.to_writer_with(w, options))
     }
 
     fn to_external_binary(&self) -> Result<Vec<u8>, Error> {
+        self.to_external_binary_with(&encode::EncodeOptions::default())
+    }
+
+    fn to_external_binary_with(&self, options: &encode::EncodeOptions) -> Result<Vec<u8>, Error> {
         let mut result = Vec::new();
 
-        self.to_writer(&mut result)?;
+        self.write_to_with(&mut result, options)?;
 
         Ok(result)
     }
 }
 
-impl<T> ETerm for T where T: encode::ToExternalBinary + fmt::Display + Any {}
+impl<T> ETerm for T where T: encode::ToExternalBinary + fmt::Display + Any {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn at(&self, path: &str) -> Result<&dyn ETerm, Error> {
+        path::Path::parse(path)?.get(self)
+    }
+
+    fn at_mut(&mut self, path: &str) -> Result<&mut dyn ETerm, Error> {
+        path::Path::parse(path)?.get_mut(self)
+    }
+}
+
+impl fmt::Debug for dyn ETerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl dyn ETerm {
+    /// Returns whether this term is a `T`.
+    pub fn is<T: ETerm>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    /// Returns this term as a `T`, if it is one.
+    ///
+    /// ```
+    /// use rust_eterm::terms::{ EAtom, ETerm };
+    ///
+    /// let term: Box<dyn ETerm> = Box::new(EAtom::new("ok"));
+    /// assert_eq!(Some("ok"), term.downcast_ref::<EAtom>().map(EAtom::as_str));
+    /// assert!(term.downcast_ref::<u8>().is_none());
+    /// ```
+    pub fn downcast_ref<T: ETerm>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
+
+    /// Returns this term as a mutable `T`, if it is one.
+    pub fn downcast_mut<T: ETerm>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut::<T>()
+    }
+
+    /// Converts the term at `path` in this term.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rust_eterm;
+    /// # fn main() {
+    /// use rust_eterm::terms::ETerm;
+    ///
+    /// let term: Box<dyn ETerm> = Box::new(eterm!({point, #{x => 3, y => -4}}));
+    /// assert_eq!(-4, term.at_as::<i32>("2.y").unwrap());
+    /// # }
+    /// ```
+    pub fn at_as<T: FromETerm>(&self, path: &str) -> Result<T, Error> {
+        T::from_eterm(self.at(path)?)
+    }
+
+    /// Replaces the term at `path` in this term by `term`, and returns the
+    /// term that was there.
+    ///
+    /// Unlike [`at_mut`], this can change the type of the term.
+    ///
+    /// [`at_mut`]: trait.ETerm.html#tymethod.at_mut
+    pub fn replace_at(&mut self, path: &str, term: Box<dyn ETerm>) -> Result<Box<dyn ETerm>, Error> {
+        path::Path::parse(path)?.replace(self, term)
+    }
+
+    /// Converts this boxed term into a `T`, or returns it unchanged if it is
+    /// not one.
+    pub fn downcast<T: ETerm>(self: Box<Self>) -> Result<Box<T>, Box<dyn ETerm>> {
+        if self.is::<T>() {
+            Ok(self.into_any().downcast::<T>().expect("The type was just checked"))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// How Rust values without an obvious counterpart in Erlang are represented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Convention {
+    /// `None` is the atom `undefined`, and strings are lists of code points.
+    Erlang,
+    /// `None` is the atom `nil`, and strings are UTF-8 binaries.
+    Elixir,
+    /// `None` is the atom `none` and `Some(value)` is `{some, Value}`, like
+    /// Gleam's `Option`, `()` is `nil` like Gleam's `Nil`, and strings are
+    /// UTF-8 binaries.
+    Gleam,
+}
+
+impl Convention {
+    /// The atom that represents `None`.
+    pub fn none(self) -> &'static str {
+        match self {
+            Convention::Erlang => "undefined",
+            Convention::Elixir => "nil",
+            Convention::Gleam => "none",
+        }
+    }
+
+    /// Whether `Some(value)` is wrapped in a `{some, Value}` tuple.
+    pub fn wraps_some(self) -> bool {
+        self == Convention::Gleam
+    }
+
+    /// The atom that represents `()`.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Convention::Erlang | Convention::Elixir => "ok",
+            Convention::Gleam => "nil",
+        }
+    }
+
+    /// Whether `Ok(())` and `Err(())` are wrapped in `{ok, Value}` and
+    /// `{error, Reason}` tuples like other results, rather than being just
+    /// the atoms `ok` and `error`.
+    pub fn wraps_unit(self) -> bool {
+        self == Convention::Gleam
+    }
+}
 
 /// Represents an Erlang `NIL_EXT` term.
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ENil;
 
 impl fmt::Display for ENil {
@@ -1031,15 +1286,76 @@ impl fmt::Display for ENil {
 /// Represents a proper `LIST_EXT` term with a `nil` tail.
 pub struct EList(Vec<Box<dyn ETerm>>);
 
-impl<'a> fmt::Display for EList {
+impl EList {
+    pub fn new(elements: Vec<Box<dyn ETerm>>) -> EList {
+        EList(elements)
+    }
+
+    pub fn as_slice(&self) -> &[Box<dyn ETerm>] {
+        &self.0
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Box<dyn ETerm>] {
+        &mut self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Box<dyn ETerm>> {
+        self.0.iter()
+    }
+
+    /// Appends a term to the end of this list.
+    pub fn push<T: ETerm>(&mut self, term: T) {
+        self.0.push(Box::new(term));
+    }
+
+    pub fn into_inner(self) -> Vec<Box<dyn ETerm>> {
+        self.0
+    }
+}
+
+impl From<Vec<Box<dyn ETerm>>> for EList {
+    fn from(elements: Vec<Box<dyn ETerm>>) -> EList {
+        EList(elements)
+    }
+}
+
+impl FromIterator<Box<dyn ETerm>> for EList {
+    fn from_iter<I: IntoIterator<Item = Box<dyn ETerm>>>(iter: I) -> EList {
+        EList(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for EList {
+    type Item = Box<dyn ETerm>;
+    type IntoIter = vec::IntoIter<Box<dyn ETerm>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a EList {
+    type Item = &'a Box<dyn ETerm>;
+    type IntoIter = slice::Iter<'a, Box<dyn ETerm>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for EList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut s = "[".to_string();
-        for d in self.0.iter() {
-            s.push_str(format!("{}", *d).as_ref());
-        }
-        s.push(']');
+        let parts: Vec<String> = self.0.iter().map(|d| d.to_string()).collect();
 
-        f.write_str(s.as_ref())
+        write!(f, "[{}]", parts.join(","))
     }
 }
 
@@ -1049,39 +1365,133 @@ pub struct ENonProperList {
     tail: Box<dyn ETerm>,
 }
 
+impl ENonProperList {
+    pub fn new(data: Vec<Box<dyn ETerm>>, tail: Box<dyn ETerm>) -> ENonProperList {
+        ENonProperList { data, tail }
+    }
+
+    /// The elements of this list, not including the tail.
+    pub fn data(&self) -> &[Box<dyn ETerm>] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [Box<dyn ETerm>] {
+        &mut self.data
+    }
+
+    pub fn tail(&self) -> &dyn ETerm {
+        &*self.tail
+    }
+
+    pub fn tail_mut(&mut self) -> &mut Box<dyn ETerm> {
+        &mut self.tail
+    }
+
+    /// Returns the elements and the tail of this list.
+    pub fn into_parts(self) -> (Vec<Box<dyn ETerm>>, Box<dyn ETerm>) {
+        (self.data, self.tail)
+    }
+}
+
 impl fmt::Display for ENonProperList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut s = "[".to_string();
-        for d in self.data.iter() {
-            s.push_str(format!("{}", d).as_ref());
-        }
-        s.push('|');
-        s.push_str(format!("{}", self.tail).as_ref());
-        s.push(']');
+        let parts: Vec<String> = self.data.iter().map(|d| d.to_string()).collect();
 
-        f.write_str(s.as_ref())
+        write!(f, "[{}|{}]", parts.join(","), self.tail)
     }
 }
 
 /// Describes an `ATOM_UTF8_EXT` term and a `SMALL_ATOM_UTF8_EXT` term.
 ///
-/// TODO: Make sure the string representation of atoms that need to be quoted
-///  is implemented.
+/// Atoms are displayed the way Erlang writes them, quoted only when they
+/// would not be read back as the same atom:
+///
+/// ```
+/// use rust_eterm::terms::EAtom;
+///
+/// assert_eq!("ok", EAtom::new("ok").to_string());
+/// assert_eq!("'Hello'", EAtom::new("Hello").to_string());
+/// assert_eq!("'end'", EAtom::new("end").to_string());
+/// assert_eq!("'it\\'s'", EAtom::new("it's").to_string());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct EAtom(String);
 
+impl EAtom {
+    pub fn new<S: Into<String>>(name: S) -> EAtom {
+        EAtom(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
+    /// Whether this atom has to be quoted in Erlang source, like
+    /// `io_lib:quote_atom/2` decides.
+    ///
+    /// Atoms that start with a lowercase letter and only contain letters,
+    /// digits, `_` and `@` do not, unless they are reserved words.
+    /// Only the letters of LATIN-1 count: even though atoms can contain any
+    /// Unicode character since OTP 20, Erlang still quotes the others.
+    pub fn needs_quotes(&self) -> bool {
+        let mut chars = self.0.chars();
+
+        let starts_lowercase = match chars.next() {
+            Some(c) => is_lowercase(c),
+            None => return true,
+        };
+
+        !starts_lowercase
+            || !chars.all(|c| is_lowercase(c) || is_uppercase(c) || c.is_ascii_digit() || c == '_' || c == '@')
+            || RESERVED_WORDS.contains(&self.0.as_str())
+    }
+}
+
+/// The words that can not be used as unquoted atoms, as reported by
+/// `erl_scan:reserved_word/1`, including `maybe` and `else` of the `maybe`
+/// expression that is enabled by default since OTP 27.
+const RESERVED_WORDS: &[&str] = &[
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case", "catch",
+    "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not", "of", "or", "orelse", "receive",
+    "rem", "try", "when", "xor",
+];
+
+fn is_lowercase(c: char) -> bool {
+    matches!(c, 'a'..='z' | 'ß'..='ÿ') && c != '÷'
+}
+
+fn is_uppercase(c: char) -> bool {
+    matches!(c, 'A'..='Z' | 'À'..='Þ') && c != '×'
+}
+
+impl From<&str> for EAtom {
+    fn from(name: &str) -> EAtom {
+        EAtom(name.to_string())
+    }
+}
+
+impl From<String> for EAtom {
+    fn from(name: String) -> EAtom {
+        EAtom(name)
+    }
+}
+
+impl AsRef<str> for EAtom {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
 impl fmt::Display for EAtom {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        lazy_static! {
-            static ref RX_SIMPLE_ATOM_REPR: Regex =
-                Regex::new("[a-z@][0-9a-zA-Z_@]*").unwrap();
-        }
-
-        if RX_SIMPLE_ATOM_REPR.is_match(self.0.as_ref()) {
-            // It is not necessary to escape the atom, so don't.
-            write!(f, "{}", self.0)
+        if self.needs_quotes() {
+            write!(f, "'{}'", escape_quoted(&self.0, '\''))
         } else {
-            // It is necessary to escape the atom.
-            write!(f, "'{}'", escape_string(&self.0))
+            f.write_str(&self.0)
         }
     }
 }
@@ -1093,6 +1503,25 @@ pub struct EExport {
     arity: u8,
 }
 
+impl EExport {
+    /// Creates the term for `fun module:function/arity`.
+    pub fn new<M: Into<EAtom>, F: Into<EAtom>>(module: M, function: F, arity: u8) -> EExport {
+        EExport { module: module.into(), function: function.into(), arity }
+    }
+
+    pub fn module(&self) -> &EAtom {
+        &self.module
+    }
+
+    pub fn function(&self) -> &EAtom {
+        &self.function
+    }
+
+    pub fn arity(&self) -> u8 {
+        self.arity
+    }
+}
+
 impl fmt::Display for EExport {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = "{".to_string();
@@ -1108,8 +1537,103 @@ impl fmt::Display for EExport {
 }
 
 /// Represents a `LARGE_TUPLE_EXT` or a `SMALL_TUPLE_EXT` term.
+///
+/// Rust tuples of up to 12 terms can be converted into an `ETuple`:
+///
+/// ```
+/// use rust_eterm::terms::{ EAtom, ETuple };
+///
+/// let tuple = ETuple::from((EAtom::new("ok"), 42u8));
+/// assert_eq!(2, tuple.len());
+/// assert_eq!("{ok,42}", tuple.to_string());
+/// ```
 pub struct ETuple(Vec<Box<dyn ETerm>>);
 
+impl ETuple {
+    pub fn new(elements: Vec<Box<dyn ETerm>>) -> ETuple {
+        ETuple(elements)
+    }
+
+    pub fn as_slice(&self) -> &[Box<dyn ETerm>] {
+        &self.0
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Box<dyn ETerm>] {
+        &mut self.0
+    }
+
+    /// The arity of this tuple.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Box<dyn ETerm>> {
+        self.0.iter()
+    }
+
+    pub fn into_inner(self) -> Vec<Box<dyn ETerm>> {
+        self.0
+    }
+}
+
+impl From<Vec<Box<dyn ETerm>>> for ETuple {
+    fn from(elements: Vec<Box<dyn ETerm>>) -> ETuple {
+        ETuple(elements)
+    }
+}
+
+impl FromIterator<Box<dyn ETerm>> for ETuple {
+    fn from_iter<I: IntoIterator<Item = Box<dyn ETerm>>>(iter: I) -> ETuple {
+        ETuple(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for ETuple {
+    type Item = Box<dyn ETerm>;
+    type IntoIter = vec::IntoIter<Box<dyn ETerm>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ETuple {
+    type Item = &'a Box<dyn ETerm>;
+    type IntoIter = slice::Iter<'a, Box<dyn ETerm>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+macro_rules! tuple_from {
+    ($($name:ident),+) => {
+        impl<$($name: ETerm),+> From<($($name,)+)> for ETuple {
+            #[allow(non_snake_case)]
+            fn from(($($name,)+): ($($name,)+)) -> ETuple {
+                ETuple(vec![$(Box::new($name)),+])
+            }
+        }
+    };
+}
+
+tuple_from!(A);
+tuple_from!(A, B);
+tuple_from!(A, B, C);
+tuple_from!(A, B, C, D);
+tuple_from!(A, B, C, D, E);
+tuple_from!(A, B, C, D, E, F);
+tuple_from!(A, B, C, D, E, F, G);
+tuple_from!(A, B, C, D, E, F, G, H);
+tuple_from!(A, B, C, D, E, F, G, H, I);
+tuple_from!(A, B, C, D, E, F, G, H, I, J);
+tuple_from!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_from!(A, B, C, D, E, F, G, H, I, J, K, L);
+
 impl fmt::Display for ETuple {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = "{".to_string();
@@ -1127,8 +1651,41 @@ impl fmt::Display for ETuple {
 /// Describes an `STRING_EXT` term.
 ///
 /// TODO: Test proper quoting of string.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct EString(String);
 
+impl EString {
+    pub fn new<S: Into<String>>(value: S) -> EString {
+        EString(value.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl From<&str> for EString {
+    fn from(value: &str) -> EString {
+        EString(value.to_string())
+    }
+}
+
+impl From<String> for EString {
+    fn from(value: String) -> EString {
+        EString(value)
+    }
+}
+
+impl AsRef<str> for EString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
 impl fmt::Display for EString {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "\"{}\"", escape_string(&self.0))
@@ -1142,6 +1699,24 @@ pub struct EPort {
     creation: u32,
 }
 
+impl EPort {
+    pub fn new<N: Into<EAtom>>(node: N, id: u32, creation: u32) -> EPort {
+        EPort { node: node.into(), id, creation }
+    }
+
+    pub fn node(&self) -> &EAtom {
+        &self.node
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn creation(&self) -> u32 {
+        self.creation
+    }
+}
+
 impl fmt::Display for EPort {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "#Port<{}.{}>", self.node, self.id)
@@ -1156,15 +1731,131 @@ pub struct EPid {
     creation: u32,
 }
 
+impl EPid {
+    pub fn new<N: Into<EAtom>>(node: N, id: u32, serial: u32, creation: u32) -> EPid {
+        EPid { node: node.into(), id, serial, creation }
+    }
+
+    pub fn node(&self) -> &EAtom {
+        &self.node
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    pub fn creation(&self) -> u32 {
+        self.creation
+    }
+}
+
 impl fmt::Display for EPid {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "<{}.{}.{}>", self.node, self.id, self.serial)
     }
 }
 
+/// Whether two map keys are exactly equal, without encoding atoms.
+fn same_key(a: &dyn ETerm, b: &dyn ETerm) -> bool {
+    match (a.downcast_ref::<EAtom>(), b.downcast_ref::<EAtom>()) {
+        (Some(a), Some(b)) => a == b,
+        _ => order::exactly_equal(a, b).unwrap_or(false),
+    }
+}
+
 /// Describes an Erlang Map
 pub struct EMap(Vec<(Box<dyn ETerm>, Box<dyn ETerm>)>);
 
+impl EMap {
+    pub fn new(entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)>) -> EMap {
+        EMap(entries)
+    }
+
+    /// The key-value pairs of this map, in insertion order.
+    #[allow(clippy::type_complexity)]
+    pub fn as_slice(&self) -> &[(Box<dyn ETerm>, Box<dyn ETerm>)] {
+        &self.0
+    }
+
+    /// The value of `key`, which is compared to the keys of this map like
+    /// `=:=`, so `1` and `1.0` are different keys.
+    pub fn get(&self, key: &dyn ETerm) -> Option<&dyn ETerm> {
+        self.0.iter()
+            .find(|(k, _)| same_key(&**k, key))
+            .map(|(_, value)| &**value)
+    }
+
+    pub fn get_mut(&mut self, key: &dyn ETerm) -> Option<&mut Box<dyn ETerm>> {
+        self.0.iter_mut()
+            .find(|(k, _)| same_key(&**k, key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, (Box<dyn ETerm>, Box<dyn ETerm>)> {
+        self.0.iter()
+    }
+
+    /// Sets the value of `key`, returning the value it replaces.
+    ///
+    /// Keys are compared like `=:=` does, and new keys are added after the
+    /// existing ones.
+    pub fn insert<K: ETerm, V: ETerm>(&mut self, key: K, value: V) -> Option<Box<dyn ETerm>> {
+        match self.get_mut(&key) {
+            Some(existing) => Some(mem::replace(existing, Box::new(value))),
+            None => {
+                self.0.push((Box::new(key), Box::new(value)));
+                None
+            },
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<(Box<dyn ETerm>, Box<dyn ETerm>)> {
+        self.0
+    }
+}
+
+impl From<Vec<(Box<dyn ETerm>, Box<dyn ETerm>)>> for EMap {
+    fn from(entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)>) -> EMap {
+        EMap(entries)
+    }
+}
+
+impl FromIterator<(Box<dyn ETerm>, Box<dyn ETerm>)> for EMap {
+    fn from_iter<I: IntoIterator<Item = (Box<dyn ETerm>, Box<dyn ETerm>)>>(iter: I) -> EMap {
+        EMap(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for EMap {
+    type Item = (Box<dyn ETerm>, Box<dyn ETerm>);
+    type IntoIter = vec::IntoIter<(Box<dyn ETerm>, Box<dyn ETerm>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a EMap {
+    type Item = &'a (Box<dyn ETerm>, Box<dyn ETerm>);
+    type IntoIter = slice::Iter<'a, (Box<dyn ETerm>, Box<dyn ETerm>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 impl fmt::Display for EMap {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = "#{".to_string();
@@ -1180,7 +1871,48 @@ impl fmt::Display for EMap {
 }
 
 /// Describes an Erlang Binary
-struct EBinary(Vec<u8>);
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EBinary(Vec<u8>);
+
+impl EBinary {
+    pub fn new(bytes: Vec<u8>) -> EBinary {
+        EBinary(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for EBinary {
+    fn from(bytes: Vec<u8>) -> EBinary {
+        EBinary(bytes)
+    }
+}
+
+impl From<&[u8]> for EBinary {
+    fn from(bytes: &[u8]) -> EBinary {
+        EBinary(bytes.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for EBinary {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
 
 impl fmt::Display for EBinary {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -1197,9 +1929,37 @@ impl fmt::Display for EBinary {
     }
 }
 
+/// Escapes `s` to be written between `quote`s, like `io_lib:write_atom/1`
+/// does.
+fn escape_quoted(s: &str, quote: char) -> String {
+    let mut result = String::new();
+
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\x0b' => result.push_str("\\v"),
+            '\x08' => result.push_str("\\b"),
+            '\x0c' => result.push_str("\\f"),
+            '\x1b' => result.push_str("\\e"),
+            '\x7f' => result.push_str("\\d"),
+            _ if c == quote => {
+                result.push('\\');
+                result.push(c);
+            },
+            ' '..='~' | '\u{a0}'..=char::MAX => result.push(c),
+            // Other control characters are written in octal.
+            _ => result.push_str(&format!("\\{:03o}", c as u32)),
+        }
+    }
+
+    result
+}
+
 fn escape_string(s: &str) -> String {
     let mut result = String::new();
-    s.escape_default();
     for c in s.chars() {
         result.push_str(
             match c {
@@ -1232,7 +1992,7 @@ fn to_hex(c: char, len: usize) -> String {
         result.push(
             match val {
                 0..=9 => (val + 48) as u8 as char,
-                10..=15 => ((val - 10 + 65) as u8 as char),
+                10..=15 => (val - 10 + 65) as u8 as char,
                 _ => '\0',
             }
         );
@@ -1246,3 +2006,138 @@ fn to_hex(c: char, len: usize) -> String {
 
     result.chars().rev().collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constructors_and_accessors() {
+        let atom = EAtom::new("ok");
+        assert_eq!("ok", atom.as_str());
+        assert_eq!(EAtom::from("ok"), atom);
+        assert_eq!("ok".to_string(), atom.into_inner());
+
+        let string = EString::from("text".to_string());
+        assert_eq!("text", string.as_ref());
+
+        let binary = EBinary::from(&[1, 2, 3][..]);
+        assert_eq!(&[1, 2, 3], binary.as_bytes());
+        assert_eq!(vec![1, 2, 3], binary.into_inner());
+
+        let export = EExport::new("lists", "map", 2);
+        assert_eq!(("lists", "map", 2), (export.module().as_str(), export.function().as_str(), export.arity()));
+
+        let port = EPort::new("a@b", 1, 2);
+        assert_eq!(("a@b", 1, 2), (port.node().as_str(), port.id(), port.creation()));
+
+        let pid = EPid::new("a@b", 1, 2, 3);
+        assert_eq!(("a@b", 1, 2, 3), (pid.node().as_str(), pid.id(), pid.serial(), pid.creation()));
+
+        let improper = ENonProperList::new(vec![Box::new(1u8)], Box::new(EAtom::new("t")));
+        assert_eq!(1, improper.data().len());
+        assert_eq!("[1|t]", improper.to_string());
+        let (data, tail) = improper.into_parts();
+        assert_eq!((1, "t".to_string()), (data.len(), tail.to_string()));
+    }
+
+    #[test]
+    fn collections() {
+        let mut list: EList = vec![Box::new(1u8) as Box<dyn ETerm>].into_iter().collect();
+        list.push(EAtom::new("a"));
+        assert_eq!(2, list.len());
+        assert_eq!(vec!["1", "a"], list.iter().map(|t| t.to_string()).collect::<Vec<_>>());
+        assert_eq!(2, list.into_inner().len());
+        assert!(EList::new(vec![]).is_empty());
+
+        let tuple = ETuple::from((1u8, 2u16, 3u32, 4u64, 5i8, 6i16, 7i32, 8i64, 9.5f64, ENil, EAtom::new("k"), EString::new("l")));
+        assert_eq!(12, tuple.len());
+        assert_eq!("{1,2,3,4,5,6,7,8,9.5,[],k,\"l\"}", tuple.to_string());
+        assert_eq!(12, tuple.into_iter().count());
+
+        let mut map = EMap::new(vec![]);
+        assert!(map.insert(EAtom::new("a"), 1u8).is_none());
+        assert!(map.insert(2u8, 1u8).is_none());
+        assert_eq!("1", map.insert(EAtom::new("a"), 3u8).unwrap().to_string());
+        assert!(map.insert(2.0f64, 4u8).is_none());
+        assert_eq!(3, map.len());
+        assert_eq!("3", map.get(&EAtom::new("a")).unwrap().to_string());
+
+        let mut map = EMap::new(vec![]);
+        map.insert(EAtom::new("a"), 1u8);
+        assert_eq!(1, map.len());
+        for (key, value) in &map {
+            assert_eq!(("a".to_string(), "1".to_string()), (key.to_string(), value.to_string()));
+        }
+    }
+
+    #[test]
+    fn downcasting() {
+        let term: Box<dyn ETerm> = Box::new(EBinary::new(vec![1]));
+        assert!(term.is::<EBinary>());
+        assert!(!term.is::<EString>());
+        assert_eq!(Some(&[1][..]), term.downcast_ref::<EBinary>().map(EBinary::as_bytes));
+
+        let term = term.downcast::<EAtom>().unwrap_err();
+        assert_eq!(vec![1], term.downcast::<EBinary>().unwrap().into_inner());
+    }
+
+    #[test]
+    fn atom_quoting() {
+        let unquoted = [
+            "ok", "a", "z", "hello_world", "camelCase", "a1", "a@b", "node@host", "a_", "a__B@9",
+            "ß", "ÿ", "élan", "über", "aÀÞ", "andy", "ends", "ifs", "nil", "true", "undefined",
+        ];
+
+        for name in unquoted.iter() {
+            assert_eq!(*name, EAtom::new(*name).to_string(), "{}", name);
+        }
+
+        let quoted = [
+            ("", "''"),
+            ("Hello", "'Hello'"),
+            ("_", "'_'"),
+            ("_a", "'_a'"),
+            ("@a", "'@a'"),
+            ("1a", "'1a'"),
+            ("foo bar", "'foo bar'"),
+            ("a-b", "'a-b'"),
+            ("a.b", "'a.b'"),
+            ("a÷b", "'a÷b'"),
+            ("a×b", "'a×b'"),
+            ("÷", "'÷'"),
+            ("À", "'À'"),
+            ("ā", "'ā'"),
+            ("aā", "'aā'"),
+            ("日本", "'日本'"),
+            ("a\u{a0}", "'a\u{a0}'"),
+        ];
+
+        for (name, expected) in quoted.iter() {
+            assert_eq!(*expected, EAtom::new(*name).to_string(), "{}", name);
+        }
+
+        for word in RESERVED_WORDS.iter() {
+            assert!(EAtom::new(*word).needs_quotes(), "{}", word);
+            assert_eq!(format!("'{}'", word), EAtom::new(*word).to_string());
+        }
+    }
+
+    #[test]
+    fn atom_escaping() {
+        let cases = [
+            ("it's", "'it\\'s'"),
+            ("back\\slash", "'back\\\\slash'"),
+            ("\"", "'\"'"),
+            ("a\nb", "'a\\nb'"),
+            ("\r\t\x0b\x08\x0c\x1b\x7f", "'\\r\\t\\v\\b\\f\\e\\d'"),
+            ("\0\x01\x1f", "'\\000\\001\\037'"),
+            ("\u{80}\u{9f}", "'\\200\\237'"),
+            ("é ā", "'é ā'"),
+        ];
+
+        for (name, expected) in cases.iter() {
+            assert_eq!(*expected, EAtom::new(*name).to_string(), "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! Reading and writing terms on top of tokio's asynchronous I/O.
+//!
+//! Terms read and written by this module start with the [`ETF_VERSION`]
+//! byte, just like the output of `erlang:term_to_binary/1`.
+//! The [`ETermCodec`] frames those with a big-endian length prefix, which is
+//! what a BEAM port or socket opened with `{packet, N}` expects.
+//!
+//! [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+//! [`ETermCodec`]: struct.ETermCodec.html
+
+use bytes::{ Buf, BufMut, BytesMut };
+use tokio::io::{ AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt };
+use tokio_util::codec::{ Decoder, Encoder };
+
+use super::{ ETerm, TermTag, ETF_VERSION };
+use super::borrowed;
+use super::decode::DecodeOptions;
+use super::encode::EncodeOptions;
+use super::layout::{ header_size, layout };
+use super::super::error::Error;
+
+use std::convert::TryInto;
+
+/// Reads a single versioned term of at most `max_length` bytes from
+/// `reader`, not counting the version byte.
+///
+/// Only the bytes belonging to the term are consumed from the reader, so
+/// multiple terms can be read back-to-back from the same stream.
+/// Like [`borrowed::decode`], this fails on terms nested more than
+/// [`MAX_DEPTH`] levels deep.
+///
+/// [`borrowed::decode`]: ../borrowed/fn.decode.html
+/// [`MAX_DEPTH`]: ../borrowed/constant.MAX_DEPTH.html
+pub async fn read_term<R>(reader: &mut R, options: &DecodeOptions, max_length: usize) -> Result<Box<dyn ETerm>, Error>
+    where R: AsyncRead + Unpin + ?Sized
+{
+    let version = reader.read_u8().await?;
+
+    if version != ETF_VERSION {
+        return Err(Error::Message(format!("Unsupported external term format version {}", version)));
+    }
+
+    let bytes = read_term_bytes(reader, max_length).await?;
+
+    borrowed::decode(&mut bytes.as_slice())?.to_owned_with(options)
+}
+
+/// Writes `term` to `writer`, prefixed with the [`ETF_VERSION`] byte.
+///
+/// Returns the amount of bytes written.
+///
+/// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+pub async fn write_term<W, T>(writer: &mut W, term: &T) -> Result<usize, Error>
+    where W: AsyncWrite + Unpin + ?Sized,
+          T: ETerm + ?Sized
+{
+    let bytes = to_versioned_binary(term)?;
+
+    writer.write_all(&bytes).await?;
+
+    Ok(bytes.len())
+}
+
+/// Reads the raw bytes of exactly one (unversioned) encoded term, which may
+/// be at most `max_length` bytes long.
+///
+/// The term is delimited using the size information in each tag, without
+/// decoding it.
+/// Reading stops as soon as the term turns out to be longer than
+/// `max_length`.
+pub async fn read_term_bytes<R>(reader: &mut R, max_length: usize) -> Result<Vec<u8>, Error>
+    where R: AsyncRead + Unpin + ?Sized
+{
+    enum Step {
+        Terms(usize),
+        Bytes(usize),
+    }
+
+    let mut result = Vec::new();
+    let mut steps = vec![Step::Terms(1)];
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Bytes(amount) => read_into(reader, &mut result, amount, max_length).await?,
+            Step::Terms(0) => {},
+            Step::Terms(amount) => {
+                steps.push(Step::Terms(amount - 1));
+
+                read_into(reader, &mut result, 1, max_length).await?;
+
+                let tag: TermTag = result[result.len() - 1].try_into()
+                    .map_err(|_| Error::Message("Unsupported term type".to_string()))?;
+
+                let start = result.len();
+                read_into(reader, &mut result, header_size(tag), max_length).await?;
+
+                let layout = layout(tag, &result[start..])?;
+
+                // These are handled last-in, first-out.
+                steps.push(Step::Bytes(layout.trailing));
+                steps.push(Step::Terms(layout.terms));
+                steps.push(Step::Bytes(layout.leading));
+            },
+        }
+    }
+
+    Ok(result)
+}
+
+async fn read_into<R>(reader: &mut R, buffer: &mut Vec<u8>, amount: usize, max_length: usize) -> Result<(), Error>
+    where R: AsyncRead + Unpin + ?Sized
+{
+    if amount > max_length.saturating_sub(buffer.len()) {
+        return Err(Error::Message(format!("Term exceeds the maximum length of {} bytes", max_length)));
+    }
+
+    // Reading through `take` prevents a bogus length from allocating a huge
+    // buffer up front.
+    let read = reader.take(amount as u64).read_to_end(buffer).await?;
+
+    if read == amount {
+        Ok(())
+    } else {
+        Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()))
+    }
+}
+
+fn to_versioned_binary<T>(term: &T) -> Result<Vec<u8>, Error>
+    where T: ETerm + ?Sized
+{
+    term.to_external_binary_with(&EncodeOptions::default().with_version_byte(true))
+}
+
+/// A `tokio_util` codec for length-prefixed, versioned terms.
+///
+/// Every frame consists of a big-endian length of `packet` bytes, followed
+/// by that amount of bytes containing the [`ETF_VERSION`] byte and the term.
+/// This is the framing used by Erlang ports and sockets opened with
+/// `{packet, 1}`, `{packet, 2}` or `{packet, 4}`.
+///
+/// Frames longer than the [maximum frame length] are rejected before any of
+/// their bytes are buffered, so a peer cannot make the codec allocate
+/// gigabytes with a single length prefix.
+/// Frames are decoded with [`borrowed::decode`], which bounds allocations by
+/// the length of the frame and rejects terms nested more than
+/// [`MAX_DEPTH`] levels deep.
+///
+/// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+/// [maximum frame length]: #method.with_max_frame_length
+/// [`borrowed::decode`]: ../borrowed/fn.decode.html
+/// [`MAX_DEPTH`]: ../borrowed/constant.MAX_DEPTH.html
+pub struct ETermCodec {
+    packet: usize,
+    max_frame_length: usize,
+    options: DecodeOptions,
+}
+
+/// The maximum frame length of a new codec, 8 MiB.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+impl ETermCodec {
+    /// Creates a codec with a length prefix of `packet` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `packet` is not 1, 2 or 4.
+    pub fn new(packet: usize, options: DecodeOptions) -> ETermCodec {
+        assert!(packet == 1 || packet == 2 || packet == 4, "Packet size must be 1, 2 or 4, not {}", packet);
+
+        ETermCodec {
+            packet,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            options,
+        }
+    }
+
+    /// The amount of bytes used for the length prefix of every frame.
+    pub fn packet(&self) -> usize {
+        self.packet
+    }
+
+    /// The largest frame that will be encoded or decoded.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// Sets the largest frame that will be encoded or decoded, not counting
+    /// its length prefix.
+    ///
+    /// Frames with a larger length fail to decode, and terms that would need
+    /// one fail to encode.
+    /// Defaults to [`DEFAULT_MAX_FRAME_LENGTH`].
+    ///
+    /// [`DEFAULT_MAX_FRAME_LENGTH`]: constant.DEFAULT_MAX_FRAME_LENGTH.html
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> ETermCodec {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+}
+
+impl Default for ETermCodec {
+    fn default() -> ETermCodec {
+        ETermCodec::new(4, DecodeOptions::default())
+    }
+}
+
+impl Decoder for ETermCodec {
+    type Item = Box<dyn ETerm>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Box<dyn ETerm>>, Error> {
+        if src.len() < self.packet {
+            return Ok(None);
+        }
+
+        let mut length = [0u8; 8];
+        length[8 - self.packet..].copy_from_slice(&src[..self.packet]);
+        let length = u64::from_be_bytes(length);
+
+        if length > self.max_frame_length as u64 {
+            return Err(Error::Message(format!("Frame of {} bytes exceeds the maximum frame length of {} bytes", length, self.max_frame_length)));
+        }
+
+        let length = length as usize;
+
+        if src.len() < self.packet + length {
+            src.reserve(self.packet + length - src.len());
+            return Ok(None);
+        }
+
+        src.advance(self.packet);
+        let frame = src.split_to(length);
+
+        match frame.split_first() {
+            Some((&ETF_VERSION, mut data)) => {
+                let term = borrowed::decode(&mut data)?.to_owned_with(&self.options)?;
+
+                if data.is_empty() {
+                    Ok(Some(term))
+                } else {
+                    Err(Error::Message(format!("Frame contains {} bytes after the term", data.len())))
+                }
+            },
+            Some((version, _)) =>
+                Err(Error::Message(format!("Unsupported external term format version {}", version))),
+            None => Err(Error::Message("Received an empty frame".to_string())),
+        }
+    }
+}
+
+impl<'a, T> Encoder<&'a T> for ETermCodec
+    where T: ETerm + ?Sized
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: &'a T, dst: &mut BytesMut) -> Result<(), Error> {
+        // The version byte and the term.
+        let size = 1 + item.encoded_size()?;
+
+        if size as u64 >= 1u64 << (8 * self.packet) {
+            return Err(Error::Message(format!("Term of {} bytes does not fit in a {} byte packet", size, self.packet)));
+        }
+
+        if size > self.max_frame_length {
+            return Err(Error::Message(format!("Frame of {} bytes exceeds the maximum frame length of {} bytes", size, self.max_frame_length)));
+        }
+
+        let start = dst.len();
+        dst.reserve(self.packet + size);
+        dst.put_slice(&(size as u64).to_be_bytes()[8 - self.packet..]);
+        dst.put_u8(ETF_VERSION);
+
+        let result = item.to_writer(&mut dst.writer()).and_then(|written| match written + 1 {
+            written if written == size => Ok(()),
+            written => Err(Error::Message(format!("Term of {} bytes was encoded in {} bytes", size, written))),
+        });
+
+        if result.is_err() {
+            // Leave no partial frame behind to corrupt the stream.
+            dst.truncate(start);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{ EAtom, ETuple };
+
+    fn sample() -> ETuple {
+        ETuple(vec![
+            Box::new(EAtom("ok".to_string())),
+            Box::new(1024i32),
+            Box::new(ETuple(vec![Box::new(7u8)])),
+        ])
+    }
+
+    #[tokio::test]
+    async fn write_then_read() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let written = write_term(&mut buffer, &sample()).await.unwrap();
+        write_term(&mut buffer, &EAtom("next".to_string())).await.unwrap();
+
+        assert_eq!(&[ETF_VERSION, TermTag::SmallTuple as u8, 3], &buffer[..3]);
+        assert_eq!(written, 1 + 2 + 4 + 5 + 4);
+
+        let mut reader = buffer.as_slice();
+        let options = DecodeOptions::default();
+        assert_eq!("{ok,1024,{7}}", read_term(&mut reader, &options, 15).await.unwrap().to_string());
+        assert_eq!("next", read_term(&mut reader, &options, 1024).await.unwrap().to_string());
+        assert!(reader.is_empty());
+    }
+
+    #[tokio::test]
+    async fn read_term_limits() {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_term(&mut buffer, &sample()).await.unwrap();
+
+        let error = read_term(&mut buffer.as_slice(), &DecodeOptions::default(), 14).await.unwrap_err();
+        assert_eq!("Term exceeds the maximum length of 14 bytes", error.to_string());
+
+        let binary = [ETF_VERSION, TermTag::Binary as u8, 0xff, 0xff, 0xff, 0xf0];
+        assert!(read_term(&mut &binary[..], &DecodeOptions::default(), 1024).await.is_err());
+
+        let mut nested = vec![ETF_VERSION];
+        for _ in 0..100_000 {
+            nested.extend_from_slice(&[TermTag::List as u8, 0, 0, 0, 1]);
+        }
+        nested.resize(nested.len() + 100_001, TermTag::Nil as u8);
+
+        let error = read_term(&mut nested.as_slice(), &DecodeOptions::default(), nested.len()).await.unwrap_err();
+        assert_eq!("Terms are nested more than 512 levels deep", error.to_string());
+    }
+
+    #[tokio::test]
+    async fn truncated_term() {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_term(&mut buffer, &sample()).await.unwrap();
+        buffer.pop();
+
+        assert!(read_term(&mut buffer.as_slice(), &DecodeOptions::default(), 1024).await.is_err());
+    }
+
+    #[test]
+    fn codec_round_trip() {
+        let mut codec = ETermCodec::new(2, DecodeOptions::default());
+        let mut buffer = BytesMut::new();
+
+        codec.encode(&sample(), &mut buffer).unwrap();
+        codec.encode(&EAtom("next".to_string()) as &dyn ETerm, &mut buffer).unwrap();
+        assert_eq!(&[0, 16, ETF_VERSION], &buffer[..3]);
+
+        let mut partial = buffer.split_to(10);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(buffer);
+
+        assert_eq!("{ok,1024,{7}}", codec.decode(&mut partial).unwrap().unwrap().to_string());
+        assert_eq!("next", codec.decode(&mut partial).unwrap().unwrap().to_string());
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+    }
+
+    #[test]
+    fn codec_rejects_oversized_terms() {
+        let mut codec = ETermCodec::new(1, DecodeOptions::default());
+        let term = EAtom("a".repeat(300));
+
+        assert!(codec.encode(&term, &mut BytesMut::new()).is_err());
+    }
+
+    #[test]
+    fn codec_discards_partial_frames() {
+        use super::super::encode::{ EncodeOptions, ToExternalBinary };
+        use std::fmt;
+        use std::io::Write;
+
+        /// A term that fails halfway through encoding.
+        struct Unencodable;
+
+        impl ToExternalBinary for Unencodable {
+            fn to_writer_with(&self, writer: &mut dyn Write, _options: &EncodeOptions) -> Result<usize, Error> {
+                writer.write_all(&[TermTag::SmallTuple as u8, 1])?;
+
+                Err(Error::Message("Unencodable".to_string()))
+            }
+
+            fn encoded_size_with(&self, _options: &EncodeOptions) -> Result<usize, Error> {
+                Ok(4)
+            }
+        }
+
+        impl fmt::Display for Unencodable {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("unencodable")
+            }
+        }
+
+        let mut codec = ETermCodec::new(2, DecodeOptions::default());
+        let mut buffer = BytesMut::new();
+
+        codec.encode(&EAtom("ok".to_string()), &mut buffer).unwrap();
+        assert!(codec.encode(&Unencodable, &mut buffer).is_err());
+        codec.encode(&EAtom("next".to_string()), &mut buffer).unwrap();
+
+        assert_eq!("ok", codec.decode(&mut buffer).unwrap().unwrap().to_string());
+        assert_eq!("next", codec.decode(&mut buffer).unwrap().unwrap().to_string());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn codec_rejects_long_frames() {
+        let mut codec = ETermCodec::default().with_max_frame_length(16);
+        let mut buffer = BytesMut::from(&[255, 255, 255, 255][..]);
+
+        assert!(codec.decode(&mut buffer).is_err());
+        assert!(buffer.capacity() < 16);
+
+        let mut buffer = BytesMut::new();
+        codec.encode(&sample(), &mut buffer).unwrap();
+        assert!(codec.decode(&mut buffer).unwrap().is_some());
+
+        let error = codec.encode(&EAtom("a".repeat(16)), &mut buffer).unwrap_err();
+        assert_eq!("Frame of 19 bytes exceeds the maximum frame length of 16 bytes", error.to_string());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn codec_rejects_hostile_frames() {
+        let mut codec = ETermCodec::default();
+        let mut buffer = BytesMut::from(&[0, 0, 0, 6, ETF_VERSION, TermTag::Binary as u8, 0xff, 0xff, 0xff, 0xf0][..]);
+
+        assert!(codec.decode(&mut buffer).is_err());
+
+        let mut frame = vec![ETF_VERSION];
+        for _ in 0..200_000 {
+            frame.extend_from_slice(&[TermTag::List as u8, 0, 0, 0, 1]);
+        }
+        frame.resize(frame.len() + 200_001, TermTag::Nil as u8);
+
+        let mut buffer = BytesMut::from(&(frame.len() as u32).to_be_bytes()[..]);
+        buffer.extend_from_slice(&frame);
+
+        let error = codec.decode(&mut buffer).unwrap_err();
+        assert_eq!("Terms are nested more than 512 levels deep", error.to_string());
+    }
+
+    #[test]
+    fn codec_rejects_unversioned_frames() {
+        let mut codec = ETermCodec::default();
+        let mut buffer = BytesMut::from(&[0, 0, 0, 2, TermTag::SmallInteger as u8, 1][..]);
+
+        assert!(codec.decode(&mut buffer).is_err());
+    }
+}
@@ -0,0 +1,444 @@
+//! Zero-copy decoding of terms from a byte slice.
+//!
+//! Decoding with [`decode`] copies every binary, atom and string into its
+//! owned term type.
+//! For large messages that copying dominates, so this module decodes into a
+//! [`BorrowedTerm`], which refers to those parts of the input in place.
+//! A [`BorrowedTerm`] can be converted into the owned term types with
+//! [`BorrowedTerm::to_owned`] whenever that is necessary.
+//!
+//! [`decode`]: ../decode/fn.decode.html
+//! [`BorrowedTerm`]: enum.BorrowedTerm.html
+//! [`BorrowedTerm::to_owned`]: enum.BorrowedTerm.html#method.to_owned
+
+use super::{
+    EAtom,
+    EBinary,
+    EExport,
+    ENil,
+    EPid,
+    EPort,
+    ETerm,
+    ETuple,
+    EList,
+    EMap,
+    EString,
+    TermTag,
+};
+use super::decode::{ check_finite, list_to_term, num_vec_to_term, parse_float_ext, DecodeOptions };
+use super::super::error::Error;
+
+use std::borrow::Cow;
+use std::convert::{ TryFrom, TryInto };
+use std::io;
+use std::str;
+
+/// A decoded term that borrows binaries, atoms and strings from the buffer it
+/// was decoded from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BorrowedTerm<'a> {
+    /// A `SMALL_INTEGER_EXT` or `INTEGER_EXT`.
+    Integer(i32),
+    /// A `SMALL_BIG_EXT` or `LARGE_BIG_EXT`, with its digits stored
+    /// least-significant byte first.
+    BigInteger { negative: bool, digits: &'a [u8] },
+    /// A `NEW_FLOAT_EXT` or `FLOAT_EXT`.
+    Float(f64),
+    /// Any of the atom encodings.
+    ///
+    /// LATIN-1 atoms are only copied when they contain non-ASCII characters.
+    Atom(Cow<'a, str>),
+    /// A `NIL_EXT`.
+    Nil,
+    /// A `STRING_EXT`, which is a list of bytes.
+    String(&'a [u8]),
+    /// A `LIST_EXT` with its elements and its tail.
+    List(Vec<BorrowedTerm<'a>>, Box<BorrowedTerm<'a>>),
+    /// A `SMALL_TUPLE_EXT` or `LARGE_TUPLE_EXT`.
+    Tuple(Vec<BorrowedTerm<'a>>),
+    /// A `MAP_EXT`, with its pairs in encoded order.
+    Map(Vec<(BorrowedTerm<'a>, BorrowedTerm<'a>)>),
+    /// A `BINARY_EXT`.
+    Binary(&'a [u8]),
+    /// An `EXPORT_EXT`.
+    Export { module: Cow<'a, str>, function: Cow<'a, str>, arity: u8 },
+    /// A `PID_EXT` or `NEW_PID_EXT`.
+    Pid { node: Cow<'a, str>, id: u32, serial: u32, creation: u32 },
+    /// A `PORT_EXT` or `NEW_PORT_EXT`.
+    Port { node: Cow<'a, str>, id: u32, creation: u32 },
+}
+
+impl<'a> BorrowedTerm<'a> {
+    /// Converts this term into the owned term types, interpreting lists and
+    /// strings like [`decode`] does with the default options.
+    ///
+    /// [`decode`]: ../decode/fn.decode.html
+    pub fn to_owned(&self) -> Result<Box<dyn ETerm>, Error> {
+        self.to_owned_with(&DecodeOptions::default())
+    }
+
+    /// Converts this term into the owned term types, interpreting lists and
+    /// strings like [`decode`] does with `options`.
+    ///
+    /// [`decode`]: ../decode/fn.decode.html
+    pub fn to_owned_with(&self, options: &DecodeOptions) -> Result<Box<dyn ETerm>, Error> {
+        match self {
+            BorrowedTerm::Integer(i) => Ok(match u8::try_from(*i) {
+                Ok(byte) => Box::from(byte) as Box<dyn ETerm>,
+                Err(_) => Box::from(*i),
+            }),
+            BorrowedTerm::BigInteger { negative, digits } => num_vec_to_term(*negative, digits),
+            BorrowedTerm::Float(f) => Ok(Box::from(*f)),
+            BorrowedTerm::Atom(name) => Ok(Box::from(EAtom(name.to_string()))),
+            BorrowedTerm::Nil => Ok(Box::from(ENil)),
+            BorrowedTerm::String(bytes) => {
+                if options.read_string_ext_as_list {
+                    str::from_utf8(bytes)
+                        .map(|s| Box::from(EString(s.to_string())) as Box<dyn ETerm>)
+                        .map_err(|e| Error::Message(e.to_string()))
+                } else {
+                    Ok(Box::from(EList(bytes.iter().map(|&b| Box::from(b) as Box<dyn ETerm>).collect())))
+                }
+            },
+            BorrowedTerm::List(elements, tail) => list_to_term(
+                to_owned_all(elements, options)?,
+                tail.to_owned_with(options)?,
+                options,
+            ),
+            BorrowedTerm::Tuple(elements) => Ok(Box::from(ETuple(to_owned_all(elements, options)?))),
+            BorrowedTerm::Map(pairs) => {
+                let mut result = Vec::with_capacity(pairs.len());
+
+                for (k, v) in pairs {
+                    result.push((k.to_owned_with(options)?, v.to_owned_with(options)?));
+                }
+
+                Ok(Box::from(EMap(result)))
+            },
+            BorrowedTerm::Binary(bytes) => Ok(Box::from(EBinary(bytes.to_vec()))),
+            BorrowedTerm::Export { module, function, arity } => Ok(Box::from(EExport {
+                module: EAtom(module.to_string()),
+                function: EAtom(function.to_string()),
+                arity: *arity,
+            })),
+            BorrowedTerm::Pid { node, id, serial, creation } => Ok(Box::from(EPid {
+                node: EAtom(node.to_string()),
+                id: *id,
+                serial: *serial,
+                creation: *creation,
+            })),
+            BorrowedTerm::Port { node, id, creation } => Ok(Box::from(EPort {
+                node: EAtom(node.to_string()),
+                id: *id,
+                creation: *creation,
+            })),
+        }
+    }
+}
+
+fn to_owned_all(terms: &[BorrowedTerm], options: &DecodeOptions) -> Result<Vec<Box<dyn ETerm>>, Error> {
+    terms.iter().map(|t| t.to_owned_with(options)).collect()
+}
+
+/// The deepest nesting of lists, tuples and maps that [`decode`] accepts.
+///
+/// Decoding is recursive, so without a limit a small, crafted input of
+/// nested tuples could overflow the stack, and so could everything that
+/// walks the decoded term.
+///
+/// [`decode`]: fn.decode.html
+pub const MAX_DEPTH: usize = 512;
+
+/// Decodes a single (unversioned) term from the start of `input`, advancing
+/// `input` past it.
+///
+/// Fails when terms are nested deeper than [`MAX_DEPTH`].
+///
+/// [`MAX_DEPTH`]: constant.MAX_DEPTH.html
+pub fn decode<'a>(input: &mut &'a [u8]) -> Result<BorrowedTerm<'a>, Error> {
+    decode_nested(input, 0)
+}
+
+fn decode_nested<'a>(input: &mut &'a [u8], depth: usize) -> Result<BorrowedTerm<'a>, Error> {
+    let tag: TermTag = read_u8(input)?
+        .try_into()
+        .map_err(|_| Error::Message("Unsupported term type".to_string()))?;
+
+    if depth == MAX_DEPTH && matches!(tag, TermTag::List | TermTag::SmallTuple | TermTag::LargeTuple | TermTag::Map) {
+        return Err(Error::Message(format!("Terms are nested more than {} levels deep", MAX_DEPTH)));
+    }
+
+    match tag {
+        TermTag::List => decode_list(input, depth + 1),
+        TermTag::SmallTuple => {
+            let len = read_u8(input)? as usize;
+            Ok(BorrowedTerm::Tuple(decode_all(input, len, depth + 1)?))
+        },
+        TermTag::LargeTuple => {
+            let len = read_u32(input)? as usize;
+            Ok(BorrowedTerm::Tuple(decode_all(input, len, depth + 1)?))
+        },
+        TermTag::Map => decode_map(input, depth + 1),
+        _ => decode_leaf(input, tag),
+    }
+}
+
+fn decode_list<'a>(input: &mut &'a [u8], depth: usize) -> Result<BorrowedTerm<'a>, Error> {
+    let len = read_u32(input)? as usize;
+    let elements = decode_all(input, len, depth)?;
+    let tail = decode_nested(input, depth)?;
+
+    Ok(BorrowedTerm::List(elements, Box::new(tail)))
+}
+
+fn decode_map<'a>(input: &mut &'a [u8], depth: usize) -> Result<BorrowedTerm<'a>, Error> {
+    let len = read_u32(input)? as usize;
+    // Every pair takes at least two bytes.
+    let mut pairs = Vec::with_capacity(std::cmp::min(len, input.len() / 2));
+
+    for _ in 0..len {
+        pairs.push((decode_nested(input, depth)?, decode_nested(input, depth)?));
+    }
+
+    Ok(BorrowedTerm::Map(pairs))
+}
+
+/// Decodes a term that contains no other terms.
+///
+/// This is kept out of `decode_nested`, as its many cases would otherwise
+/// take up stack space at every level of nesting.
+fn decode_leaf<'a>(input: &mut &'a [u8], tag: TermTag) -> Result<BorrowedTerm<'a>, Error> {
+    match tag {
+        TermTag::SmallInteger => Ok(BorrowedTerm::Integer(read_u8(input)?.into())),
+        TermTag::Integer => Ok(BorrowedTerm::Integer(i32::from_be_bytes(read_array(input)?))),
+        TermTag::SmallBig => {
+            let len = read_u8(input)? as usize;
+            let negative = read_u8(input)? != 0;
+            Ok(BorrowedTerm::BigInteger { negative, digits: take(input, len)? })
+        },
+        TermTag::LargeBig => {
+            let len = read_u32(input)? as usize;
+            let negative = read_u8(input)? != 0;
+            Ok(BorrowedTerm::BigInteger { negative, digits: take(input, len)? })
+        },
+        TermTag::NewFloat => Ok(BorrowedTerm::Float(check_finite(f64::from_be_bytes(read_array(input)?))?)),
+        TermTag::Float => Ok(BorrowedTerm::Float(parse_float_ext(take(input, 31)?)?)),
+        TermTag::SmallAtom | TermTag::SmallAtomUtf8 | TermTag::Atom | TermTag::AtomUtf8 =>
+            Ok(BorrowedTerm::Atom(read_atom_body(input, tag)?)),
+        TermTag::Nil => Ok(BorrowedTerm::Nil),
+        TermTag::String => {
+            let len = read_u16(input)? as usize;
+            Ok(BorrowedTerm::String(take(input, len)?))
+        },
+        TermTag::Binary => {
+            let len = read_u32(input)? as usize;
+            Ok(BorrowedTerm::Binary(take(input, len)?))
+        },
+        TermTag::Export => {
+            let module = read_atom(input)?;
+            let function = read_atom(input)?;
+
+            match read_u8(input)?.try_into() {
+                Ok(TermTag::SmallInteger) => Ok(BorrowedTerm::Export { module, function, arity: read_u8(input)? }),
+                _ => Err(Error::Message("The arity of an export must be a small integer".to_string())),
+            }
+        },
+        TermTag::Pid | TermTag::NewPid => {
+            let node = read_atom(input)?;
+            let id = read_u32(input)?;
+            let serial = read_u32(input)?;
+            let creation = if tag == TermTag::Pid { read_u8(input)?.into() } else { read_u32(input)? };
+
+            Ok(BorrowedTerm::Pid { node, id, serial, creation })
+        },
+        TermTag::Port | TermTag::NewPort => {
+            let node = read_atom(input)?;
+            let id = read_u32(input)?;
+            let creation = if tag == TermTag::Port { read_u8(input)?.into() } else { read_u32(input)? };
+
+            Ok(BorrowedTerm::Port { node, id, creation })
+        },
+        _ => Err(Error::Message("Decoding of this term type not yet supported".to_string())),
+    }
+}
+
+fn decode_all<'a>(input: &mut &'a [u8], length: usize, depth: usize) -> Result<Vec<BorrowedTerm<'a>>, Error> {
+    // Every term takes at least one byte, which bounds the preallocation for
+    // bogus lengths.
+    let mut result = Vec::with_capacity(std::cmp::min(length, input.len()));
+
+    for _ in 0..length {
+        result.push(decode_nested(input, depth)?);
+    }
+
+    Ok(result)
+}
+
+fn read_atom<'a>(input: &mut &'a [u8]) -> Result<Cow<'a, str>, Error> {
+    match read_u8(input)?.try_into() {
+        Ok(tag @ TermTag::SmallAtom)
+        | Ok(tag @ TermTag::SmallAtomUtf8)
+        | Ok(tag @ TermTag::Atom)
+        | Ok(tag @ TermTag::AtomUtf8) => read_atom_body(input, tag),
+        _ => Err(Error::Message("Expected an atom".to_string())),
+    }
+}
+
+fn read_atom_body<'a>(input: &mut &'a [u8], tag: TermTag) -> Result<Cow<'a, str>, Error> {
+    let len = match tag {
+        TermTag::SmallAtom | TermTag::SmallAtomUtf8 => read_u8(input)? as usize,
+        _ => read_u16(input)? as usize,
+    };
+    let bytes = take(input, len)?;
+
+    match tag {
+        TermTag::SmallAtomUtf8 | TermTag::AtomUtf8 => str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|e| Error::Message(e.to_string())),
+        _ if bytes.is_ascii() => Ok(Cow::Borrowed(str::from_utf8(bytes).expect("ASCII is valid UTF-8"))),
+        _ => Ok(Cow::Owned(bytes.iter().map(|&c| c as char).collect())),
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], amount: usize) -> Result<&'a [u8], Error> {
+    if input.len() < amount {
+        return Err(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    let (head, tail) = input.split_at(amount);
+    *input = tail;
+
+    Ok(head)
+}
+
+fn read_array<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], Error> {
+    Ok(take(input, N)?.try_into().expect("Slice has the requested length"))
+}
+
+fn read_u8(input: &mut &[u8]) -> Result<u8, Error> {
+    Ok(take(input, 1)?[0])
+}
+
+fn read_u16(input: &mut &[u8]) -> Result<u16, Error> {
+    Ok(u16::from_be_bytes(read_array(input)?))
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32, Error> {
+    Ok(u32::from_be_bytes(read_array(input)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::decode;
+
+    fn decode_both(bytes: &[u8]) -> (BorrowedTerm<'_>, String) {
+        let mut input = bytes;
+        let borrowed = decode(&mut input).unwrap();
+        assert!(input.is_empty(), "Not all input was consumed");
+
+        let owned = decode::decode(&mut &bytes[..], &DecodeOptions::default()).unwrap();
+
+        (borrowed, owned.to_string())
+    }
+
+    #[test]
+    fn binaries_are_borrowed() {
+        let bytes = [109, 0, 0, 0, 3, 1, 2, 3];
+        let (term, owned) = decode_both(&bytes);
+
+        match term {
+            BorrowedTerm::Binary(data) => {
+                assert_eq!(&[1, 2, 3], data);
+                assert_eq!(bytes[5..].as_ptr(), data.as_ptr());
+            },
+            other => panic!("Unexpected term {:?}", other),
+        }
+
+        assert_eq!(owned, term.to_owned().unwrap().to_string());
+    }
+
+    #[test]
+    fn atoms() {
+        let utf8 = [119, 2, 0xc3, 0xa9];
+        assert_eq!(BorrowedTerm::Atom(Cow::Borrowed("é")), decode(&mut &utf8[..]).unwrap());
+
+        let latin1 = [115, 1, 0xe9];
+        match decode(&mut &latin1[..]).unwrap() {
+            BorrowedTerm::Atom(Cow::Owned(name)) => assert_eq!("é", name),
+            other => panic!("Unexpected term {:?}", other),
+        }
+
+        let ascii = [100, 0, 2, b'o', b'k'];
+        match decode(&mut &ascii[..]).unwrap() {
+            BorrowedTerm::Atom(Cow::Borrowed(name)) => assert_eq!("ok", name),
+            other => panic!("Unexpected term {:?}", other),
+        }
+
+        assert!(decode(&mut &[119, 1, 0xff][..]).is_err());
+    }
+
+    #[test]
+    fn nested_terms_match_owned_decoding() {
+        for bytes in &[
+            // {ok, [1, 2, 3], #{a => 300}}
+            vec![104, 3, 119, 2, b'o', b'k', 107, 0, 3, 1, 2, 3, 116, 0, 0, 0, 1, 119, 1, b'a', 98, 0, 0, 1, 44],
+            // [1, ok | 2]
+            vec![108, 0, 0, 0, 2, 97, 1, 119, 2, b'o', b'k', 97, 2],
+            // "hi" as a LIST_EXT
+            vec![108, 0, 0, 0, 2, 97, b'h', 97, b'i', 106],
+            // -(2^64)
+            vec![110, 9, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            // 2^32 - 1
+            vec![110, 4, 0, 255, 255, 255, 255],
+        ] {
+            let (term, owned) = decode_both(bytes);
+            assert_eq!(owned, term.to_owned().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn pids_and_ports() {
+        let pid = [88, 119, 1, b'n', 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3];
+        assert_eq!(
+            BorrowedTerm::Pid { node: Cow::Borrowed("n"), id: 1, serial: 2, creation: 3 },
+            decode(&mut &pid[..]).unwrap(),
+        );
+
+        let port = [102, 119, 1, b'n', 0, 0, 0, 1, 3];
+        assert_eq!(
+            BorrowedTerm::Port { node: Cow::Borrowed("n"), id: 1, creation: 3 },
+            decode(&mut &port[..]).unwrap(),
+        );
+    }
+
+    #[test]
+    fn truncated_input() {
+        let bytes = [104, 2, 97, 1];
+        assert!(decode(&mut &bytes[..]).is_err());
+
+        let huge = [108, 255, 255, 255, 255, 106];
+        assert!(decode(&mut &huge[..]).is_err());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| {
+            let mut bytes = [104, 1].repeat(depth);
+            bytes.push(106);
+            bytes
+        };
+
+        let bytes = nested(MAX_DEPTH);
+        let term = decode(&mut bytes.as_slice()).unwrap();
+        assert!(term.to_owned().is_ok());
+
+        for depth in [MAX_DEPTH + 1, 1_000_000] {
+            let bytes = nested(depth);
+            assert_eq!(
+                format!("Terms are nested more than {} levels deep", MAX_DEPTH),
+                decode(&mut bytes.as_slice()).unwrap_err().to_string(),
+            );
+        }
+    }
+}
@@ -0,0 +1,576 @@
+//! Converting decoded terms into Rust values.
+//!
+//! [`decode`] produces a `Box<dyn ETerm>`, from which [`FromETerm`] extracts
+//! a value of a specific type, failing with a descriptive error if the term
+//! has a different shape.
+//!
+//! | Rust type                        | Accepted terms                                       |
+//! |----------------------------------|------------------------------------------------------|
+//! | `i8` to `i128`, `u8` to `u128`   | Any integer that fits                                |
+//! | `f32`, `f64`                     | Floats                                               |
+//! | `bool`                           | The atoms `true` and `false`                         |
+//! | `String`                         | Strings, lists of code points and UTF-8 binaries     |
+//! | `Vec<T>`                         | Proper lists, including strings                      |
+//! | `HashMap<K, V>`, `BTreeMap<K, V>`| Maps                                                 |
+//! | `()`                             | The atom `ok`, or `nil` for Gleam                    |
+//! | `char`                           | Integers that are Unicode code points                |
+//! | `Option<T>`                      | `undefined`, `nil` or `none` for `None`, or a `T`    |
+//! | `Result<T, E>`                   | `{ok, Value}` and `{error, Reason}`                  |
+//! | Tuples of up to 12 elements      | Tuples of the same arity                             |
+//! | [`EAtom`], [`EString`], [`EBinary`], [`ENil`] | The term itself                         |
+//!
+//! The types that do not have type parameters can also be converted with
+//! `TryFrom<&dyn ETerm>`.
+//!
+//! ```
+//! use rust_eterm::terms::{ EAtom, ETerm, FromETerm };
+//! use std::collections::HashMap;
+//! use std::convert::TryFrom;
+//!
+//! let term: Box<dyn ETerm> = Box::new(EAtom::new("ok"));
+//! assert_eq!("ok", EAtom::from_eterm(&*term).unwrap().as_str());
+//! assert!(rust_eterm::terms::EBinary::from_eterm(&*term).is_err());
+//!
+//! let term = rust_eterm::eterm!(#{"one" => 1, "two" => 2});
+//! let map = HashMap::<String, i64>::from_eterm(&term).unwrap();
+//! assert_eq!(Some(&2), map.get("two"));
+//!
+//! assert_eq!(300u16, u16::try_from(&300i32 as &dyn ETerm).unwrap());
+//! assert!(u8::try_from(&300i32 as &dyn ETerm).is_err());
+//! ```
+//!
+//! [`decode`]: ../decode/fn.decode.html
+//! [`FromETerm`]: trait.FromETerm.html
+//! [`EAtom`]: ../struct.EAtom.html
+//! [`EString`]: ../struct.EString.html
+//! [`EBinary`]: ../struct.EBinary.html
+//! [`ENil`]: ../struct.ENil.html
+
+#[cfg(feature="bigint")]
+use {
+    num_bigint::{ BigInt, BigUint },
+    num_traits::cast::ToPrimitive,
+};
+
+use super::{ EAtom, EBinary, EList, ENil, ETerm, ETuple, EString };
+use super::decode::DecodeOptions;
+use super::super::error::Error;
+
+use std::char;
+use std::collections::{ BTreeMap, HashMap };
+use std::convert::TryFrom;
+use std::hash::Hash;
+
+/// A type that can be extracted from a term.
+pub trait FromETerm: Sized {
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<Self, Error>;
+
+    fn from_eterm(term: &dyn ETerm) -> Result<Self, Error> {
+        Self::from_eterm_with(term, &DecodeOptions::default())
+    }
+}
+
+/// The error for a term that is not the `expected` kind of value.
+pub fn mismatch(expected: &str, term: &dyn ETerm) -> Error {
+    Error::Message(format!("Expected {}, got {}", expected, term))
+}
+
+macro_rules! from_term_struct {
+    ($($t:ident => $description:expr),*) => {
+        $(
+            impl FromETerm for $t {
+                fn from_eterm_with(term: &dyn ETerm, _options: &DecodeOptions) -> Result<$t, Error> {
+                    term.downcast_ref::<$t>()
+                        .cloned()
+                        .ok_or_else(|| mismatch($description, term))
+                }
+            }
+        )*
+    };
+}
+
+from_term_struct!(
+    ENil => "nil",
+    EAtom => "an atom",
+    EString => "a string",
+    EBinary => "a binary"
+);
+
+/// The value of an integer term, whichever type it was decoded as.
+pub(crate) enum Integer {
+    Signed(i128),
+    Unsigned(u128),
+    /// An integer that does not fit in 128 bits.
+    #[cfg(feature="bigint")]
+    Big,
+}
+
+pub(crate) fn integer(term: &dyn ETerm) -> Option<Integer> {
+    macro_rules! try_integer {
+        ($variant:ident: $($t:ty),*) => {
+            $(
+                if let Some(&value) = term.downcast_ref::<$t>() {
+                    return Some(Integer::$variant(value as _));
+                }
+            )*
+        };
+    }
+
+    try_integer!(Unsigned: u8, u16, u32, u64, u128, usize);
+    try_integer!(Signed: i8, i16, i32, i64, i128, isize);
+
+    #[cfg(feature="bigint")]
+    {
+        if let Some(value) = term.downcast_ref::<BigInt>() {
+            return Some(value.to_i128().map(Integer::Signed)
+                .or_else(|| value.to_u128().map(Integer::Unsigned))
+                .unwrap_or(Integer::Big));
+        }
+
+        if let Some(value) = term.downcast_ref::<BigUint>() {
+            return Some(value.to_u128().map(Integer::Unsigned).unwrap_or(Integer::Big));
+        }
+    }
+
+    None
+}
+
+macro_rules! from_term_integer {
+    ($($t:ty),*) => {
+        $(
+            impl FromETerm for $t {
+                fn from_eterm_with(term: &dyn ETerm, _options: &DecodeOptions) -> Result<$t, Error> {
+                    let out_of_range = || Error::Message(format!("Integer {} does not fit in a {}", term, stringify!($t)));
+
+                    match integer(term) {
+                        Some(Integer::Signed(value)) => <$t>::try_from(value).map_err(|_| out_of_range()),
+                        Some(Integer::Unsigned(value)) => <$t>::try_from(value).map_err(|_| out_of_range()),
+                        #[cfg(feature="bigint")]
+                        Some(Integer::Big) => Err(out_of_range()),
+                        None => Err(mismatch("an integer", term)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_term_integer!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
+
+#[cfg(feature="bigint")]
+impl FromETerm for BigInt {
+    fn from_eterm_with(term: &dyn ETerm, _options: &DecodeOptions) -> Result<BigInt, Error> {
+        if let Some(value) = term.downcast_ref::<BigInt>() {
+            return Ok(value.clone());
+        }
+
+        if let Some(value) = term.downcast_ref::<BigUint>() {
+            return Ok(BigInt::from(value.clone()));
+        }
+
+        match integer(term) {
+            Some(Integer::Signed(value)) => Ok(BigInt::from(value)),
+            Some(Integer::Unsigned(value)) => Ok(BigInt::from(value)),
+            _ => Err(mismatch("an integer", term)),
+        }
+    }
+}
+
+#[cfg(feature="bigint")]
+impl FromETerm for BigUint {
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<BigUint, Error> {
+        BigInt::from_eterm_with(term, options)?
+            .to_biguint()
+            .ok_or_else(|| mismatch("a non-negative integer", term))
+    }
+}
+
+impl FromETerm for f64 {
+    fn from_eterm_with(term: &dyn ETerm, _options: &DecodeOptions) -> Result<f64, Error> {
+        if let Some(&value) = term.downcast_ref::<f32>() {
+            return Ok(value.into());
+        }
+
+        term.downcast_ref::<f64>()
+            .copied()
+            .ok_or_else(|| mismatch("a float", term))
+    }
+}
+
+impl FromETerm for f32 {
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<f32, Error> {
+        let value = f64::from_eterm_with(term, options)? as f32;
+
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(Error::Message(format!("Float {} does not fit in a f32", term)))
+        }
+    }
+}
+
+impl FromETerm for bool {
+    fn from_eterm_with(term: &dyn ETerm, _options: &DecodeOptions) -> Result<bool, Error> {
+        match term.downcast_ref::<EAtom>().map(EAtom::as_str) {
+            Some("true") => Ok(true),
+            Some("false") => Ok(false),
+            _ => Err(mismatch("a boolean", term)),
+        }
+    }
+}
+
+impl FromETerm for String {
+    /// Accepts strings, lists of Unicode code points (like Erlang's strings)
+    /// and binaries that contain UTF-8 (like Elixir's strings).
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<String, Error> {
+        if let Some(string) = term.downcast_ref::<EString>() {
+            return Ok(string.as_str().to_string());
+        }
+
+        if term.is::<ENil>() {
+            return Ok(String::new());
+        }
+
+        if let Some(binary) = term.downcast_ref::<EBinary>() {
+            return String::from_utf8(binary.as_bytes().to_vec())
+                .map_err(|_| mismatch("a UTF-8 binary", term));
+        }
+
+        if let Some(list) = term.downcast_ref::<EList>() {
+            return list.iter()
+                .map(|element| char::from_eterm_with(&**element, options).ok())
+                .collect::<Option<String>>()
+                .ok_or_else(|| mismatch("a list of code points", term));
+        }
+
+        Err(mismatch("a string", term))
+    }
+}
+
+/// The elements of a proper list.
+fn elements(term: &dyn ETerm) -> Result<&[Box<dyn ETerm>], Error> {
+    if let Some(list) = term.downcast_ref::<EList>() {
+        Ok(list.as_slice())
+    } else if term.is::<ENil>() {
+        Ok(&[])
+    } else {
+        Err(mismatch("a list", term))
+    }
+}
+
+impl<T: FromETerm> FromETerm for Vec<T> {
+    /// Accepts proper lists, and strings as the lists of their code points,
+    /// which is how lists of small integers are decoded by default.
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<Vec<T>, Error> {
+        if let Some(string) = term.downcast_ref::<EString>() {
+            return string.as_str()
+                .chars()
+                .map(|c| T::from_eterm_with(&(c as u32), options))
+                .collect();
+        }
+
+        elements(term)?
+            .iter()
+            .map(|element| T::from_eterm_with(&**element, options))
+            .collect()
+    }
+}
+
+impl<K: FromETerm + Eq + Hash, V: FromETerm> FromETerm for HashMap<K, V> {
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<HashMap<K, V>, Error> {
+        super::derive::map(term)?
+            .iter()
+            .map(|(k, v)| Ok((K::from_eterm_with(&**k, options)?, V::from_eterm_with(&**v, options)?)))
+            .collect()
+    }
+}
+
+impl<K: FromETerm + Ord, V: FromETerm> FromETerm for BTreeMap<K, V> {
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<BTreeMap<K, V>, Error> {
+        super::derive::map(term)?
+            .iter()
+            .map(|(k, v)| Ok((K::from_eterm_with(&**k, options)?, V::from_eterm_with(&**v, options)?)))
+            .collect()
+    }
+}
+
+impl<T: FromETerm> FromETerm for Option<T> {
+    /// Accepts the atom for `None` of the [`Convention`] in `options`, or
+    /// a `T` (in a `{some, T}` tuple for Gleam).
+    ///
+    /// [`Convention`]: ../enum.Convention.html
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<Option<T>, Error> {
+        let convention = options.convention();
+
+        match term.downcast_ref::<EAtom>() {
+            Some(atom) if atom.as_str() == convention.none() => Ok(None),
+            _ if convention.wraps_some() => {
+                let value = super::derive::record(term, "some", 1)
+                    .map_err(|_| mismatch("none or {some, _}", term))?;
+
+                T::from_eterm_with(&*value[0], options).map(Some)
+            },
+            _ => T::from_eterm_with(term, options).map(Some),
+        }
+    }
+}
+
+impl FromETerm for () {
+    /// Accepts `ok`, or `nil` for Gleam.
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<(), Error> {
+        let unit = options.convention().unit();
+
+        match term.downcast_ref::<EAtom>() {
+            Some(atom) if atom.as_str() == unit => Ok(()),
+            _ => Err(mismatch(unit, term)),
+        }
+    }
+}
+
+impl FromETerm for char {
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<char, Error> {
+        char::from_u32(u32::from_eterm_with(term, options)?)
+            .ok_or_else(|| mismatch("a code point", term))
+    }
+}
+
+impl<T: FromETerm, E: FromETerm> FromETerm for Result<T, E> {
+    /// Accepts `{ok, Value}` and `{error, Reason}`, as well as the atoms
+    /// `ok` and `error` when `T` or `E` is `()`, except for Gleam.
+    fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<Result<T, E>, Error> {
+        let bare = term.is::<EAtom>() && !options.convention().wraps_unit();
+        let unit = EAtom::new(options.convention().unit());
+
+        match super::derive::tag(term) {
+            Some("ok") if bare => Ok(Ok(T::from_eterm_with(&unit, options)?)),
+            Some("error") if bare => Ok(Err(E::from_eterm_with(&unit, options)?)),
+            Some("ok") => Ok(Ok(T::from_eterm_with(&*super::derive::record(term, "ok", 1)?[0], options)?)),
+            Some("error") => Ok(Err(E::from_eterm_with(&*super::derive::record(term, "error", 1)?[0], options)?)),
+            _ => Err(mismatch("{ok, _} or {error, _}", term)),
+        }
+    }
+}
+
+macro_rules! from_term_tuple {
+    ($arity:expr; $($name:ident),+) => {
+        impl<$($name: FromETerm),+> FromETerm for ($($name,)+) {
+            fn from_eterm_with(term: &dyn ETerm, options: &DecodeOptions) -> Result<($($name,)+), Error> {
+                let error = || mismatch(&format!("a tuple of {} elements", $arity), term);
+
+                let elements = term.downcast_ref::<ETuple>().ok_or_else(error)?.as_slice();
+                if elements.len() != $arity {
+                    return Err(error());
+                }
+
+                let mut elements = elements.iter();
+                Ok(($($name::from_eterm_with(&**elements.next().expect("The arity was just checked"), options)?,)+))
+            }
+        }
+    };
+}
+
+from_term_tuple!(1; A);
+from_term_tuple!(2; A, B);
+from_term_tuple!(3; A, B, C);
+from_term_tuple!(4; A, B, C, D);
+from_term_tuple!(5; A, B, C, D, E);
+from_term_tuple!(6; A, B, C, D, E, F);
+from_term_tuple!(7; A, B, C, D, E, F, G);
+from_term_tuple!(8; A, B, C, D, E, F, G, H);
+from_term_tuple!(9; A, B, C, D, E, F, G, H, I);
+from_term_tuple!(10; A, B, C, D, E, F, G, H, I, J);
+from_term_tuple!(11; A, B, C, D, E, F, G, H, I, J, K);
+from_term_tuple!(12; A, B, C, D, E, F, G, H, I, J, K, L);
+
+macro_rules! try_from_term {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<&dyn ETerm> for $t {
+                type Error = Error;
+
+                fn try_from(term: &dyn ETerm) -> Result<$t, Error> {
+                    <$t as FromETerm>::from_eterm(term)
+                }
+            }
+        )*
+    };
+}
+
+try_from_term!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize, f32, f64, bool, char, String);
+try_from_term!(ENil, EAtom, EString, EBinary);
+
+#[cfg(feature="bigint")]
+try_from_term!(BigInt, BigUint);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{ Convention, EMap };
+
+    fn list(elements: Vec<Box<dyn ETerm>>) -> EList {
+        EList::new(elements)
+    }
+
+    #[test]
+    fn term_structs() {
+        let term: Box<dyn ETerm> = Box::new(EBinary::new(vec![1, 2]));
+        assert_eq!(vec![1, 2], EBinary::from_eterm(&*term).unwrap().into_inner());
+
+        let error = EAtom::from_eterm(&*term).unwrap_err();
+        assert_eq!("Expected an atom, got <<1,2>>", error.to_string());
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(255u8, u8::from_eterm(&255i32).unwrap());
+        assert_eq!(-1i8, i8::from_eterm(&-1i128).unwrap());
+        assert_eq!(u128::MAX, u128::from_eterm(&u128::MAX).unwrap());
+        assert_eq!(7i64, i64::from_eterm(&7u8).unwrap());
+
+        assert_eq!("Integer 256 does not fit in a u8", u8::from_eterm(&256i32).unwrap_err().to_string());
+        assert_eq!("Integer -1 does not fit in a u64", u64::from_eterm(&-1i8).unwrap_err().to_string());
+        assert_eq!("Expected an integer, got 1.5", i32::from_eterm(&1.5f64).unwrap_err().to_string());
+    }
+
+    #[cfg(feature="bigint")]
+    #[test]
+    fn big_integers() {
+        let big = BigInt::from(u128::MAX) * 2;
+
+        assert_eq!(5u8, u8::from_eterm(&BigInt::from(5)).unwrap());
+        assert!(u128::from_eterm(&big).is_err());
+        assert_eq!(big, BigInt::from_eterm(&big).unwrap());
+        assert_eq!(BigUint::from(3u8), BigUint::from_eterm(&3i32).unwrap());
+        assert!(BigUint::from_eterm(&-3i32).is_err());
+    }
+
+    #[test]
+    fn floats_and_booleans() {
+        assert_eq!(1.5f64, f64::from_eterm(&1.5f32).unwrap());
+        assert_eq!(1.5f32, f32::from_eterm(&1.5f64).unwrap());
+        assert!(f32::from_eterm(&f64::MAX).is_err());
+        assert!(f64::from_eterm(&1u8).is_err());
+
+        assert!(bool::from_eterm(&EAtom::new("true")).unwrap());
+        assert!(!bool::from_eterm(&EAtom::new("false")).unwrap());
+        assert_eq!("Expected a boolean, got yes", bool::from_eterm(&EAtom::new("yes")).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!("abc", String::from_eterm(&EString::new("abc")).unwrap());
+        assert_eq!("", String::from_eterm(&ENil).unwrap());
+        assert_eq!("hé", String::from_eterm(&EBinary::new("hé".as_bytes().to_vec())).unwrap());
+        assert_eq!("hé", String::from_eterm(&list(vec![Box::new(104u8), Box::new(233u8)])).unwrap());
+        assert_eq!("Ж", String::from_eterm(&list(vec![Box::new(1046i32)])).unwrap());
+
+        assert!(String::from_eterm(&EBinary::new(vec![0xff])).is_err());
+        assert!(String::from_eterm(&list(vec![Box::new(-1i32)])).is_err());
+        assert!(String::from_eterm(&EAtom::new("abc")).is_err());
+    }
+
+    #[test]
+    fn collections() {
+        let numbers = list(vec![Box::new(1u8), Box::new(1000i32)]);
+        assert_eq!(vec![1u16, 1000], Vec::<u16>::from_eterm(&numbers).unwrap());
+        assert_eq!(Vec::<u8>::new(), Vec::<u8>::from_eterm(&ENil).unwrap());
+        assert!(Vec::<u8>::from_eterm(&numbers).is_err());
+
+        // A LIST_EXT of small integers is decoded as a string.
+        let decoded = super::super::decode::decode(&mut &[108, 0, 0, 0, 3, 97, 1, 97, 2, 97, 3, 106][..], &DecodeOptions::default()).unwrap();
+        assert!(decoded.is::<EString>());
+        assert_eq!(vec![1u8, 2, 3], Vec::<u8>::from_eterm(&*decoded).unwrap());
+        assert_eq!(vec![104u32, 1046], Vec::<u32>::from_eterm(&EString::new("hЖ")).unwrap());
+        assert!(Vec::<u8>::from_eterm(&EString::new("Ж")).is_err());
+
+        let map = EMap::new(vec![
+            (Box::new(EString::new("a")), Box::new(1u8)),
+            (Box::new(EString::new("b")), Box::new(EAtom::new("undefined"))),
+        ]);
+        let hash_map = HashMap::<String, Option<u8>>::from_eterm(&map).unwrap();
+        assert_eq!(Some(&Some(1)), hash_map.get("a"));
+        assert_eq!(Some(&None), hash_map.get("b"));
+        let btree_map = BTreeMap::<String, Option<i32>>::from_eterm(&map).unwrap();
+        assert_eq!(vec!["a", "b"], btree_map.keys().collect::<Vec<_>>());
+
+        let tuple = ETuple::from((EAtom::new("ok"), 5u8));
+        assert_eq!((true, 5i64), <(bool, i64)>::from_eterm(&ETuple::from((EAtom::new("true"), 5u8))).unwrap());
+        assert_eq!("Expected a tuple of 3 elements, got {ok,5}", <(EAtom, u8, u8)>::from_eterm(&tuple).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn conventions() {
+        let elixir = DecodeOptions::default().with_convention(Convention::Elixir);
+        let gleam = DecodeOptions::default().with_convention(Convention::Gleam);
+        let undefined = EAtom::new("undefined");
+        let nil = EAtom::new("nil");
+
+        assert_eq!(None, Option::<u8>::from_eterm(&undefined).unwrap());
+        assert_eq!(Some(EAtom::new("nil")), Option::<EAtom>::from_eterm(&nil).unwrap());
+        assert_eq!(None, Option::<EAtom>::from_eterm_with(&nil, &elixir).unwrap());
+        assert_eq!(Some(undefined.clone()), Option::<EAtom>::from_eterm_with(&undefined, &elixir).unwrap());
+        assert_eq!(vec![None, Some(1)], Vec::<Option<u8>>::from_eterm_with(&list(vec![Box::new(nil), Box::new(1u8)]), &elixir).unwrap());
+
+        assert_eq!(None, Option::<u8>::from_eterm_with(&EAtom::new("none"), &gleam).unwrap());
+        assert_eq!(Some(1), Option::<u8>::from_eterm_with(&ETuple::from((EAtom::new("some"), 1u8)), &gleam).unwrap());
+        assert_eq!("Expected none or {some, _}, got 1", Option::<u8>::from_eterm_with(&1u8, &gleam).unwrap_err().to_string());
+
+        assert_eq!((), <()>::from_eterm(&EAtom::new("ok")).unwrap());
+        assert!(<()>::from_eterm(&EAtom::new("error")).is_err());
+        assert_eq!('€', char::from_eterm(&0x20aci32).unwrap());
+        assert!(char::from_eterm(&0xd800i32).is_err());
+    }
+
+    #[test]
+    fn units_follow_the_convention() {
+        use super::super::decode::decode;
+        use super::super::encode::{ EncodeOptions, ToExternalBinary };
+
+        fn roundtrip<T: ToExternalBinary + FromETerm + PartialEq + std::fmt::Debug>(value: T, convention: Convention, expected: &str) {
+            let mut bytes = Vec::new();
+            value.to_writer_with(&mut bytes, &EncodeOptions::default().with_convention(convention)).unwrap();
+            let options = DecodeOptions::default().with_convention(convention);
+            let term = decode(&mut bytes.as_slice(), &options).unwrap();
+
+            assert_eq!(expected, term.to_string());
+            assert_eq!(value, T::from_eterm_with(&*term, &options).unwrap());
+        }
+
+        for convention in [Convention::Erlang, Convention::Elixir] {
+            roundtrip((), convention, "ok");
+            roundtrip(Ok::<(), ()>(()), convention, "ok");
+            roundtrip(Err::<(), ()>(()), convention, "error");
+        }
+
+        roundtrip((), Convention::Gleam, "nil");
+        roundtrip(Ok::<(), ()>(()), Convention::Gleam, "{ok,nil}");
+        roundtrip(Err::<(), ()>(()), Convention::Gleam, "{error,nil}");
+
+        let gleam = DecodeOptions::default().with_convention(Convention::Gleam);
+        assert!(<()>::from_eterm_with(&EAtom::new("ok"), &gleam).is_err());
+        assert!(Result::<(), ()>::from_eterm_with(&EAtom::new("ok"), &gleam).is_err());
+    }
+
+    #[test]
+    fn results() {
+        let ok = ETuple::from((EAtom::new("ok"), 5u8));
+        let error = ETuple::from((EAtom::new("error"), EAtom::new("enoent")));
+
+        assert_eq!(Ok(5), Result::<u8, EAtom>::from_eterm(&ok).unwrap());
+        assert_eq!(Err(EAtom::new("enoent")), Result::<u8, EAtom>::from_eterm(&error).unwrap());
+        assert_eq!(Ok(()), Result::<(), EAtom>::from_eterm(&EAtom::new("ok")).unwrap());
+        assert_eq!(Err(()), Result::<u8, ()>::from_eterm(&EAtom::new("error")).unwrap());
+
+        assert!(Result::<u8, EAtom>::from_eterm(&EAtom::new("ok")).is_err());
+        assert!(Result::<u8, ()>::from_eterm(&error).is_err());
+        assert_eq!("Expected {ok, _} or {error, _}, got {done,5}", Result::<u8, u8>::from_eterm(&ETuple::from((EAtom::new("done"), 5u8))).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn try_from() {
+        let term: Box<dyn ETerm> = Box::new(EString::new("text"));
+
+        assert_eq!("text", String::try_from(&*term).unwrap());
+        assert!(i32::try_from(&*term).is_err());
+    }
+}
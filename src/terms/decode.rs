@@ -1,32 +1,40 @@
 #[cfg(feature="bigint")]
 use num_bigint::{ BigInt, BigUint, Sign };
 
+#[cfg(feature="compression")]
+use flate2::bufread::ZlibDecoder;
+
 use super::{
+    Convention,
     EList,
     ENil,
     ENonProperList,
     EAtom,
-    EExport,
     ETerm,
     ETuple,
     EString,
-    EPort,
-    EPid,
     EMap,
     EBinary,
     TermTag,
+    DistHeaderTag,
+    ETF_VERSION,
 };
+use super::borrowed;
+use super::layout::term_size;
 
 use super::super::error::{ Error };
 
 use std::any::Any;
-use std::io::{ Read };
+use std::borrow::Cow;
+use std::io::{ self, Read };
 use std::convert::TryInto;
 use std::str::FromStr;
 
+#[derive(Clone, Debug)]
 pub struct DecodeOptions {
-    read_string_ext_as_list: bool,
-    try_read_list_ext_as_estring: bool,
+    pub(crate) read_string_ext_as_list: bool,
+    pub(crate) try_read_list_ext_as_estring: bool,
+    pub(crate) convention: Convention,
 }
 
 impl Default for DecodeOptions {
@@ -34,10 +42,25 @@ impl Default for DecodeOptions {
         DecodeOptions {
             read_string_ext_as_list: false,
             try_read_list_ext_as_estring: true,
+            convention: Convention::Erlang,
         }
     }
 }
 
+impl DecodeOptions {
+    pub fn convention(&self) -> Convention {
+        self.convention
+    }
+
+    /// Chooses how [`FromETerm`] recognizes `None`.
+    ///
+    /// [`FromETerm`]: ../convert/trait.FromETerm.html
+    pub fn with_convention(mut self, convention: Convention) -> DecodeOptions {
+        self.convention = convention;
+        self
+    }
+}
+
 pub fn decode(reader: &mut dyn Read, options: &DecodeOptions) -> Result<Box<dyn ETerm>, Error> {
     let tag: TermTag = read_u8(reader)?
         .try_into()
@@ -61,54 +84,27 @@ pub fn decode(reader: &mut dyn Read, options: &DecodeOptions) -> Result<Box<dyn
         },
         TermTag::List => {
             let len = read_u32(reader)? as usize;
-            if options.try_read_list_ext_as_estring {
-                let list: Vec<Box<dyn ETerm>> = read_terms(reader, len, options)?;
-                let tail: Box<dyn ETerm> = decode(reader, options)?;
-
-                let mut bytes: Vec<u8> = vec![];
-
-                for e in &list {
-                    let x: &dyn Any = e;
-
-                    if let Some(byte) = (*x).downcast_ref::<u8>() {
-                        bytes.push(*byte);
-                    } else {
-                        let y: &dyn Any = &tail;
-
-                        return if (*y).downcast_ref::<ENil>().is_some() {
-                            Ok(Box::from(EList(list)))
-                        } else {
-                            Ok(Box::from(ENonProperList { data: list, tail }))
-                        };
-                    }
-                }
+            let list = read_terms(reader, len, options)?;
+            let tail = decode(reader, options)?;
 
-                Ok(Box::from(EString(read_string(&mut bytes.as_mut_slice().as_ref(), list.len())?)))
-            } else {
-                Ok(Box::from(EList(read_terms(reader, len, options)?)))
-            }
-        },
-        TermTag::NewFloat => Ok(Box::from(read_f64(reader)?)),
-        TermTag::Float => {
-            match f64::from_str(read_string(reader, 31)?.as_ref()) {
-                Ok(f) => Ok(Box::from(f)),
-                Err(e) => Err(Error::Message(e.to_string())),
-            }
+            list_to_term(list, tail, options)
         },
+        TermTag::NewFloat => Ok(Box::from(check_finite(read_f64(reader)?)?)),
+        TermTag::Float => Ok(Box::from(parse_float_ext(&read_vec(reader, 31)?)?)),
         TermTag::SmallInteger => Ok(Box::from(read_u8(reader)?)),
         TermTag::Integer => Ok(Box::from(read_i32(reader)?)),
-        TermTag::Nil => Ok(Box::from(ENil::default())),
+        TermTag::Nil => Ok(Box::from(ENil)),
         TermTag::SmallBig => {
             let len = read_u8(reader)? as usize;
             let negative = read_u8(reader)? != 0;
             let value = read_vec(reader, len)?;
-            num_vec_to_term(negative, value)
+            num_vec_to_term(negative, &value)
         },
         TermTag::LargeBig => {
             let len = read_u32(reader)? as usize;
             let negative = read_u8(reader)? != 0;
             let value = read_vec(reader, len)?;
-            num_vec_to_term(negative, value)
+            num_vec_to_term(negative, &value)
         },
         TermTag::SmallTuple => {
             let len = read_u8(reader)? as usize;
@@ -146,6 +142,78 @@ pub fn decode(reader: &mut dyn Read, options: &DecodeOptions) -> Result<Box<dyn
     }
 }
 
+/// Decodes the complete message at the start of `input`, like
+/// `binary_to_term/1`, and advances `input` past it.
+///
+/// See [`message_body`] for the messages that are understood.
+/// Like [`borrowed::decode`], this fails on terms nested more than
+/// [`MAX_DEPTH`] levels deep.
+///
+/// [`message_body`]: fn.message_body.html
+/// [`borrowed::decode`]: ../borrowed/fn.decode.html
+/// [`MAX_DEPTH`]: ../borrowed/constant.MAX_DEPTH.html
+pub fn decode_message(input: &mut &[u8], options: &DecodeOptions) -> Result<Box<dyn ETerm>, Error> {
+    let body = message_body(input)?;
+
+    borrowed::decode(&mut body.as_ref())?.to_owned_with(options)
+}
+
+/// Returns the term of the complete message at the start of `input`, without
+/// the [`ETF_VERSION`] byte, and advances `input` past the message.
+///
+/// Messages written by `term_to_binary/2` with the `compressed` option are
+/// decompressed, which requires the `compression` feature.
+/// The [`LOCAL_EXT`] tag that [`EncodeOptions::with_local`] writes is
+/// skipped.
+///
+/// [`LOCAL_EXT`]: ../enum.DistHeaderTag.html#variant.Local
+/// [`EncodeOptions::with_local`]: ../encode/struct.EncodeOptions.html#method.with_local
+/// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+pub fn message_body<'a>(input: &mut &'a [u8]) -> Result<Cow<'a, [u8]>, Error> {
+    let rest = match input.split_first() {
+        Some((&ETF_VERSION, rest)) => rest,
+        Some((version, _)) => return Err(Error::Message(format!("Unsupported external term format version {}", version))),
+        None => return Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+    };
+    let rest = match rest.split_first() {
+        Some((&tag, rest)) if tag == DistHeaderTag::Local as u8 => rest,
+        _ => rest,
+    };
+
+    if rest.first() != Some(&(DistHeaderTag::Compressed as u8)) {
+        let size = term_size(rest)?;
+        *input = &rest[size..];
+
+        return Ok(Cow::Borrowed(&rest[..size]));
+    }
+
+    #[cfg(feature="compression")]
+    {
+        let mut compressed = &rest[1..];
+        let size = read_u32(&mut compressed)? as usize;
+        let mut body = Vec::new();
+
+        // The size in the header is not trusted: the body grows as data is
+        // inflated, and inflating stops one byte past the stated size.
+        ZlibDecoder::new(&mut compressed).take(size as u64 + 1).read_to_end(&mut body)
+            .map_err(|e| Error::Message(format!("Invalid compressed term: {}", e)))?;
+
+        if body.len() > size {
+            return Err(Error::Message(format!("Compressed term is larger than the {} bytes its header states", size)));
+        }
+
+        if body.len() != size {
+            return Err(Error::Message(format!("Compressed term is {} bytes, not the {} bytes its header states", body.len(), size)));
+        }
+
+        *input = compressed;
+        Ok(Cow::Owned(body))
+    }
+
+    #[cfg(not(feature="compression"))]
+    Err(Error::Message("Decoding compressed terms requires the compression feature".to_string()))
+}
+
 fn read_terms(reader: &mut dyn Read, length: usize, options: &DecodeOptions) -> Result<Vec<Box<dyn ETerm>>, Error> {
     let mut entries: Vec<Box<dyn ETerm>> = vec![];
 
@@ -200,27 +268,82 @@ fn read_map(reader: &mut dyn Read, options: &DecodeOptions, length: usize) -> Re
     Ok(EMap(result))
 }
 
-fn num_vec_to_term(negative: bool, data: Vec<u8>) -> Result<Box<dyn ETerm>, Error> {
+/// Parses the text of a `FLOAT_EXT`.
+///
+/// The text is padded to 31 bytes, which Erlang does with NUL bytes.
+/// Some older encoders pad with spaces instead, so both are ignored.
+pub(crate) fn parse_float_ext(text: &[u8]) -> Result<f64, Error> {
+    let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+    let text = std::str::from_utf8(&text[..end])
+        .map_err(|e| Error::Message(e.to_string()))?
+        .trim();
+
+    f64::from_str(text)
+        .map_err(|e| Error::Message(e.to_string()))
+        .and_then(check_finite)
+}
+
+/// Erlang has no representation for infinite or NaN floats.
+pub(crate) fn check_finite(value: f64) -> Result<f64, Error> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(Error::Message(format!("Float {} is not finite", value)))
+    }
+}
+
+/// Builds the term for a proper or improper list from its decoded elements
+/// and tail.
+pub(crate) fn list_to_term(list: Vec<Box<dyn ETerm>>, tail: Box<dyn ETerm>, options: &DecodeOptions) -> Result<Box<dyn ETerm>, Error> {
+    let tail_any: &dyn Any = &*tail;
+
+    if tail_any.downcast_ref::<ENil>().is_none() {
+        return Ok(Box::from(ENonProperList { data: list, tail }));
+    }
+
+    if options.try_read_list_ext_as_estring {
+        let bytes: Option<Vec<u8>> = list.iter()
+            .map(|e| {
+                let x: &dyn Any = &**e;
+                x.downcast_ref::<u8>().copied()
+            })
+            .collect();
+
+        if let Some(Ok(string)) = bytes.map(String::from_utf8) {
+            return Ok(Box::from(EString(string)));
+        }
+    }
+
+    Ok(Box::from(EList(list)))
+}
+
+/// Converts the little-endian digits of a `SMALL_BIG_EXT` or `LARGE_BIG_EXT`
+/// into the smallest fitting integer type.
+pub(crate) fn num_vec_to_term(negative: bool, data: &[u8]) -> Result<Box<dyn ETerm>, Error> {
+    let significant = data.iter().rposition(|&d| d != 0).map_or(0, |p| p + 1);
+    let data = &data[..significant];
+
     match (negative, data.len()) {
         (_, 0) => Ok(Box::from(0u8)),
         (false, 1..=16) =>
-            Ok(Box::from(u128::from_be_bytes(slice_16_from_vec(&data)))),
-        (true, 1..=16) if u128::from_be_bytes(slice_16_from_vec(&data)) <= i128::max_value() as u128 =>
-            Ok(Box::from(-i128::from_be_bytes(slice_16_from_vec(&data)))),
-        (true, 16) if data[0] == 0x7fu8 => Ok(Box::from(i128::min_value())),
+            Ok(Box::from(u128::from_le_bytes(slice_16_from_le(data)))),
+        (true, 1..=16) if u128::from_le_bytes(slice_16_from_le(data)) <= i128::MAX as u128 =>
+            Ok(Box::from(-i128::from_le_bytes(slice_16_from_le(data)))),
+        (true, 16) if u128::from_le_bytes(slice_16_from_le(data)) == i128::MIN as u128 =>
+            Ok(Box::from(i128::MIN)),
         #[cfg(feature="bigint")]
-        (false, _) => Ok(Box::from(BigUint::from_bytes_le(&data))),
+        (false, _) => Ok(Box::from(BigUint::from_bytes_le(data))),
         #[cfg(feature="bigint")]
-        (true, _) => Ok(Box::from(BigInt::from_bytes_le(Sign::Minus, &data))),
+        (true, _) => Ok(Box::from(BigInt::from_bytes_le(Sign::Minus, data))),
         #[cfg(not(feature="bigint"))]
         _ => Err(Error::Message("Attempting to deserialize a big integer, but the feature is not enabled.".to_string())),
     }
 }
 
-fn slice_16_from_vec(data: &[u8]) -> [u8; 16] {
+fn slice_16_from_le(data: &[u8]) -> [u8; 16] {
     let mut slice = [0; 16];
 
-    slice[16 - std::cmp::min(16, data.len())..16].clone_from_slice(&data[16 - std::cmp::min(16, data.len())..16]);
+    slice[..data.len()].clone_from_slice(data);
 
     slice
 }
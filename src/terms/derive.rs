@@ -0,0 +1,130 @@
+//! Support for the code generated by `#[derive(ToETerm, FromETerm)]`.
+//!
+//! Nothing in here is meant to be used directly, and it may change at any
+//! time.
+
+use super::{ EAtom, EMap, ETerm, ETuple };
+use super::convert::mismatch;
+use super::encode::{ tuple_header_size, write_map_header, write_tuple_header, EncodeOptions, ToExternalBinary };
+use super::super::error::Error;
+
+use std::fmt;
+use std::io::Write;
+
+/// A borrowed value that can be encoded and displayed as a term.
+pub trait Field: ToExternalBinary + fmt::Display {}
+
+impl<T: ToExternalBinary + fmt::Display + ?Sized> Field for T {}
+
+/// A tuple of borrowed terms.
+pub struct Tuple<'a>(pub &'a [&'a dyn Field]);
+
+impl ToExternalBinary for Tuple<'_> {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_tuple_header(writer, self.0.len())?;
+
+        for element in self.0 {
+            written += element.to_writer_with(writer, options)?;
+        }
+
+        Ok(written)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut size = tuple_header_size(self.0.len());
+
+        for element in self.0 {
+            size += element.encoded_size_with(options)?;
+        }
+
+        Ok(size)
+    }
+}
+
+impl fmt::Display for Tuple<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|element| element.to_string()).collect();
+
+        write!(f, "{{{}}}", parts.join(","))
+    }
+}
+
+/// A map of borrowed terms, with its keys already in the order in which they
+/// should be encoded.
+pub struct Map<'a>(pub &'a [(&'a dyn Field, &'a dyn Field)]);
+
+impl ToExternalBinary for Map<'_> {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_map_header(writer, self.0.len())?;
+
+        for (key, value) in self.0 {
+            written += key.to_writer_with(writer, options)?;
+            written += value.to_writer_with(writer, options)?;
+        }
+
+        Ok(written)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut size = 5;
+
+        for (key, value) in self.0 {
+            size += key.encoded_size_with(options)? + value.encoded_size_with(options)?;
+        }
+
+        Ok(size)
+    }
+}
+
+impl fmt::Display for Map<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|(key, value)| format!("{}=>{}", key, value)).collect();
+
+        write!(f, "#{{{}}}", parts.join(","))
+    }
+}
+
+/// The name of an atom, or of the atom a tuple starts with.
+pub fn tag(term: &dyn ETerm) -> Option<&str> {
+    if let Some(atom) = term.downcast_ref::<EAtom>() {
+        return Some(atom.as_str());
+    }
+
+    term.downcast_ref::<ETuple>()
+        .and_then(|tuple| tuple.as_slice().first())
+        .and_then(|first| first.downcast_ref::<EAtom>())
+        .map(EAtom::as_str)
+}
+
+/// Checks that `term` is the atom `name`.
+pub fn atom(term: &dyn ETerm, name: &str) -> Result<(), Error> {
+    match term.downcast_ref::<EAtom>() {
+        Some(atom) if atom.as_str() == name => Ok(()),
+        _ => Err(mismatch(&format!("the atom {}", EAtom::new(name)), term)),
+    }
+}
+
+/// Returns the elements following the tag of a tuple `{name, ...}` with
+/// `arity` elements after the tag.
+pub fn record<'a>(term: &'a dyn ETerm, name: &str, arity: usize) -> Result<&'a [Box<dyn ETerm>], Error> {
+    let error = || mismatch(&format!("a tuple {{{}, ...}} of {} elements", EAtom::new(name), arity + 1), term);
+
+    let elements = term.downcast_ref::<ETuple>().ok_or_else(error)?.as_slice();
+
+    match elements.split_first() {
+        Some((first, rest)) if rest.len() == arity && first.downcast_ref::<EAtom>().map(EAtom::as_str) == Some(name) => Ok(rest),
+        _ => Err(error()),
+    }
+}
+
+pub fn map(term: &dyn ETerm) -> Result<&EMap, Error> {
+    term.downcast_ref::<EMap>().ok_or_else(|| mismatch("a map", term))
+}
+
+/// Returns the value associated with the atom `key` in `map`.
+pub fn map_field<'a>(map: &'a EMap, key: &str) -> Result<&'a dyn ETerm, Error> {
+    map.iter()
+        .find(|(k, _)| k.downcast_ref::<EAtom>().map(EAtom::as_str) == Some(key))
+        .map(|(_, value)| &**value)
+        .ok_or_else(|| Error::Message(format!("Missing key {} in {}", EAtom::new(key), map)))
+}
@@ -0,0 +1,429 @@
+//! Structural differences between terms.
+//!
+//! [`diff`] compares two terms like `=:=` does, and reports every place
+//! where they differ, by its [`Path`]:
+//! elements of tuples and lists and the values of map keys are compared
+//! recursively, so a single wrong element deep inside a large reply is
+//! reported as just that element.
+//!
+//! ```
+//! # #[macro_use] extern crate rust_eterm;
+//! # fn main() {
+//! use rust_eterm::terms::diff::diff;
+//!
+//! let expected = eterm!({reply, #{users => [<<"joe">>, <<"mike">>], count => 2}});
+//! let actual = eterm!({reply, #{users => [<<"joe">>, "mike"], count => 2.0}});
+//!
+//! assert_eq!("\
+//! 2.count: 2 != 2.0 (integer vs float)
+//! 2.users[1]: <<\"mike\">> != \"mike\" (binary vs list)", diff(&expected, &actual).unwrap().to_string());
+//! # }
+//! ```
+//!
+//! The [`assert_term_eq!`] macro prints these differences when it fails.
+//!
+//! [`diff`]: fn.diff.html
+//! [`Path`]: ../path/struct.Path.html
+//! [`assert_term_eq!`]: ../../macro.assert_term_eq.html
+
+use super::ETerm;
+use super::borrowed::{ self, BorrowedTerm };
+use super::order::{ compare_keys, exactly_equal_terms };
+use super::path::{ Path, Segment };
+use super::pretty::single_line;
+use super::super::error::Error;
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Deref;
+use std::slice;
+use std::vec;
+
+/// How two terms differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// Terms of different types, such as an atom and a tuple.
+    Type(&'static str, &'static str),
+    /// An integer and a float, which may be equal in value.
+    IntegerFloat,
+    /// Terms of the same type, with different values.
+    Value,
+    /// Tuples of different arities.
+    Arity(usize, usize),
+    /// Lists of different lengths.
+    Length(usize, usize),
+    /// A key that only the left map contains.
+    OnlyLeft,
+    /// A key that only the right map contains.
+    OnlyRight,
+}
+
+/// A place where two terms differ.
+#[derive(Debug)]
+pub struct Difference {
+    /// The path to the differing terms.
+    pub path: Path,
+    /// How the terms differ.
+    pub kind: DifferenceKind,
+    /// The term of the left side, which is `None` for a key that only the
+    /// right map contains.
+    pub left: Option<Box<dyn ETerm>>,
+    /// The term of the right side, which is `None` for a key that only the
+    /// left map contains.
+    pub right: Option<Box<dyn ETerm>>,
+}
+
+/// All places where two terms differ, in the order of their paths.
+///
+/// A `Diff` derefs to a slice of its differences, which is empty when the
+/// terms are exactly equal.
+#[derive(Debug, Default)]
+pub struct Diff(Vec<Difference>);
+
+impl Diff {
+    /// Takes the differences out of this diff.
+    pub fn into_inner(self) -> Vec<Difference> {
+        self.0
+    }
+}
+
+impl Deref for Diff {
+    type Target = [Difference];
+
+    fn deref(&self) -> &[Difference] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Diff {
+    type Item = Difference;
+    type IntoIter = vec::IntoIter<Difference>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diff {
+    type Item = &'a Difference;
+    type IntoIter = slice::Iter<'a, Difference>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Compares two terms, and returns the places where they differ.
+///
+/// Lists are compared element by element, whether they are encoded as
+/// strings or as lists, and maps are compared key by key, regardless of
+/// the order of their keys.
+pub fn diff(left: &dyn ETerm, right: &dyn ETerm) -> Result<Diff, Error> {
+    let (left, right) = (left.to_external_binary()?, right.to_external_binary()?);
+    let (left, right) = (borrowed::decode(&mut left.as_slice())?, borrowed::decode(&mut right.as_slice())?);
+
+    let mut differ = Differ { path: Vec::new(), differences: Vec::new() };
+    differ.terms(&left, &right)?;
+
+    Ok(Diff(differ.differences))
+}
+
+/// A segment of the path to the terms that are being compared.
+enum Step<'t, 'a> {
+    Element(usize),
+    Index(usize),
+    Tail,
+    Key(&'t BorrowedTerm<'a>),
+}
+
+struct Differ<'t, 'a> {
+    path: Vec<Step<'t, 'a>>,
+    differences: Vec<Difference>,
+}
+
+impl<'t, 'a> Differ<'t, 'a> {
+    fn terms(&mut self, left: &'t BorrowedTerm<'a>, right: &'t BorrowedTerm<'a>) -> Result<(), Error> {
+        if let Some(kind) = shallow(left, right) {
+            return self.record(kind, Some(left), Some(right));
+        }
+
+        match (left, right) {
+            (BorrowedTerm::Tuple(l), BorrowedTerm::Tuple(r)) => {
+                for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                    self.nested(Step::Element(i + 1), l, r)?;
+                }
+
+                Ok(())
+            },
+            (BorrowedTerm::Map(l), BorrowedTerm::Map(r)) => self.maps(l, r),
+            _ if type_name(left) == "list" => self.lists(left, right),
+            _ => Ok(()),
+        }
+    }
+
+    fn nested(&mut self, step: Step<'t, 'a>, left: &'t BorrowedTerm<'a>, right: &'t BorrowedTerm<'a>) -> Result<(), Error> {
+        self.path.push(step);
+        let result = self.terms(left, right);
+        self.path.pop();
+
+        result
+    }
+
+    fn lists(&mut self, left: &'t BorrowedTerm<'a>, right: &'t BorrowedTerm<'a>) -> Result<(), Error> {
+        let (l, l_tail) = list_parts(left);
+        let (r, r_tail) = list_parts(right);
+
+        for (i, (l, r)) in l.iter().zip(&r).enumerate() {
+            match (l, r) {
+                (Cow::Borrowed(l), Cow::Borrowed(r)) => self.nested(Step::Index(i), l, r)?,
+                // The bytes of strings are integers, so they have no nested terms.
+                (l, r) => if let Some(kind) = shallow(l, r) {
+                    self.path.push(Step::Index(i));
+                    let result = self.record(kind, Some(l), Some(r));
+                    self.path.pop();
+                    result?;
+                },
+            }
+        }
+
+        if l.len() != r.len() {
+            return self.record(DifferenceKind::Length(l.len(), r.len()), Some(left), Some(right));
+        }
+
+        match (l_tail, r_tail) {
+            (None, None) => Ok(()),
+            (Some(l), Some(r)) => self.nested(Step::Tail, l, r),
+            (l, r) => {
+                let nil = BorrowedTerm::Nil;
+                let (l, r) = (l.unwrap_or(&nil), r.unwrap_or(&nil));
+
+                self.path.push(Step::Tail);
+                let result = self.record(shallow(l, r).unwrap_or(DifferenceKind::Value), Some(l), Some(r));
+                self.path.pop();
+
+                result
+            },
+        }
+    }
+
+    fn maps(&mut self, left: &'t [(BorrowedTerm<'a>, BorrowedTerm<'a>)], right: &'t [(BorrowedTerm<'a>, BorrowedTerm<'a>)]) -> Result<(), Error> {
+        let (left, right) = (sorted_by_key(left), sorted_by_key(right));
+        let (mut i, mut j) = (0, 0);
+
+        while i < left.len() || j < right.len() {
+            let order = match (left.get(i), right.get(j)) {
+                (Some((l, _)), Some((r, _))) => compare_keys(l, r),
+                (Some(_), None) => Ordering::Less,
+                _ => Ordering::Greater,
+            };
+
+            match order {
+                Ordering::Less => {
+                    self.path.push(Step::Key(&left[i].0));
+                    let result = self.record(DifferenceKind::OnlyLeft, Some(&left[i].1), None);
+                    self.path.pop();
+                    result?;
+                    i += 1;
+                },
+                Ordering::Greater => {
+                    self.path.push(Step::Key(&right[j].0));
+                    let result = self.record(DifferenceKind::OnlyRight, None, Some(&right[j].1));
+                    self.path.pop();
+                    result?;
+                    j += 1;
+                },
+                Ordering::Equal => {
+                    self.nested(Step::Key(&left[i].0), &left[i].1, &right[j].1)?;
+                    i += 1;
+                    j += 1;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record(&mut self, kind: DifferenceKind, left: Option<&BorrowedTerm>, right: Option<&BorrowedTerm>) -> Result<(), Error> {
+        let path = self.path.iter()
+            .map(|step| Ok(match step {
+                Step::Element(n) => Segment::Element(*n),
+                Step::Index(i) => Segment::Index(*i),
+                Step::Tail => Segment::Tail,
+                Step::Key(key) => Segment::Key(BorrowedTerm::to_owned(key)?),
+            }))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        self.differences.push(Difference {
+            path: Path::new(path),
+            kind,
+            left: left.map(BorrowedTerm::to_owned).transpose()?,
+            right: right.map(BorrowedTerm::to_owned).transpose()?,
+        });
+
+        Ok(())
+    }
+}
+
+/// Compares two terms without comparing the terms nested in them, and returns
+/// how they differ, if they do.
+///
+/// Lists, maps and tuples of the same arity are never different here.
+fn shallow(left: &BorrowedTerm, right: &BorrowedTerm) -> Option<DifferenceKind> {
+    match (type_name(left), type_name(right)) {
+        ("integer", "float") | ("float", "integer") => Some(DifferenceKind::IntegerFloat),
+        (l, r) if l != r => Some(DifferenceKind::Type(l, r)),
+        ("list", _) | ("map", _) => None,
+        ("tuple", _) => match (left, right) {
+            (BorrowedTerm::Tuple(l), BorrowedTerm::Tuple(r)) if l.len() != r.len() => Some(DifferenceKind::Arity(l.len(), r.len())),
+            _ => None,
+        },
+        _ if exactly_equal_terms(left, right) => None,
+        _ => Some(DifferenceKind::Value),
+    }
+}
+
+fn type_name(term: &BorrowedTerm) -> &'static str {
+    match term {
+        BorrowedTerm::Integer(_) | BorrowedTerm::BigInteger { .. } => "integer",
+        BorrowedTerm::Float(_) => "float",
+        BorrowedTerm::Atom(_) => "atom",
+        BorrowedTerm::Nil | BorrowedTerm::String(_) | BorrowedTerm::List(_, _) => "list",
+        BorrowedTerm::Tuple(_) => "tuple",
+        BorrowedTerm::Map(_) => "map",
+        BorrowedTerm::Binary(_) => "binary",
+        BorrowedTerm::Export { .. } => "fun",
+        BorrowedTerm::Pid { .. } => "pid",
+        BorrowedTerm::Port { .. } => "port",
+    }
+}
+
+type ListParts<'t, 'a> = (Vec<Cow<'t, BorrowedTerm<'a>>>, Option<&'t BorrowedTerm<'a>>);
+
+/// Splits a list into its elements and its tail, if it is improper.
+///
+/// Tails that are lists themselves are flattened, since `[1|[2]]` is `[1,2]`.
+fn list_parts<'t, 'a>(mut list: &'t BorrowedTerm<'a>) -> ListParts<'t, 'a> {
+    let mut elements = Vec::new();
+
+    loop {
+        match list {
+            BorrowedTerm::Nil => return (elements, None),
+            BorrowedTerm::String(bytes) => {
+                elements.extend(bytes.iter().map(|&byte| Cow::Owned(BorrowedTerm::Integer(byte.into()))));
+                return (elements, None);
+            },
+            BorrowedTerm::List(terms, tail) => {
+                elements.extend(terms.iter().map(Cow::Borrowed));
+                list = tail;
+            },
+            tail => return (elements, Some(tail)),
+        }
+    }
+}
+
+fn sorted_by_key<'t, 'a>(pairs: &'t [(BorrowedTerm<'a>, BorrowedTerm<'a>)]) -> Vec<&'t (BorrowedTerm<'a>, BorrowedTerm<'a>)> {
+    let mut result: Vec<_> = pairs.iter().collect();
+    result.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+
+    result
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "top level: ")?;
+        } else {
+            write!(f, "{}: ", self.path)?;
+        }
+
+        let (left, right) = match (&self.left, &self.right) {
+            (Some(left), Some(right)) => (left, right),
+            (Some(left), None) => return write!(f, "only in left: {}", single_line(&**left)),
+            (None, Some(right)) => return write!(f, "only in right: {}", single_line(&**right)),
+            (None, None) => return write!(f, "{:?}", self.kind),
+        };
+
+        write!(f, "{} != {}", single_line(&**left), single_line(&**right))?;
+
+        match self.kind {
+            DifferenceKind::Type(l, r) => write!(f, " ({} vs {})", l, r),
+            DifferenceKind::IntegerFloat if left.is::<f64>() => write!(f, " (float vs integer)"),
+            DifferenceKind::IntegerFloat => write!(f, " (integer vs float)"),
+            DifferenceKind::Arity(l, r) => write!(f, " (arity {} vs {})", l, r),
+            DifferenceKind::Length(l, r) => write!(f, " (length {} vs {})", l, r),
+            DifferenceKind::Value | DifferenceKind::OnlyLeft | DifferenceKind::OnlyRight => Ok(()),
+        }
+    }
+}
+
+/// Writes every difference on its own line.
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, difference) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", difference)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ diff, DifferenceKind };
+    use super::super::{ EList, ENil, EString, ETerm };
+    use super::super::path::Segment;
+
+    fn render(left: &dyn ETerm, right: &dyn ETerm) -> String {
+        diff(left, right).unwrap().to_string()
+    }
+
+    #[test]
+    fn equal() {
+        assert!(diff(&eterm!({ok, [1, 2], #{a => 1, b => 2}}), &eterm!({ok, [1, 2], #{b => 2, a => 1}})).unwrap().is_empty());
+        assert!(diff(&EString::new("ab"), &eterm!([97, 98])).unwrap().is_empty());
+        assert!(diff(&ENil, &EList::new(vec![])).unwrap().is_empty());
+        assert!(diff(&eterm!([1 | [2]]), &eterm!([1, 2])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn kinds() {
+        let result = diff(&eterm!({a, 1, [x]}), &eterm!({a, 1.0, {x}})).unwrap();
+        let kinds: Vec<_> = result.iter().map(|difference| difference.kind).collect();
+        assert_eq!(vec![DifferenceKind::IntegerFloat, DifferenceKind::Type("list", "tuple")], kinds);
+
+        let first = &result[0];
+        assert_eq!(&[Segment::Element(2)], &*first.path);
+        assert_eq!("1", first.left.as_ref().unwrap().to_string());
+        assert_eq!(Some(1.0), first.right.as_ref().unwrap().downcast_ref::<f64>().copied());
+
+        assert_eq!(2, (&result).into_iter().filter(|difference| difference.left.is_some()).count());
+        let paths: Vec<String> = result.into_iter().map(|difference| difference.path.to_string()).collect();
+        assert_eq!(vec!["2", "3"], paths);
+    }
+
+    #[test]
+    fn rendering() {
+        assert_eq!("top level: ok != error", render(&eterm!(ok), &eterm!(error)));
+        assert_eq!("top level: {a,b} != {a} (arity 2 vs 1)", render(&eterm!({a, b}), &eterm!({a})));
+        assert_eq!("1: 1.5 != 1 (float vs integer)", render(&eterm!({1.5}), &eterm!({1})));
+        assert_eq!(
+            "[1]: 2 != 3\ntop level: [1,2,3] != [1,3] (length 3 vs 2)",
+            render(&eterm!([1, 2, 3]), &eterm!([1, 3])),
+        );
+        assert_eq!("[1][|]: c != [] (atom vs list)", render(&eterm!([[a], [b | c]]), &eterm!([[a], [b]])));
+        assert_eq!("[1]: 98 != b (integer vs atom)", render(&EString::new("ab"), &eterm!([97, b])));
+        assert_eq!(
+            "count: only in right: 1\nname: <<\"joe\">> != <<\"mike\">>\n(\"s\"): only in left: 2",
+            render(&eterm!(#{name => <<"joe">>, "s" => 2}), &eterm!(#{count => 1, name => <<"mike">>})),
+        );
+        assert_eq!(
+            "1.#id.(1.0): a != b",
+            render(&eterm!({#{<<"id">> => #{1 => x, 1.0 => a}}}), &eterm!({#{<<"id">> => #{1 => x, 1.0 => b}}})),
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Disassembling encoded terms, to see how their bytes are laid out.
+//!
+//! [`disassemble`] writes a line for every (nested) term, with its offset,
+//! the bytes of its tag and header, the name of its tag, its length fields
+//! and, for leaf terms, its value.
+//! Nested terms are indented below the term that contains them:
+//!
+//! ```
+//! use rust_eterm::terms::{ EAtom, ETerm, ETuple };
+//! use rust_eterm::terms::disasm::disassemble;
+//! use rust_eterm::terms::encode::EncodeOptions;
+//!
+//! let term = ETuple::from((EAtom::new("ok"), vec![1000i32].into_iter().map(|i| Box::new(i) as Box<dyn ETerm>).collect::<rust_eterm::terms::EList>()));
+//! let bytes = term.to_external_binary_with(&EncodeOptions::default().with_version_byte(true)).unwrap();
+//!
+//! assert_eq!(disassemble(&bytes), "\
+//! 00000000  83                        ETF_VERSION
+//! 00000001  68 02                     SMALL_TUPLE_EXT arity=2
+//! 00000003  77 02 6f 6b                 SMALL_ATOM_UTF8_EXT len=2 ok
+//! 00000007  6c 00 00 00 01              LIST_EXT len=1
+//! 0000000c  62 00 00 03 e8                INTEGER_EXT 1000
+//! 00000011  6a                            tail: NIL_EXT
+//! ");
+//! ```
+//!
+//! Disassembling does not stop at the first error: everything up to the
+//! offending byte is written, followed by a line with the error, so that a
+//! wrong length field shows up where it is read.
+//!
+//! [`disassemble`]: fn.disassemble.html
+
+use super::{ DistHeaderTag, EAtom, TermTag, ETF_VERSION };
+use super::decode::{ num_vec_to_term, parse_float_ext };
+use super::layout::{ header_size, layout, read_tag };
+use super::super::error::Error;
+
+use std::convert::TryInto;
+use std::fmt::Write;
+use std::io;
+use std::str;
+
+/// The amount of bytes that are shown in hexadecimal for every term.
+const SHOWN_BYTES: usize = 8;
+
+/// The amount of bytes of strings and binaries that are shown as a value.
+const PREVIEW_BYTES: usize = 32;
+
+/// Disassembles the term at the start of `bytes`, which may be a complete
+/// message that starts with the [`ETF_VERSION`] byte.
+///
+/// The bytes of compressed messages are disassembled after decompressing
+/// them, which requires the `compression` feature; the offsets of those
+/// terms are positions in the decompressed bytes.
+///
+/// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+pub fn disassemble(bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    if bytes.first() != Some(&ETF_VERSION) {
+        disassemble_term(bytes, 0, &mut out);
+        return out;
+    }
+
+    line(&mut out, 0, &bytes[..1], 0, "ETF_VERSION");
+
+    let mut start = 1;
+    if bytes.get(start) == Some(&(DistHeaderTag::Local as u8)) {
+        line(&mut out, start, &bytes[start..start + 1], 0, "LOCAL_EXT");
+        start += 1;
+    }
+
+    if bytes.get(start) != Some(&(DistHeaderTag::Compressed as u8)) {
+        disassemble_term(bytes, start, &mut out);
+        return out;
+    }
+
+    match bytes.get(start + 1..start + 5) {
+        Some(size) => {
+            let size = u32::from_be_bytes(size.try_into().expect("4 bytes"));
+            line(&mut out, start, &bytes[start..start + 5], 0, &format!("COMPRESSED uncompressed_size={}", size));
+        },
+        None => {
+            error(&mut out, start, &Error::Io(io::ErrorKind::UnexpectedEof.into()));
+            return out;
+        },
+    }
+
+    #[cfg(feature="compression")]
+    {
+        match super::decode::message_body(&mut &*bytes) {
+            Ok(body) => {
+                out.push_str("-- uncompressed --\n");
+                disassemble_term(&body, 0, &mut out);
+            },
+            Err(e) => error(&mut out, start + 5, &e),
+        }
+    }
+
+    #[cfg(not(feature="compression"))]
+    error(&mut out, start + 5, &Error::Message("Disassembling compressed terms requires the compression feature".to_string()));
+
+    out
+}
+
+/// Disassembles the term at `start`, followed by a line about the bytes after
+/// it, if any.
+fn disassemble_term(bytes: &[u8], start: usize, out: &mut String) {
+    match walk(bytes, start, out) {
+        Ok(end) if end < bytes.len() => {
+            let _ = writeln!(out, "{:08x}  {} trailing bytes", end, bytes.len() - end);
+        },
+        Ok(_) => {},
+        Err((position, e)) => error(out, position, &e),
+    }
+}
+
+fn line(out: &mut String, position: usize, bytes: &[u8], depth: usize, description: &str) {
+    let mut hex: Vec<String> = bytes.iter().take(SHOWN_BYTES).map(|byte| format!("{:02x}", byte)).collect();
+    if bytes.len() > SHOWN_BYTES {
+        hex[SHOWN_BYTES - 1] = "..".to_string();
+    }
+
+    let _ = writeln!(out, "{:08x}  {:<24}  {:indent$}{}", position, hex.join(" "), "", description, indent = 2 * depth);
+}
+
+fn error(out: &mut String, position: usize, error: &Error) {
+    let _ = writeln!(out, "{:08x}  error: {}", position, error);
+}
+
+/// Writes a line for every term, like [`layout::walk`] visits them, and
+/// returns the end of the term or the position and the cause of an error.
+///
+/// [`layout::walk`]: ../layout/fn.walk.html
+fn walk(bytes: &[u8], start: usize, out: &mut String) -> Result<usize, (usize, Error)> {
+    enum Step {
+        /// An amount of nested terms, of which the last is the tail of a
+        /// list if `tail` is set.
+        Terms { amount: usize, depth: usize, tail: bool },
+        /// The bytes after the nested terms of a term with `tag`.
+        Trailing { tag: TermTag, amount: usize, depth: usize },
+    }
+
+    let mut position = start;
+    let mut steps = vec![Step::Terms { amount: 1, depth: 0, tail: false }];
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Terms { amount: 0, .. } => {},
+            Step::Terms { amount, depth, tail } => {
+                steps.push(Step::Terms { amount: amount - 1, depth, tail });
+
+                let tag = read_tag(bytes, position).map_err(|e| (position, e))?;
+                let end = advance(bytes, position, 1 + header_size(tag))?;
+                let header = &bytes[position + 1..end];
+                let layout = layout(tag, header).map_err(|e| (position, e))?;
+                let leading_end = advance(bytes, end, layout.leading).map_err(|(_, e)| (position, e))?;
+
+                let mut description = describe(tag, header, &bytes[end..leading_end]);
+                if tail && amount == 1 {
+                    description = format!("tail: {}", description);
+                }
+
+                line(out, position, &bytes[position..leading_end], depth, &description);
+                position = leading_end;
+
+                // These are handled last-in, first-out.
+                if layout.trailing > 0 {
+                    steps.push(Step::Trailing { tag, amount: layout.trailing, depth: depth + 1 });
+                }
+                steps.push(Step::Terms { amount: layout.terms, depth: depth + 1, tail: tag == TermTag::List });
+            },
+            Step::Trailing { tag, amount, depth } => {
+                let end = advance(bytes, position, amount)?;
+                line(out, position, &bytes[position..end], depth, &describe_trailing(tag, &bytes[position..end]));
+                position = end;
+            },
+        }
+    }
+
+    Ok(position)
+}
+
+fn advance(bytes: &[u8], position: usize, amount: usize) -> Result<usize, (usize, Error)> {
+    match position.checked_add(amount) {
+        Some(end) if end <= bytes.len() => Ok(end),
+        _ => Err((position, Error::Message(format!(
+            "Expected {} more bytes, but only {} are left", amount, bytes.len() - position.min(bytes.len()),
+        )))),
+    }
+}
+
+fn be_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Describes a term by the name of its tag, its header and its leading bytes.
+fn describe(tag: TermTag, header: &[u8], leading: &[u8]) -> String {
+    let details = match tag {
+        TermTag::AtomCacheRef => format!("index={}", leading[0]),
+        TermTag::SmallInteger => leading[0].to_string(),
+        TermTag::Integer => (be_u32(leading) as i32).to_string(),
+        TermTag::Float => parse_float_ext(leading).map(|value| value.to_string()).unwrap_or_else(|e| format!("invalid: {}", e)),
+        TermTag::NewFloat => f64::from_be_bytes(leading.try_into().expect("8 bytes")).to_string(),
+        TermTag::SmallTuple => format!("arity={}", header[0]),
+        TermTag::LargeTuple | TermTag::Map => format!("arity={}", be_u32(header)),
+        TermTag::String => format!("len={} {}", be_u16(header), preview(leading)),
+        TermTag::List => format!("len={}", be_u32(header)),
+        TermTag::Binary => format!("len={} {}", be_u32(header), preview(leading)),
+        TermTag::BitBinary => format!("len={} bits={} {}", be_u32(header), header[4], preview(leading)),
+        TermTag::SmallBig | TermTag::LargeBig => {
+            let (n, sign) = header.split_at(header.len() - 1);
+            let n = if n.len() == 1 { n[0] as u32 } else { be_u32(n) };
+            let value = num_vec_to_term(sign[0] != 0, leading).map(|term| term.to_string()).unwrap_or_else(|e| format!("invalid: {}", e));
+
+            format!("n={} sign={} {}", n, sign[0], value)
+        },
+        TermTag::AtomUtf8 | TermTag::SmallAtomUtf8 => {
+            let name = str::from_utf8(leading).map(|name| EAtom::new(name).to_string()).unwrap_or_else(|e| format!("invalid UTF-8: {}", e));
+            format!("len={} {}", leading.len(), name)
+        },
+        TermTag::Atom | TermTag::SmallAtom => {
+            format!("len={} {}", leading.len(), EAtom::new(leading.iter().map(|&c| c as char).collect::<String>()))
+        },
+        TermTag::NewReference | TermTag::NewerReference => format!("len={}", be_u16(header)),
+        TermTag::Fun => format!("num_free={}", be_u32(header)),
+        TermTag::NewFun => format!("size={}", be_u32(header)),
+        TermTag::Nil | TermTag::Export | TermTag::Port | TermTag::NewPort | TermTag::Pid | TermTag::NewPid | TermTag::Reference => String::new(),
+    };
+
+    if details.is_empty() {
+        tag.name().to_string()
+    } else {
+        format!("{} {}", tag.name(), details)
+    }
+}
+
+/// Describes the bytes after the node of a pid, port or reference.
+fn describe_trailing(tag: TermTag, bytes: &[u8]) -> String {
+    let words: Vec<u32> = bytes.chunks(4).filter(|chunk| chunk.len() == 4).map(be_u32).collect();
+
+    match tag {
+        TermTag::Pid => format!("id={} serial={} creation={}", words[0], words[1], bytes[8]),
+        TermTag::NewPid => format!("id={} serial={} creation={}", words[0], words[1], words[2]),
+        TermTag::Port | TermTag::Reference => format!("id={} creation={}", words[0], bytes[4]),
+        TermTag::NewPort => format!("id={} creation={}", words[0], words[1]),
+        TermTag::NewReference => format!("creation={} ids={:?}", bytes[0], bytes[1..].chunks(4).map(be_u32).collect::<Vec<_>>()),
+        TermTag::NewerReference => format!("creation={} ids={:?}", words[0], &words[1..]),
+        _ => format!("{} bytes", bytes.len()),
+    }
+}
+
+/// Shows bytes as a string when they are printable ASCII, and as a list of
+/// bytes otherwise.
+fn preview(bytes: &[u8]) -> String {
+    let shown = &bytes[..bytes.len().min(PREVIEW_BYTES)];
+    let more = if bytes.len() > shown.len() { "..." } else { "" };
+
+    if shown.iter().all(|&byte| (0x20..0x7f).contains(&byte) && byte != b'"' && byte != b'\\') {
+        format!("\"{}\"{}", str::from_utf8(shown).expect("ASCII"), more)
+    } else {
+        let bytes: Vec<String> = shown.iter().map(|byte| byte.to_string()).collect();
+        format!("<<{}{}>>", bytes.join(","), if more.is_empty() { "" } else { ",..." })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::disassemble;
+
+    #[test]
+    fn leaves() {
+        let cases: Vec<(&[u8], &str)> = vec![
+            (&[97, 255], "SMALL_INTEGER_EXT 255"),
+            (&[98, 255, 255, 255, 255], "INTEGER_EXT -1"),
+            (&[70, 63, 248, 0, 0, 0, 0, 0, 0], "NEW_FLOAT_EXT 1.5"),
+            (&[106], "NIL_EXT"),
+            (&[107, 0, 2, 104, 105], "STRING_EXT len=2 \"hi\""),
+            (&[109, 0, 0, 0, 2, 0, 255], "BINARY_EXT len=2 <<0,255>>"),
+            (&[77, 0, 0, 0, 1, 3, 224], "BIT_BINARY_EXT len=1 bits=3 <<224>>"),
+            (&[110, 2, 1, 0, 1], "SMALL_BIG_EXT n=2 sign=1 -256"),
+            (&[100, 0, 2, 233, 116], "ATOM_EXT len=2 ét"),
+            (&[119, 2, 195, 169], "SMALL_ATOM_UTF8_EXT len=2 é"),
+            (&[118, 0, 1, 255], "ATOM_UTF8_EXT len=1 invalid UTF-8: invalid utf-8 sequence of 1 bytes from index 0"),
+        ];
+
+        for (bytes, expected) in cases {
+            let output = disassemble(bytes);
+            assert_eq!(expected, output[36..].trim_end(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let bytes = [
+            116, 0, 0, 0, 1,
+                88, 119, 3, 97, 64, 98, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3,
+                108, 0, 0, 0, 1, 109, 0, 0, 0, 40,
+                    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+                    21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40,
+                    97, 7,
+            106,
+        ];
+
+        assert_eq!(disassemble(&bytes), "\
+00000000  74 00 00 00 01            MAP_EXT arity=1
+00000005  58                          NEW_PID_EXT
+00000006  77 03 61 40 62                SMALL_ATOM_UTF8_EXT len=3 a@b
+0000000b  00 00 00 01 00 00 00 ..       id=1 serial=2 creation=3
+00000017  6c 00 00 00 01              LIST_EXT len=1
+0000001c  6d 00 00 00 28 01 02 ..       BINARY_EXT len=40 <<1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,...>>
+00000049  61 07                         tail: SMALL_INTEGER_EXT 7
+0000004b  1 trailing bytes
+");
+    }
+
+    #[test]
+    fn errors() {
+        // A tuple that claims three elements, but only has two.
+        assert_eq!(disassemble(&[131, 104, 3, 97, 1, 97, 2]), "\
+00000000  83                        ETF_VERSION
+00000001  68 03                     SMALL_TUPLE_EXT arity=3
+00000003  61 01                       SMALL_INTEGER_EXT 1
+00000005  61 02                       SMALL_INTEGER_EXT 2
+00000007  error: unexpected end of file
+");
+
+        // A binary with a length that is four times too large.
+        assert_eq!(disassemble(&[109, 0, 0, 0, 8, 1, 2]), "\
+00000000  error: Expected 8 more bytes, but only 2 are left
+");
+
+        assert_eq!(disassemble(&[200]), "00000000  error: Unsupported term type\n");
+    }
+
+    #[test]
+    fn local() {
+        assert_eq!(disassemble(&[131, 121, 97, 1]), "\
+00000000  83                        ETF_VERSION
+00000001  79                        LOCAL_EXT
+00000002  61 01                     SMALL_INTEGER_EXT 1
+");
+    }
+
+    #[cfg(feature="compression")]
+    #[test]
+    fn compressed() {
+        use super::super::EString;
+        use super::super::ETerm;
+        use super::super::encode::EncodeOptions;
+
+        let bytes = EString::new("a".repeat(100)).to_external_binary_with(&EncodeOptions::default().with_compression(6)).unwrap();
+
+        assert_eq!(disassemble(&bytes), "\
+00000000  83                        ETF_VERSION
+00000001  50 00 00 00 67            COMPRESSED uncompressed_size=103
+-- uncompressed --
+00000000  6b 00 64 61 61 61 61 ..   STRING_EXT len=100 \"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\"...
+");
+    }
+}
@@ -0,0 +1,420 @@
+//! Formatting terms in Elixir syntax, like Elixir's `inspect/1`.
+//!
+//! | Term                                   | Elixir                      |
+//! |----------------------------------------|-----------------------------|
+//! | `ok`, `'hello world'`                  | `:ok`, `:"hello world"`     |
+//! | `nil`, `true`, `false`                 | `nil`, `true`, `false`      |
+//! | `'Elixir.String'`                      | `String`                    |
+//! | `"text"` (a printable list)            | `~c"text"`                  |
+//! | `<<"text">>` (printable UTF-8)         | `"text"`                    |
+//! | `[{a, 1}, {b, 2}]`                     | `[a: 1, b: 2]`              |
+//! | `#{a => 1}`                            | `%{a: 1}`                   |
+//! | `#{1 => 2}`                            | `%{1 => 2}`                 |
+//! | `#{'__struct__' => 'Elixir.URI', ..}`  | `%URI{..}`                  |
+//! | `fun lists:map/2`                      | `&:lists.map/2`             |
+//! | A pid or a port                        | `#PID<0.1.2>`, `#Port<0.1>` |
+//!
+//! Like in Elixir, pids and ports are written with `0` for their node,
+//! which is what Elixir uses for the local node; use the term's `Display`
+//! implementation to see the node name.
+//!
+//! ```
+//! use rust_eterm::terms::{ EAtom, EBinary, EMap, ETerm, ETuple };
+//! use rust_eterm::terms::elixir::inspect;
+//!
+//! let user: EMap = vec![
+//!     (EAtom::new("name"), EBinary::from("José".as_bytes())),
+//!     (EAtom::new("__struct__"), EBinary::from(&b"User"[..])),
+//! ].into_iter().map(|(k, v)| (Box::new(k) as Box<dyn ETerm>, Box::new(v) as Box<dyn ETerm>)).collect();
+//! assert_eq!("%{__struct__: \"User\", name: \"José\"}", inspect(&user));
+//!
+//! let reply = ETuple::from((EAtom::new("ok"), EAtom::new("Elixir.Enum")));
+//! assert_eq!("{:ok, Enum}", inspect(&reply));
+//! ```
+
+use super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, EPort, ETerm, ETuple, EString };
+use super::convert::{ integer, FromETerm };
+use super::pretty::format_float;
+use super::printing::{ code_points, reencode, sorted_entries, write_separated };
+
+use std::str;
+
+/// Formats `term` in Elixir syntax.
+pub fn inspect(term: &dyn ETerm) -> String {
+    let mut out = String::new();
+    write_term(term, &mut out, false);
+
+    out
+}
+
+/// Writes `term` to `out`, with `decoded` as described for [`reencode`].
+fn write_term(term: &dyn ETerm, out: &mut String, decoded: bool) {
+    if let Some(atom) = term.downcast_ref::<EAtom>() {
+        write_atom(atom.as_str(), out);
+    } else if let Some(list) = term.downcast_ref::<EList>() {
+        write_list(list.as_slice(), None, out);
+    } else if let Some(list) = term.downcast_ref::<ENonProperList>() {
+        write_list(list.data(), Some(list.tail()), out);
+    } else if let Some(string) = term.downcast_ref::<EString>() {
+        write_list(&code_points(string), None, out);
+    } else if term.is::<ENil>() {
+        out.push_str("[]");
+    } else if let Some(tuple) = term.downcast_ref::<ETuple>() {
+        out.push('{');
+        write_separated(tuple.iter(), out, |element, out| write_term(&**element, out, false));
+        out.push('}');
+    } else if let Some(map) = term.downcast_ref::<EMap>() {
+        write_map(map, out);
+    } else if let Some(binary) = term.downcast_ref::<EBinary>() {
+        write_binary(binary.as_bytes(), out);
+    } else if let Some(export) = term.downcast_ref::<EExport>() {
+        out.push('&');
+        write_module(export.module().as_str(), out);
+        out.push('.');
+        if is_identifier(export.function().as_str()) {
+            out.push_str(export.function().as_str());
+        } else {
+            write_quoted(export.function().as_str(), out);
+        }
+        out.push_str(&format!("/{}", export.arity()));
+    } else if let Some(pid) = term.downcast_ref::<EPid>() {
+        out.push_str(&format!("#PID<0.{}.{}>", pid.id(), pid.serial()));
+    } else if let Some(port) = term.downcast_ref::<EPort>() {
+        out.push_str(&format!("#Port<0.{}>", port.id()));
+    } else if let Some(&value) = term.downcast_ref::<f64>() {
+        out.push_str(&format_float(value));
+    } else if let Some(&value) = term.downcast_ref::<f32>() {
+        out.push_str(&format_float(value.into()));
+    } else if decoded || integer(term).is_some() {
+        out.push_str(&term.to_string());
+    } else {
+        match reencode(term) {
+            Some(decoded_term) => write_term(&*decoded_term, out, true),
+            None => out.push_str(&term.to_string()),
+        }
+    }
+}
+
+fn write_list(elements: &[Box<dyn ETerm>], tail: Option<&dyn ETerm>, out: &mut String) {
+    if tail.is_none() {
+        let chars: Option<Vec<u32>> = elements.iter()
+            .map(|element| u32::from_eterm(&**element).ok())
+            .collect();
+
+        match chars {
+            Some(ref chars) if !chars.is_empty() && chars.iter().all(|&c| is_printable_ascii(c)) => {
+                out.push_str("~c");
+                write_quoted(&chars.iter().map(|&c| c as u8 as char).collect::<String>(), out);
+                return;
+            },
+            _ => {},
+        }
+
+        let keywords: Option<Vec<(&str, &dyn ETerm)>> = elements.iter()
+            .map(|element| keyword(&**element))
+            .collect();
+
+        if let Some(keywords) = keywords {
+            out.push('[');
+            write_separated(keywords, out, |(key, value), out| write_pair(key, value, out));
+            out.push(']');
+            return;
+        }
+    }
+
+    out.push('[');
+    write_separated(elements, out, |element, out| write_term(&**element, out, false));
+    if let Some(tail) = tail {
+        out.push_str(" | ");
+        write_term(tail, out, false);
+    }
+    out.push(']');
+}
+
+/// The key and value of a `{key, Value}` tuple in a keyword list.
+fn keyword(term: &dyn ETerm) -> Option<(&str, &dyn ETerm)> {
+    match term.downcast_ref::<ETuple>()?.as_slice() {
+        [key, value] => Some((keyword_key(&**key)?, &**value)),
+        _ => None,
+    }
+}
+
+/// The name of an atom that can be written as `key:`, which excludes
+/// aliases like `Elixir.Mod`, like `Inspect.List.keyword?/1` does.
+fn keyword_key(term: &dyn ETerm) -> Option<&str> {
+    term.downcast_ref::<EAtom>()
+        .map(EAtom::as_str)
+        .filter(|name| !name.starts_with("Elixir."))
+}
+
+/// Writes `key: value`.
+fn write_pair(key: &str, value: &dyn ETerm, out: &mut String) {
+    if is_identifier(key) {
+        out.push_str(key);
+    } else {
+        write_quoted(key, out);
+    }
+
+    out.push_str(": ");
+    write_term(value, out, false);
+}
+
+fn write_map(map: &EMap, out: &mut String) {
+    let mut entries = sorted_entries(map);
+
+    let struct_name = entries.iter()
+        .find(|(key, _)| key.downcast_ref::<EAtom>().map(EAtom::as_str) == Some("__struct__"))
+        .and_then(|(_, value)| value.downcast_ref::<EAtom>());
+
+    out.push('%');
+    if let Some(name) = struct_name {
+        write_module(name.as_str(), out);
+        entries.retain(|(key, _)| key.downcast_ref::<EAtom>().map(EAtom::as_str) != Some("__struct__"));
+    }
+    out.push('{');
+
+    if entries.iter().all(|(key, _)| keyword_key(&**key).is_some()) {
+        write_separated(entries, out, |(key, value), out| {
+            write_pair(keyword_key(&**key).expect("All keys are keywords"), &**value, out)
+        });
+    } else {
+        write_separated(entries, out, |(key, value), out| {
+            write_term(&**key, out, false);
+            out.push_str(" => ");
+            write_term(&**value, out, false);
+        });
+    }
+
+    out.push('}');
+}
+
+fn write_binary(bytes: &[u8], out: &mut String) {
+    match str::from_utf8(bytes) {
+        Ok(text) if text.chars().all(is_printable) => write_quoted(text, out),
+        _ => {
+            out.push_str("<<");
+            write_separated(bytes, out, |byte, out| out.push_str(&byte.to_string()));
+            out.push_str(">>");
+        },
+    }
+}
+
+fn write_atom(name: &str, out: &mut String) {
+    match name {
+        "nil" | "true" | "false" => out.push_str(name),
+        _ if is_alias(name) => out.push_str(&name["Elixir.".len()..]),
+        _ if is_identifier(name) || OPERATORS.contains(&name) => {
+            out.push(':');
+            out.push_str(name);
+        },
+        _ => {
+            out.push(':');
+            write_quoted(name, out);
+        },
+    }
+}
+
+/// Writes the name of a module, which is an alias like `Enum` for Elixir
+/// modules and an atom like `:lists` for the others.
+fn write_module(name: &str, out: &mut String) {
+    write_atom(name, out);
+}
+
+/// The operators that Elixir writes as unquoted atoms, like `:+`.
+const OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "++", "--", "**", "<", ">", "<=", ">=", "==", "!=", "===", "!==", "=~",
+    "&&", "||", "!", "<>", "|>", "..", "<-", "->", "=", "&", "@", "^", "|", "&&&", "|||",
+    "^^^", "<<<", ">>>", "~~~", "<~", "~>", "<~>", "<|>",
+];
+
+/// Whether `name` can be written as `:name` and as a keyword key: a letter
+/// or `_`, followed by letters, digits, `_` and `@`, optionally ending in `?`
+/// or `!`.
+fn is_identifier(name: &str) -> bool {
+    let name = name.strip_suffix(|c| c == '?' || c == '!').unwrap_or(name);
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '@')
+}
+
+/// Whether `name` is the name of an Elixir module, like `Elixir.Enum`.
+fn is_alias(name: &str) -> bool {
+    match name.strip_prefix("Elixir.") {
+        Some(alias) => alias.split('.').all(|part| {
+            let mut chars = part.chars();
+
+            chars.next().is_some_and(|c| c.is_ascii_uppercase())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }),
+        None => false,
+    }
+}
+
+/// Whether a character is printable in a binary, like `String.printable?/1`.
+fn is_printable(c: char) -> bool {
+    match c {
+        '\u{7}'..='\r' | '\u{1b}' => true,
+        '\0'..='\u{1f}' | '\u{7f}'..='\u{9f}' | '\u{fffe}' | '\u{ffff}' => false,
+        _ => true,
+    }
+}
+
+/// Whether a code point is printable in a charlist, like
+/// `List.ascii_printable?/1`.
+fn is_printable_ascii(c: u32) -> bool {
+    matches!(c, 0x07..=0x0d | 0x1b | 0x20..=0x7e)
+}
+
+/// Writes `text` between double quotes.
+fn write_quoted(text: &str, out: &mut String) {
+    out.push('"');
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '#' if chars.peek() == Some(&'{') => out.push_str("\\#"),
+            '\0' => out.push_str("\\0"),
+            '\u{7}' => out.push_str("\\a"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{b}' => out.push_str("\\v"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            '\u{1b}' => out.push_str("\\e"),
+            _ if is_printable(c) => out.push(c),
+            _ => out.push_str(&format!("\\x{:02X}", c as u32)),
+        }
+    }
+
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inspect;
+    use super::super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, EPort, ETerm, ETuple, EString };
+
+    fn atom(name: &str) -> Box<dyn ETerm> {
+        Box::new(EAtom::new(name))
+    }
+
+    fn map(entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)>) -> EMap {
+        EMap::new(entries)
+    }
+
+    #[test]
+    fn atoms() {
+        let cases = [
+            ("ok", ":ok"),
+            ("nil", "nil"),
+            ("true", "true"),
+            ("false", "false"),
+            ("undefined", ":undefined"),
+            ("Hello", ":Hello"),
+            ("_private", ":_private"),
+            ("valid?", ":valid?"),
+            ("save!", ":save!"),
+            ("a@b", ":a@b"),
+            ("été", ":été"),
+            ("+", ":+"),
+            ("<>", ":<>"),
+            ("Elixir.Enum", "Enum"),
+            ("Elixir.Phoenix.Controller", "Phoenix.Controller"),
+            ("Elixir", ":Elixir"),
+            ("Elixir.lower", ":\"Elixir.lower\""),
+            ("hello world", ":\"hello world\""),
+            ("a-b", ":\"a-b\""),
+            ("1a", ":\"1a\""),
+            ("?", ":\"?\""),
+            ("a?b", ":\"a?b\""),
+            ("", ":\"\""),
+            ("say \"hi\"", ":\"say \\\"hi\\\"\""),
+            ("#{x}", ":\"\\#{x}\""),
+        ];
+
+        for (name, expected) in cases.iter() {
+            assert_eq!(*expected, inspect(&EAtom::new(*name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!("42", inspect(&42u8));
+        assert_eq!("-7", inspect(&-7i64));
+        assert_eq!("1.0", inspect(&1.0f64));
+        assert_eq!("1.0e20", inspect(&1e20f64));
+        assert_eq!("0.5", inspect(&0.5f32));
+    }
+
+    #[test]
+    fn lists_and_strings() {
+        let chars = |text: &str| -> EList { text.chars().map(|c| Box::new(c as u32) as Box<dyn ETerm>).collect() };
+
+        assert_eq!("[]", inspect(&ENil));
+        assert_eq!("~c\"hello\\n\"", inspect(&chars("hello\n")));
+        assert_eq!("~c\"abc\"", inspect(&EString::new("abc")));
+        assert_eq!("[233]", inspect(&chars("é")));
+        assert_eq!("[1, 2, 3]", inspect(&chars("\u{1}\u{2}\u{3}")));
+        assert_eq!("[1 | 2]", inspect(&ENonProperList::new(vec![Box::new(1u8)], Box::new(2u8))));
+
+        assert_eq!("\"José\"", inspect(&EBinary::from("José".as_bytes())));
+        assert_eq!("\"\"", inspect(&EBinary::new(vec![])));
+        assert_eq!("\"tab\\tquote\\\"\"", inspect(&EBinary::from(&b"tab\tquote\""[..])));
+        assert_eq!("<<0, 1>>", inspect(&EBinary::new(vec![0, 1])));
+        assert_eq!("<<255>>", inspect(&EBinary::new(vec![255])));
+    }
+
+    #[test]
+    fn keyword_lists() {
+        let keywords: EList = vec![
+            Box::new(ETuple::from((EAtom::new("a"), 1u8))) as Box<dyn ETerm>,
+            Box::new(ETuple::from((EAtom::new("my key"), EAtom::new("nil")))),
+        ].into_iter().collect();
+        assert_eq!("[a: 1, \"my key\": nil]", inspect(&keywords));
+
+        let aliases: EList = vec![
+            Box::new(ETuple::from((EAtom::new("Elixir.Mod"), ENil))) as Box<dyn ETerm>,
+        ].into_iter().collect();
+        assert_eq!("[{Mod, []}]", inspect(&aliases));
+
+        let not_keywords: EList = vec![
+            Box::new(ETuple::from((EAtom::new("a"), 1u8))) as Box<dyn ETerm>,
+            Box::new(ETuple::from((1u8, 2u8))),
+        ].into_iter().collect();
+        assert_eq!("[{:a, 1}, {1, 2}]", inspect(&not_keywords));
+    }
+
+    #[test]
+    fn maps_and_structs() {
+        assert_eq!("%{}", inspect(&map(vec![])));
+        assert_eq!("%{a: 1, b: :c}", inspect(&map(vec![(atom("b"), atom("c")), (atom("a"), Box::new(1u8))])));
+        assert_eq!("%{1 => 2, :a => 3}", inspect(&map(vec![(atom("a"), Box::new(3u8)), (Box::new(1u8), Box::new(2u8))])));
+        assert_eq!("%{Mod => 1, :a => 2}", inspect(&map(vec![(atom("Elixir.Mod"), Box::new(1u8)), (atom("a"), Box::new(2u8))])));
+
+        let uri = map(vec![
+            (atom("__struct__"), atom("Elixir.URI")),
+            (atom("host"), Box::new(EBinary::from(&b"example.com"[..]))),
+            (atom("port"), Box::new(443u16)),
+        ]);
+        assert_eq!("%URI{host: \"example.com\", port: 443}", inspect(&uri));
+        assert_eq!("%:record{}", inspect(&map(vec![(atom("__struct__"), atom("record"))])));
+    }
+
+    #[test]
+    fn other_terms() {
+        assert_eq!("{:ok, {}}", inspect(&ETuple::from((EAtom::new("ok"), ETuple::new(vec![])))));
+        assert_eq!("#PID<0.84.0>", inspect(&EPid::new("node@host", 84, 0, 1)));
+        assert_eq!("#Port<0.5>", inspect(&EPort::new("node@host", 5, 1)));
+        assert_eq!("&:lists.map/2", inspect(&EExport::new("lists", "map", 2)));
+        assert_eq!("&Enum.map/2", inspect(&EExport::new("Elixir.Enum", "map", 2)));
+        assert_eq!("{true, ~c\"text\"}", inspect(&ETuple::from((true, "text".to_string()))));
+    }
+}
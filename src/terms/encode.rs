@@ -4,7 +4,12 @@ use {
     num_traits::{ sign::Signed, cast::ToPrimitive },
 };
 
+#[cfg(feature="compression")]
+use flate2::{ write::ZlibEncoder, Compression };
+
 use super::{
+    Convention,
+    DistHeaderTag,
     EList,
     ENil,
     ENonProperList,
@@ -18,309 +23,1004 @@ use super::{
     EMap,
     EBinary,
     TermTag,
+    ETF_VERSION,
 };
+use super::borrowed;
+use super::layout::term_size;
+use super::order::compare_keys;
 use super::super::error::{ Error };
 
+use std::convert::TryFrom;
 use std::io::Write;
 
 /// Replacement for `std::convert::Into<T>` that doesn't require `Sized`.
 pub trait ToExternalBinary {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error>;
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error>;
+
+    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
+        self.to_writer_with(writer, &EncodeOptions::default())
+    }
+
+    /// The exact amount of bytes [`to_writer_with`] writes with `options`.
+    ///
+    /// The types in this crate compute this without encoding anything.
+    /// Other implementors get a default that encodes into a byte counter.
+    ///
+    /// [`to_writer_with`]: #tymethod.to_writer_with
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut counter = ByteCounter(0);
+        self.to_writer_with(&mut counter, options)?;
+
+        Ok(counter.0)
+    }
+
+    /// The exact amount of bytes [`to_writer`] writes.
+    ///
+    /// [`to_writer`]: #method.to_writer
+    fn encoded_size(&self) -> Result<usize, Error> {
+        self.encoded_size_with(&EncodeOptions::default())
+    }
+
+    /// Whether this is a `()`, which `Result` encodes as a bare atom.
+    #[doc(hidden)]
+    fn is_unit(&self) -> bool {
+        false
+    }
+}
+
+/// A writer that only counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The tags used for encoding atoms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtomEncoding {
+    /// Always use [`ATOM_UTF8_EXT`] or [`SMALL_ATOM_UTF8_EXT`].
+    ///
+    /// [`ATOM_UTF8_EXT`]: ../enum.TermTag.html#variant.AtomUtf8
+    /// [`SMALL_ATOM_UTF8_EXT`]: ../enum.TermTag.html#variant.SmallAtomUtf8
+    Utf8,
+    /// Use the deprecated [`ATOM_EXT`] or [`SMALL_ATOM_EXT`] for atoms that
+    /// can be represented in LATIN-1, and UTF-8 tags for all other atoms.
+    ///
+    /// This is what `term_to_binary/2` does with `{minor_version, 1}`, and
+    /// what peers that predate OTP 20 expect.
+    ///
+    /// [`ATOM_EXT`]: ../enum.TermTag.html#variant.Atom
+    /// [`SMALL_ATOM_EXT`]: ../enum.TermTag.html#variant.SmallAtom
+    Latin1,
+}
+
+/// The tags used for encoding floats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatEncoding {
+    /// Use the 8-byte IEEE 754 [`NEW_FLOAT_EXT`].
+    ///
+    /// [`NEW_FLOAT_EXT`]: ../enum.TermTag.html#variant.NewFloat
+    Binary,
+    /// Use the deprecated [`FLOAT_EXT`], which stores the float as text.
+    ///
+    /// This is what `term_to_binary/2` does with `{minor_version, 0}`.
+    ///
+    /// [`FLOAT_EXT`]: ../enum.TermTag.html#variant.Float
+    Text,
+}
+
+/// Options that influence how terms are encoded, mirroring the options of
+/// `erlang:term_to_binary/2`.
+///
+/// The defaults match `term_to_binary/1` of OTP 26, except that no
+/// [`ETF_VERSION`] byte is written, as most of this crate's API works on
+/// unversioned terms.
+///
+/// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+#[derive(Clone, Debug)]
+pub struct EncodeOptions {
+    atom_encoding: AtomEncoding,
+    float_encoding: FloatEncoding,
+    deterministic: bool,
+    version_byte: bool,
+    compression: u32,
+    local: bool,
+    convention: Convention,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> EncodeOptions {
+        EncodeOptions {
+            atom_encoding: AtomEncoding::Utf8,
+            float_encoding: FloatEncoding::Binary,
+            deterministic: false,
+            version_byte: false,
+            compression: 0,
+            local: false,
+            convention: Convention::Erlang,
+        }
+    }
+}
+
+impl EncodeOptions {
+    pub fn atom_encoding(&self) -> AtomEncoding {
+        self.atom_encoding
+    }
+
+    pub fn with_atom_encoding(mut self, atom_encoding: AtomEncoding) -> EncodeOptions {
+        self.atom_encoding = atom_encoding;
+        self
+    }
+
+    pub fn float_encoding(&self) -> FloatEncoding {
+        self.float_encoding
+    }
+
+    pub fn with_float_encoding(mut self, float_encoding: FloatEncoding) -> EncodeOptions {
+        self.float_encoding = float_encoding;
+        self
+    }
+
+    /// Sets the atom and float encodings to what `term_to_binary/2` uses
+    /// with `{minor_version, Version}`.
+    ///
+    /// | `minor_version` | Atoms                  | Floats                  |
+    /// | --------------- | ---------------------- | ----------------------- |
+    /// | `0`             | [`AtomEncoding::Latin1`] | [`FloatEncoding::Text`]   |
+    /// | `1`             | [`AtomEncoding::Latin1`] | [`FloatEncoding::Binary`] |
+    /// | `2`             | [`AtomEncoding::Utf8`]   | [`FloatEncoding::Binary`] |
+    ///
+    /// # Panics
+    ///
+    /// Panics if `minor_version` is not 0, 1 or 2.
+    ///
+    /// [`AtomEncoding::Latin1`]: enum.AtomEncoding.html#variant.Latin1
+    /// [`AtomEncoding::Utf8`]: enum.AtomEncoding.html#variant.Utf8
+    /// [`FloatEncoding::Text`]: enum.FloatEncoding.html#variant.Text
+    /// [`FloatEncoding::Binary`]: enum.FloatEncoding.html#variant.Binary
+    pub fn with_minor_version(self, minor_version: u8) -> EncodeOptions {
+        match minor_version {
+            0 => self.with_atom_encoding(AtomEncoding::Latin1).with_float_encoding(FloatEncoding::Text),
+            1 => self.with_atom_encoding(AtomEncoding::Latin1).with_float_encoding(FloatEncoding::Binary),
+            2 => self.with_atom_encoding(AtomEncoding::Utf8).with_float_encoding(FloatEncoding::Binary),
+            _ => panic!("Minor version must be 0, 1 or 2, not {}", minor_version),
+        }
+    }
+
+    pub fn version_byte(&self) -> bool {
+        self.version_byte
+    }
+
+    /// Starts complete messages with the [`ETF_VERSION`] byte, like
+    /// `term_to_binary/2` does.
+    ///
+    /// This only affects [`ETerm::write_to_with`] and
+    /// [`ETerm::to_external_binary_with`], not the nested terms.
+    ///
+    /// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+    /// [`ETerm::write_to_with`]: ../trait.ETerm.html#method.write_to_with
+    /// [`ETerm::to_external_binary_with`]: ../trait.ETerm.html#method.to_external_binary_with
+    pub fn with_version_byte(mut self, version_byte: bool) -> EncodeOptions {
+        self.version_byte = version_byte;
+        self
+    }
+
+    /// The zlib compression level, where 0 means no compression.
+    pub fn compression(&self) -> u32 {
+        self.compression
+    }
+
+    /// Compresses complete messages with zlib at `level`, like
+    /// `term_to_binary(Term, [{compressed, Level}])`.
+    ///
+    /// Level 0 disables compression, 1 is the fastest and 9 gives the best
+    /// compression; `term_to_binary(Term, [compressed])` uses 6.
+    /// Compressed messages always start with the [`ETF_VERSION`] byte.
+    /// Just like `term_to_binary/2`, the term is left uncompressed when
+    /// compressing it would not make it smaller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is larger than 9.
+    ///
+    /// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+    #[cfg(feature="compression")]
+    pub fn with_compression(mut self, level: u32) -> EncodeOptions {
+        assert!(level <= 9, "Compression level must be between 0 and 9, not {}", level);
+
+        self.compression = level;
+        self
+    }
+
+    pub fn local(&self) -> bool {
+        self.local
+    }
+
+    /// Marks complete messages with the [`LOCAL_EXT`] tag, like
+    /// `term_to_binary(Term, [local])`.
+    ///
+    /// Erlang only decodes such messages when they were encoded by the same
+    /// runtime instance, so `binary_to_term/1` rejects the messages this
+    /// crate writes with this option.
+    /// [`decode_message`] accepts them, which makes this a way of marking
+    /// messages that must not leave the process that wrote them.
+    /// The tag follows the [`ETF_VERSION`] byte and precedes compression.
+    ///
+    /// [`LOCAL_EXT`]: ../enum.DistHeaderTag.html#variant.Local
+    /// [`decode_message`]: ../decode/fn.decode_message.html
+    /// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+    pub fn with_local(mut self, local: bool) -> EncodeOptions {
+        self.local = local;
+        self
+    }
+
+    pub fn convention(&self) -> Convention {
+        self.convention
+    }
+
+    /// Chooses how `None` and Rust strings are encoded.
+    pub fn with_convention(mut self, convention: Convention) -> EncodeOptions {
+        self.convention = convention;
+        self
+    }
+
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Encodes equal terms to identical bytes, like
+    /// `term_to_binary(Term, [deterministic])`.
+    ///
+    /// Map entries are written in the Erlang term order of their keys, rather
+    /// than in insertion order.
+    /// Lists are written the way Erlang would write them: nested tails are
+    /// flattened, proper lists of bytes become a [`STRING_EXT`] and empty
+    /// lists and strings become [`NIL_EXT`].
+    ///
+    /// [`STRING_EXT`]: ../enum.TermTag.html#variant.String
+    /// [`NIL_EXT`]: ../enum.TermTag.html#variant.Nil
+    pub fn with_deterministic(mut self, deterministic: bool) -> EncodeOptions {
+        self.deterministic = deterministic;
+        self
+    }
 }
 
 impl ToExternalBinary for i8 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i32).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i32).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i32).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for u8 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
+    fn to_writer_with(&self, writer: &mut dyn Write, _options: &EncodeOptions) -> Result<usize, Error> {
         let data: &[u8; 1] = &self.to_be_bytes();
 
-        Ok(writer.write(&[TermTag::SmallInteger as u8, data[0]])?)
+        write_bytes(writer, &[TermTag::SmallInteger as u8, data[0]])
+    }
+
+    fn encoded_size_with(&self, _options: &EncodeOptions) -> Result<usize, Error> {
+        Ok(2)
     }
 }
 
 impl ToExternalBinary for i16 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i32).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i32).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i32).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for u16 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i32).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i32).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i32).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for i32 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        if *self <= u8::max_value().into() && *self >= 0 {
-            (*self as u8).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        if *self <= u8::MAX.into() && *self >= 0 {
+            (*self as u8).to_writer_with(writer, options)
         } else {
-            let mut amount = writer.write(&[TermTag::Integer as u8])?;
-            amount += writer.write(&self.to_be_bytes())?;
+            let mut amount = write_bytes(writer, &[TermTag::Integer as u8])?;
+            amount += write_bytes(writer, &self.to_be_bytes())?;
 
             Ok(amount)
         }
     }
+
+    fn encoded_size_with(&self, _options: &EncodeOptions) -> Result<usize, Error> {
+        if *self <= u8::MAX.into() && *self >= 0 {
+            Ok(2)
+        } else {
+            Ok(5)
+        }
+    }
 }
 
 impl ToExternalBinary for u32 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i128).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for i64 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i128).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for u64 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i128).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for i128 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        if *self <= i32::max_value().into() && *self >= i32::min_value().into() {
-            (*self as i32).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        if *self <= i32::MAX.into() && *self >= i32::MIN.into() {
+            (*self as i32).to_writer_with(writer, options)
         } else {
             let mut abs = lossless_abs(*self);
 
             let bytes: u8 = (16 - (abs.leading_zeros() >> 3)) as u8;
             let sign: u8 = if *self >= 0 { 0 } else { 1 };
 
-            let mut amount = writer.write(&[TermTag::SmallBig as u8, bytes, sign])?;
+            let mut amount = write_bytes(writer, &[TermTag::SmallBig as u8, bytes, sign])?;
 
             while abs != 0 {
-                amount += writer.write(&[(abs & 0xff) as u8])?;
+                amount += write_bytes(writer, &[(abs & 0xff) as u8])?;
                 abs >>= 8;
             }
 
             Ok(amount)
         }
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        if *self <= i32::MAX.into() && *self >= i32::MIN.into() {
+            (*self as i32).encoded_size_with(options)
+        } else {
+            Ok(3 + (16 - (lossless_abs(*self).leading_zeros() >> 3)) as usize)
+        }
+    }
 }
 
 impl ToExternalBinary for u128 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        // The big number here is i128::max_value() as a `From<u128>` for i128 is not implemented
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        // The big number here is i128::MAX as a `From<u128>` for i128 is not implemented
         if *self <= 170_141_183_460_469_231_731_687_303_715_884_105_727u128 {
-            (*self as i128).to_writer(writer)
+            (*self as i128).to_writer_with(writer, options)
         } else {
             let mut tmp = *self;
 
             let bytes: u8 = (16 - (tmp.leading_zeros() >> 3)) as u8;
-            let mut amount = writer.write(&[TermTag::SmallBig as u8, bytes, 0u8])?;
+            let mut amount = write_bytes(writer, &[TermTag::SmallBig as u8, bytes, 0u8])?;
 
             while tmp != 0 {
-                amount += writer.write(&[(tmp & 0xff) as u8])?;
+                amount += write_bytes(writer, &[(tmp & 0xff) as u8])?;
                 tmp >>= 8;
             }
 
             Ok(amount)
         }
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        if *self <= i128::MAX as u128 {
+            (*self as i128).encoded_size_with(options)
+        } else {
+            Ok(3 + (16 - (self.leading_zeros() >> 3)) as usize)
+        }
+    }
 }
 
 impl ToExternalBinary for isize {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as i128).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as i128).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for usize {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as u128).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as u128).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as u128).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for ENil {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        Ok(writer.write(&[TermTag::Nil as u8])?)
+    fn to_writer_with(&self, writer: &mut dyn Write, _options: &EncodeOptions) -> Result<usize, Error> {
+        write_bytes(writer, &[TermTag::Nil as u8])
+    }
+
+    fn encoded_size_with(&self, _options: &EncodeOptions) -> Result<usize, Error> {
+        Ok(1)
     }
 }
 
-impl<'a> ToExternalBinary for EList {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        if self.0.is_empty() {
-            ENil::default().to_writer(writer)
+impl ToExternalBinary for EList {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        if options.deterministic {
+            canonical_list_to_writer(&self.0, &ENil, writer, options)
+        } else if self.0.is_empty() {
+            ENil.to_writer_with(writer, options)
         } else {
             let len: [u8; 4] = (self.0.len() as i32).to_be_bytes();
-            let mut amount = writer.write(&[TermTag::List as u8, len[0], len[1], len[2], len[3]])?;
+            let mut amount = write_bytes(writer, &[TermTag::List as u8, len[0], len[1], len[2], len[3]])?;
 
             for d in self.0.iter() {
-                amount += d.to_writer(writer)?;
+                amount += d.to_writer_with(writer, options)?;
             }
 
-            amount += (ENil {}).to_writer(writer)?;
+            amount += (ENil {}).to_writer_with(writer, options)?;
             
             Ok(amount)
         }
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        if options.deterministic {
+            // Whether the list turns into a STRING_EXT depends on how each
+            // element is encoded.
+            let mut counter = ByteCounter(0);
+            canonical_list_to_writer(&self.0, &ENil, &mut counter, options)?;
+
+            Ok(counter.0)
+        } else if self.0.is_empty() {
+            Ok(1)
+        } else {
+            Ok(5 + sum_of_sizes(self.0.iter(), options)? + 1)
+        }
+    }
 }
 
-impl<'a> ToExternalBinary for ENonProperList {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
+impl ToExternalBinary for ENonProperList {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        if options.deterministic {
+            return canonical_list_to_writer(&self.data, &*self.tail, writer, options);
+        }
+
         let len: [u8; 4] = (self.data.len() as i32).to_be_bytes();
-        let mut written = writer.write(&([TermTag::List as u8, len[0], len[1], len[2], len[3]]))?;
+        let mut written = write_bytes(writer, &([TermTag::List as u8, len[0], len[1], len[2], len[3]]))?;
 
         for d in self.data.iter() {
-            written += d.to_writer(writer)?;
+            written += d.to_writer_with(writer, options)?;
         }
 
-        written += self.tail.to_writer(writer)?;
+        written += self.tail.to_writer_with(writer, options)?;
 
         Ok(written)
     }
-}
-
-impl ToExternalBinary for EAtom {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let byte_length = self.0.as_bytes().len();
 
-        if byte_length <= u8::max_value().into() {
-            let mut written = writer.write(&[TermTag::SmallAtomUtf8 as u8, byte_length as u8])?;
-            written += writer.write(self.0.as_bytes())?;
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        if options.deterministic {
+            let mut counter = ByteCounter(0);
+            canonical_list_to_writer(&self.data, &*self.tail, &mut counter, options)?;
 
-            Ok(written)
-        } else if byte_length <= u16::max_value().into() {
-            let len: [u8; 8] = byte_length.to_be_bytes();
-            let mut written = writer.write(&[TermTag::AtomUtf8 as u8, len[6], len[7]])?;
-            written += writer.write(self.0.as_bytes())?;
-
-            Ok(written)
+            Ok(counter.0)
         } else {
-            Err(Error::Message(self.0.to_owned()))
+            Ok(5 + sum_of_sizes(self.data.iter(), options)? + self.tail.encoded_size_with(options)?)
         }
     }
 }
 
+impl ToExternalBinary for EAtom {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        write_atom(&self.0, writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        atom_size(&self.0, options)
+    }
+}
+
 impl ToExternalBinary for f32 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        (*self as f64).to_writer(writer)
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as f64).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as f64).encoded_size_with(options)
     }
 }
 
 impl ToExternalBinary for f64 {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        if self.is_finite() {
-            let bytes = self.to_be_bytes();
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        if !self.is_finite() {
+            Err(Error::Message(self.to_string()))
+        } else if options.float_encoding == FloatEncoding::Text {
+            let mut written = write_bytes(writer, &[TermTag::Float as u8])?;
+            written += write_bytes(writer, &float_ext_text(*self))?;
 
-            Ok(writer.write(&[TermTag::NewFloat as u8, bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])?)
+            Ok(written)
         } else {
+            let bytes = self.to_be_bytes();
+
+            write_bytes(writer, &[TermTag::NewFloat as u8, bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+        }
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        if !self.is_finite() {
             Err(Error::Message(self.to_string()))
+        } else if options.float_encoding == FloatEncoding::Text {
+            Ok(32)
+        } else {
+            Ok(9)
         }
     }
 }
 
 impl ToExternalBinary for EExport {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let mut written = writer.write(&[TermTag::Export as u8])?;
-        written += self.module.to_writer(writer)?;
-        written += self.function.to_writer(writer)?;
-        written += self.arity.to_writer(writer)?;
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_bytes(writer, &[TermTag::Export as u8])?;
+        written += self.module.to_writer_with(writer, options)?;
+        written += self.function.to_writer_with(writer, options)?;
+        written += self.arity.to_writer_with(writer, options)?;
 
         Ok(written)
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        Ok(1 + self.module.encoded_size_with(options)? + self.function.encoded_size_with(options)? + self.arity.encoded_size_with(options)?)
+    }
 }
 
 impl ToExternalBinary for ETuple {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let len = (self.0.len() as u32).to_be_bytes();
-        let mut written = 0;
-
-        if self.0.len() <= u8::max_value().into() {
-            written += writer.write(&[TermTag::SmallTuple as u8, len[3]])?;
-        } else {
-            written += writer.write(&[TermTag::LargeTuple as u8, len[0], len[1], len[2], len[3]])?;
-        }
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_tuple_header(writer, self.0.len())?;
 
         for d in self.0.iter() {
-            written += d.to_writer(writer)?;
+            written += d.to_writer_with(writer, options)?;
         }
 
         Ok(written)
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        Ok(tuple_header_size(self.0.len()) + sum_of_sizes(self.0.iter(), options)?)
+    }
 }
 
 impl ToExternalBinary for EString {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let byte_length = self.0.as_bytes().len();
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let byte_length = self.0.len();
 
-        if byte_length <= u16::max_value().into() {
+        if byte_length == 0 && options.deterministic {
+            ENil.to_writer_with(writer, options)
+        } else if byte_length <= u16::MAX.into() {
             let len: [u8; 8] = byte_length.to_be_bytes();
-            let mut written = writer.write(&[TermTag::String as u8, len[6], len[7]])?;
-            written += writer.write(self.0.as_bytes())?;
+            let mut written = write_bytes(writer, &[TermTag::String as u8, len[6], len[7]])?;
+            written += write_bytes(writer, self.0.as_bytes())?;
 
             Ok(written)
         } else {
-            EList(self.0.as_bytes().iter().map(|x| Box::from(*x) as Box<dyn ETerm>).collect()).to_writer(writer)
+            EList(self.0.as_bytes().iter().map(|x| Box::from(*x) as Box<dyn ETerm>).collect()).to_writer_with(writer, options)
+        }
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        let byte_length = self.0.len();
+
+        if byte_length == 0 && options.deterministic {
+            Ok(1)
+        } else if byte_length <= u16::MAX.into() {
+            Ok(3 + byte_length)
+        } else {
+            // A LIST_EXT of SMALL_INTEGER_EXTs, both with and without the
+            // deterministic option.
+            Ok(5 + 2 * byte_length + 1)
         }
     }
 }
 
 impl ToExternalBinary for EPort {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let mut written = writer.write(&[TermTag::NewPort as u8])?;
-        written += self.node.to_writer(writer)?;
-        written += writer.write(&self.id.to_be_bytes())?;
-        written += writer.write(&self.creation.to_be_bytes())?;
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_bytes(writer, &[TermTag::NewPort as u8])?;
+        written += self.node.to_writer_with(writer, options)?;
+        written += write_bytes(writer, &self.id.to_be_bytes())?;
+        written += write_bytes(writer, &self.creation.to_be_bytes())?;
 
         Ok(written)
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        Ok(1 + self.node.encoded_size_with(options)? + 4 + 4)
+    }
 }
 
 impl ToExternalBinary for EPid {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let mut written = writer.write(&[TermTag::NewPid as u8])?;
-        written += self.node.to_writer(writer)?;
-        written += writer.write(&self.id.to_be_bytes())?;
-        written += writer.write(&self.serial.to_be_bytes())?;
-        written += writer.write(&self.creation.to_be_bytes())?;
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_bytes(writer, &[TermTag::NewPid as u8])?;
+        written += self.node.to_writer_with(writer, options)?;
+        written += write_bytes(writer, &self.id.to_be_bytes())?;
+        written += write_bytes(writer, &self.serial.to_be_bytes())?;
+        written += write_bytes(writer, &self.creation.to_be_bytes())?;
         
         Ok(written)
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        Ok(1 + self.node.encoded_size_with(options)? + 4 + 4 + 4)
+    }
 }
 
-impl<'a> ToExternalBinary for EMap {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let mut written = writer.write(&[TermTag::Map as u8])?;
-        written += writer.write(&(self.0.len() as u32).to_be_bytes())?;
+impl ToExternalBinary for EMap {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let mut written = write_map_header(writer, self.0.len())?;
+
+        if options.deterministic {
+            // Keys are sorted by decoding their encoded form, which puts all
+            // equivalent representations of a key on an equal footing.
+            let keys = self.0.iter()
+                .map(|(k, _)| to_vec(&**k, options))
+                .collect::<Result<Vec<Vec<u8>>, Error>>()?;
+            let decoded = keys.iter()
+                .map(|k| borrowed::decode(&mut k.as_slice()))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let mut order: Vec<usize> = (0..keys.len()).collect();
+            order.sort_by(|&a, &b| compare_keys(&decoded[a], &decoded[b]));
+
+            for i in order {
+                written += write_bytes(writer, &keys[i])?;
+                written += self.0[i].1.to_writer_with(writer, options)?;
+            }
+
+            return Ok(written);
+        }
 
         for (k, v) in self.0.iter() {
-            written += k.to_writer(writer)?;
-            written += v.to_writer(writer)?;
+            written += k.to_writer_with(writer, options)?;
+            written += v.to_writer_with(writer, options)?;
         };
 
         Ok(written)
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        // Sorting the keys does not change the size of the map.
+        let mut size = 5;
+
+        for (k, v) in self.0.iter() {
+            size += k.encoded_size_with(options)? + v.encoded_size_with(options)?;
+        }
+
+        Ok(size)
+    }
 }
 
 impl ToExternalBinary for EBinary {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
-        let mut written = writer.write(&[TermTag::Binary as u8])?;
-        written += writer.write(&self.0.len().to_be_bytes())?;
-        written += writer.write(self.0.as_ref())?;
+    fn to_writer_with(&self, writer: &mut dyn Write, _options: &EncodeOptions) -> Result<usize, Error> {
+        write_binary(&self.0, writer)
+    }
+
+    fn encoded_size_with(&self, _options: &EncodeOptions) -> Result<usize, Error> {
+        binary_size(&self.0)
+    }
+}
+
+impl ToExternalBinary for bool {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        write_atom(if *self { "true" } else { "false" }, writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        atom_size(if *self { "true" } else { "false" }, options)
+    }
+}
+
+/// `()` is the atom `ok`, which is what Erlang functions without a
+/// meaningful result return, or `nil` for Gleam.
+impl ToExternalBinary for () {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        write_atom(options.convention.unit(), writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        atom_size(options.convention.unit(), options)
+    }
+
+    fn is_unit(&self) -> bool {
+        true
+    }
+}
+
+/// Characters are integers, like `$a` in Erlang.
+impl ToExternalBinary for char {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as u32).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (*self as u32).encoded_size_with(options)
+    }
+}
+
+/// Strings are lists of code points or UTF-8 binaries, depending on the
+/// [`Convention`].
+///
+/// [`Convention`]: ../enum.Convention.html
+impl ToExternalBinary for str {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        match options.convention {
+            Convention::Erlang => write_code_points(self, writer, options),
+            Convention::Elixir | Convention::Gleam => write_binary(self.as_bytes(), writer),
+        }
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        match options.convention {
+            Convention::Erlang => {
+                let mut counter = ByteCounter(0);
+                write_code_points(self, &mut counter, options)?;
+
+                Ok(counter.0)
+            },
+            Convention::Elixir | Convention::Gleam => binary_size(self.as_bytes()),
+        }
+    }
+}
+
+impl ToExternalBinary for String {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        self.as_str().to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        self.as_str().encoded_size_with(options)
+    }
+}
+
+impl<T: ToExternalBinary + ?Sized> ToExternalBinary for &T {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        (**self).to_writer_with(writer, options)
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        (**self).encoded_size_with(options)
+    }
+
+    fn is_unit(&self) -> bool {
+        (**self).is_unit()
+    }
+}
+
+/// `None` is `undefined`, `nil` or `none`, depending on the [`Convention`],
+/// and `Some(value)` is just the value, or `{some, Value}` for Gleam.
+///
+/// [`Convention`]: ../enum.Convention.html
+impl<T: ToExternalBinary> ToExternalBinary for Option<T> {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        match self {
+            Some(value) if options.convention.wraps_some() => {
+                let mut written = write_tuple_header(writer, 2)?;
+                written += write_atom("some", writer, options)?;
+                written += value.to_writer_with(writer, options)?;
+
+                Ok(written)
+            },
+            Some(value) => value.to_writer_with(writer, options),
+            None => write_atom(options.convention.none(), writer, options),
+        }
+    }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        match self {
+            Some(value) if options.convention.wraps_some() => {
+                Ok(tuple_header_size(2) + atom_size("some", options)? + value.encoded_size_with(options)?)
+            },
+            Some(value) => value.encoded_size_with(options),
+            None => atom_size(options.convention.none(), options),
+        }
+    }
+}
+
+/// `Ok(value)` is `{ok, Value}` and `Err(reason)` is `{error, Reason}`.
+///
+/// `Ok(())` and `Err(())` are just the atoms `ok` and `error`, which is what
+/// most Erlang functions that only report success or failure return, except
+/// for Gleam, where they are `{ok, nil}` and `{error, nil}`.
+impl<T: ToExternalBinary, E: ToExternalBinary> ToExternalBinary for Result<T, E> {
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        let (tag, value): (&str, &dyn ToExternalBinary) = match self {
+            Ok(value) => ("ok", value),
+            Err(reason) => ("error", reason),
+        };
+
+        if value.is_unit() && !options.convention.wraps_unit() {
+            return write_atom(tag, writer, options);
+        }
+
+        let mut written = write_tuple_header(writer, 2)?;
+        written += write_atom(tag, writer, options)?;
+        written += value.to_writer_with(writer, options)?;
 
         Ok(written)
     }
+
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        let (tag, value): (&str, &dyn ToExternalBinary) = match self {
+            Ok(value) => ("ok", value),
+            Err(reason) => ("error", reason),
+        };
+
+        if value.is_unit() && !options.convention.wraps_unit() {
+            return atom_size(tag, options);
+        }
+
+        Ok(tuple_header_size(2) + atom_size(tag, options)? + value.encoded_size_with(options)?)
+    }
+}
+
+fn write_binary(bytes: &[u8], writer: &mut dyn Write) -> Result<usize, Error> {
+    let len = binary_length(bytes)?.to_be_bytes();
+
+    let mut written = write_bytes(writer, &[TermTag::Binary as u8, len[0], len[1], len[2], len[3]])?;
+    written += write_bytes(writer, bytes)?;
+
+    Ok(written)
+}
+
+fn binary_size(bytes: &[u8]) -> Result<usize, Error> {
+    binary_length(bytes)?;
+
+    Ok(5 + bytes.len())
+}
+
+/// Writes `text` as a list of code points, the way `term_to_binary/1` writes
+/// an Erlang string: as a `STRING_EXT` if every code point fits in a byte.
+fn write_code_points(text: &str, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+    if text.is_empty() {
+        return ENil.to_writer_with(writer, options);
+    }
+
+    let latin1: Option<Vec<u8>> = text.chars().map(|c| u8::try_from(c as u32).ok()).collect();
+
+    match latin1 {
+        Some(bytes) if bytes.len() <= u16::MAX.into() => {
+            let len = (bytes.len() as u16).to_be_bytes();
+            let mut written = write_bytes(writer, &[TermTag::String as u8, len[0], len[1]])?;
+            written += write_bytes(writer, &bytes)?;
+
+            Ok(written)
+        },
+        _ => {
+            let len = (text.chars().count() as u32).to_be_bytes();
+            let mut written = write_bytes(writer, &[TermTag::List as u8, len[0], len[1], len[2], len[3]])?;
+
+            for c in text.chars() {
+                written += c.to_writer_with(writer, options)?;
+            }
+            written += ENil.to_writer_with(writer, options)?;
+
+            Ok(written)
+        },
+    }
+}
+
+/// Writes the atom `name`.
+fn write_atom(name: &str, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+    if options.atom_encoding == AtomEncoding::Latin1 && name.chars().all(|c| (c as u32) <= 0xff) {
+        let name: Vec<u8> = name.chars().map(|c| c as u8).collect();
+
+        if name.len() <= u8::MAX.into() {
+            let mut written = write_bytes(writer, &[TermTag::SmallAtom as u8, name.len() as u8])?;
+            written += write_bytes(writer, &name)?;
+
+            return Ok(written);
+        } else if name.len() <= u16::MAX.into() {
+            let len = (name.len() as u16).to_be_bytes();
+            let mut written = write_bytes(writer, &[TermTag::Atom as u8, len[0], len[1]])?;
+            written += write_bytes(writer, &name)?;
+
+            return Ok(written);
+        }
+    }
+
+    let byte_length = name.len();
+
+    if byte_length <= u8::MAX.into() {
+        let mut written = write_bytes(writer, &[TermTag::SmallAtomUtf8 as u8, byte_length as u8])?;
+        written += write_bytes(writer, name.as_bytes())?;
+
+        Ok(written)
+    } else if byte_length <= u16::MAX.into() {
+        let len: [u8; 8] = byte_length.to_be_bytes();
+        let mut written = write_bytes(writer, &[TermTag::AtomUtf8 as u8, len[6], len[7]])?;
+        written += write_bytes(writer, name.as_bytes())?;
+
+        Ok(written)
+    } else {
+        Err(Error::Message(name.to_owned()))
+    }
+}
+
+fn atom_size(name: &str, options: &EncodeOptions) -> Result<usize, Error> {
+    if options.atom_encoding == AtomEncoding::Latin1 && name.chars().all(|c| (c as u32) <= 0xff) {
+        let length = name.chars().count();
+
+        if length <= u8::MAX.into() {
+            return Ok(2 + length);
+        } else if length <= u16::MAX.into() {
+            return Ok(3 + length);
+        }
+    }
+
+    let byte_length = name.len();
+
+    if byte_length <= u8::MAX.into() {
+        Ok(2 + byte_length)
+    } else if byte_length <= u16::MAX.into() {
+        Ok(3 + byte_length)
+    } else {
+        Err(Error::Message(name.to_owned()))
+    }
+}
+
+fn binary_length(bytes: &[u8]) -> Result<u32, Error> {
+    u32::try_from(bytes.len())
+        .map_err(|_| Error::Message(format!("Binary of {} bytes is too large for a BINARY_EXT", bytes.len())))
 }
 
 #[cfg(feature="bigint")]
 impl ToExternalBinary for BigInt {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
+        if let Some(x) = self.to_u128() {
+            return x.encoded_size_with(options);
+        }
+
+        if let Some(x) = self.to_i128() {
+            return x.encoded_size_with(options);
+        }
+
+        big_size(self.bits().div_ceil(8))
+    }
+
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
         if let Some(x) = self.to_u128() {
-            return x.to_writer(writer);
+            return x.to_writer_with(writer, options);
         }
 
         if let Some(x) = self.to_i128() {
-            return x.to_writer(writer);
+            return x.to_writer_with(writer, options);
         }
 
         // Failing to delegate to u128 and i128 already establishes that
@@ -341,15 +1041,15 @@ impl ToExternalBinary for BigInt {
 
         let len = abs.to_bytes_be().len();
 
-        if len <= (u8::max_value() as usize) {
-            let mut written = writer.write(&[TermTag::SmallBig as u8, len as u8, sign])?;
-            written += writer.write(abs.to_bytes_le().as_ref())?;
+        if len <= (u8::MAX as usize) {
+            let mut written = write_bytes(writer, &[TermTag::SmallBig as u8, len as u8, sign])?;
+            written += write_bytes(writer, abs.to_bytes_le().as_ref())?;
 
             Ok(written)
-        } else if len <= (u32::max_value() as usize) {
+        } else if len <= (u32::MAX as usize) {
             let len_bytes = (len as u32).to_be_bytes();
-            let mut written = writer.write(&[TermTag::LargeBig as u8, len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3], sign])?;
-            written += writer.write(abs.to_bytes_le().as_ref())?;
+            let mut written = write_bytes(writer, &[TermTag::LargeBig as u8, len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3], sign])?;
+            written += write_bytes(writer, abs.to_bytes_le().as_ref())?;
 
             Ok(written)
         } else {
@@ -360,23 +1060,31 @@ impl ToExternalBinary for BigInt {
 
 #[cfg(feature="bigint")]
 impl ToExternalBinary for BigUint {
-    fn to_writer(&self, writer: &mut dyn Write) -> Result<usize, Error> {
+    fn encoded_size_with(&self, options: &EncodeOptions) -> Result<usize, Error> {
         if let Some(x) = self.to_u128() {
-            return x.to_writer(writer);
+            return x.encoded_size_with(options);
+        }
+
+        big_size(self.bits().div_ceil(8))
+    }
+
+    fn to_writer_with(&self, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+        if let Some(x) = self.to_u128() {
+            return x.to_writer_with(writer, options);
         }
 
         let tmp = self.to_bytes_be();
 
-        if tmp.len() <= (u8::max_value() as usize) {
+        if tmp.len() <= (u8::MAX as usize) {
             let len = tmp.len() as u8;
-            let mut written = writer.write(&[TermTag::SmallBig as u8, len, 0u8])?;
-            written += writer.write(self.to_bytes_le().as_ref())?;
+            let mut written = write_bytes(writer, &[TermTag::SmallBig as u8, len, 0u8])?;
+            written += write_bytes(writer, self.to_bytes_le().as_ref())?;
 
             Ok(written)
-        } else if tmp.len() <= (u32::max_value() as usize) {
+        } else if tmp.len() <= (u32::MAX as usize) {
             let len = (tmp.len() as u32).to_be_bytes();
-            let mut written = writer.write(&[TermTag::LargeBig as u8, len[0], len[1], len[2], len[3], 0u8])?;
-            written += writer.write(self.to_bytes_le().as_ref())?;
+            let mut written = write_bytes(writer, &[TermTag::LargeBig as u8, len[0], len[1], len[2], len[3], 0u8])?;
+            written += write_bytes(writer, self.to_bytes_le().as_ref())?;
 
             Ok(written)
         } else {
@@ -385,10 +1093,207 @@ impl ToExternalBinary for BigUint {
     }
 }
 
+/// The size of a SMALL_BIG_EXT or LARGE_BIG_EXT with `len` digits.
+#[cfg(feature="bigint")]
+fn big_size(len: usize) -> Result<usize, Error> {
+    if len <= (u8::MAX as usize) {
+        Ok(3 + len)
+    } else if len <= (u32::MAX as usize) {
+        Ok(6 + len)
+    } else {
+        Err(Error::Message("Integer size is outside of the possible ranges for an erlang term (-2^N..2^N-1 with N=(2^32)*8)".to_string()))
+    }
+}
+
+fn sum_of_sizes<'a, I>(terms: I, options: &EncodeOptions) -> Result<usize, Error>
+    where I: Iterator<Item = &'a Box<dyn ETerm>>
+{
+    let mut size = 0;
+
+    for term in terms {
+        size += term.encoded_size_with(options)?;
+    }
+
+    Ok(size)
+}
+
+/// Writes the tag and arity of a tuple with `arity` elements.
+pub(crate) fn write_tuple_header(writer: &mut dyn Write, arity: usize) -> Result<usize, Error> {
+    let len = (arity as u32).to_be_bytes();
+
+    if arity <= u8::MAX.into() {
+        write_bytes(writer, &[TermTag::SmallTuple as u8, len[3]])
+    } else {
+        write_bytes(writer, &[TermTag::LargeTuple as u8, len[0], len[1], len[2], len[3]])
+    }
+}
+
+pub(crate) fn tuple_header_size(arity: usize) -> usize {
+    if arity <= u8::MAX.into() { 2 } else { 5 }
+}
+
+/// Writes the tag and size of a map with `size` associations.
+pub(crate) fn write_map_header(writer: &mut dyn Write, size: usize) -> Result<usize, Error> {
+    let len = (size as u32).to_be_bytes();
+
+    write_bytes(writer, &[TermTag::Map as u8, len[0], len[1], len[2], len[3]])
+}
+
+/// Writes all of `bytes`, returning how many that were.
+///
+/// Unlike `Write::write`, this never stops after a short write.
+fn write_bytes(writer: &mut dyn Write, bytes: &[u8]) -> Result<usize, Error> {
+    writer.write_all(bytes)?;
+
+    Ok(bytes.len())
+}
+
+/// Writes the term written by `term` as a complete message, which is
+/// prefixed with the version byte and compressed as requested by `options`.
+pub(crate) fn write_message(writer: &mut dyn Write, options: &EncodeOptions, term: &dyn Fn(&mut dyn Write) -> Result<usize, Error>) -> Result<usize, Error> {
+    // Compressed messages always start with the version byte.
+    let compressed = cfg!(feature="compression") && options.compression > 0;
+    let mut written = 0;
+
+    if options.version_byte || compressed {
+        written += write_bytes(writer, &[ETF_VERSION])?;
+    }
+
+    if options.local {
+        written += write_bytes(writer, &[DistHeaderTag::Local as u8])?;
+    }
+
+    #[cfg(feature="compression")]
+    {
+        if compressed {
+            return Ok(written + write_compressed(writer, options, term)?);
+        }
+    }
+
+    written += term(writer)?;
+
+    Ok(written)
+}
+
+#[cfg(feature="compression")]
+fn write_compressed(writer: &mut dyn Write, options: &EncodeOptions, term: &dyn Fn(&mut dyn Write) -> Result<usize, Error>) -> Result<usize, Error> {
+    let mut uncompressed = Vec::new();
+    term(&mut uncompressed)?;
+
+    let size = u32::try_from(uncompressed.len())
+        .map_err(|_| Error::Message(format!("Term of {} bytes is too large to compress", uncompressed.len())))?;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(options.compression));
+    encoder.write_all(&uncompressed)?;
+    let compressed = encoder.finish()?;
+
+    // The tag and the size take 5 bytes.
+    if compressed.len() + 5 < uncompressed.len() {
+        let size = size.to_be_bytes();
+
+        let written = write_bytes(writer, &[DistHeaderTag::Compressed as u8, size[0], size[1], size[2], size[3]])?;
+        Ok(written + write_bytes(writer, &compressed)?)
+    } else {
+        write_bytes(writer, &uncompressed)
+    }
+}
+
+/// Encodes a nested term on its own, without the framing of a message.
+fn to_vec(term: &dyn ETerm, options: &EncodeOptions) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    term.to_writer_with(&mut result, options)?;
+
+    Ok(result)
+}
+
+/// Writes a list the way `term_to_binary/1` would.
+///
+/// A tail that is itself a list is merged into the list, so `[1 | [2]]` is
+/// written as `[1, 2]`.
+/// Proper lists of at most 65535 bytes are written as a `STRING_EXT`.
+fn canonical_list_to_writer(elements: &[Box<dyn ETerm>], tail: &dyn ETerm, writer: &mut dyn Write, options: &EncodeOptions) -> Result<usize, Error> {
+    let mut count = elements.len();
+    let mut encoded = Vec::new();
+
+    for d in elements.iter() {
+        d.to_writer_with(&mut encoded, options)?;
+    }
+
+    // The tail is canonical itself, so at most one level has to be merged.
+    let mut tail = to_vec(tail, options)?;
+
+    if tail[0] == TermTag::List as u8 {
+        let len = u32::from_be_bytes([tail[1], tail[2], tail[3], tail[4]]) as usize;
+        let mut end = 5;
+
+        for _ in 0..len {
+            end += term_size(&tail[end..])?;
+        }
+
+        encoded.extend_from_slice(&tail[5..end]);
+        count += len;
+        tail.drain(..end);
+    } else if tail[0] == TermTag::String as u8 {
+        for byte in tail[3..].iter() {
+            encoded.extend_from_slice(&[TermTag::SmallInteger as u8, *byte]);
+        }
+
+        count += tail.len() - 3;
+        tail = vec![TermTag::Nil as u8];
+    }
+
+    if count == 0 {
+        return write_bytes(writer, &tail);
+    }
+
+    // Every element takes at least one byte, so if all elements start with a
+    // SMALL_INTEGER_EXT tag at even offsets, all of them are bytes.
+    let is_string = tail == [TermTag::Nil as u8]
+        && count <= u16::MAX.into()
+        && encoded.len() == 2 * count
+        && encoded.chunks(2).all(|element| element[0] == TermTag::SmallInteger as u8);
+
+    if is_string {
+        let len = (count as u16).to_be_bytes();
+        let bytes: Vec<u8> = encoded.chunks(2).map(|element| element[1]).collect();
+
+        let mut written = write_bytes(writer, &[TermTag::String as u8, len[0], len[1]])?;
+        written += write_bytes(writer, &bytes)?;
+
+        Ok(written)
+    } else {
+        let len = (count as u32).to_be_bytes();
+
+        let mut written = write_bytes(writer, &[TermTag::List as u8, len[0], len[1], len[2], len[3]])?;
+        written += write_bytes(writer, &encoded)?;
+        written += write_bytes(writer, &tail)?;
+
+        Ok(written)
+    }
+}
+
+/// Formats a float like C's `printf("%.20e")`, padded with NUL bytes to the
+/// 31 bytes of a `FLOAT_EXT`.
+///
+/// 21 significant digits are more than the 17 needed to represent every
+/// `f64` exactly, so no precision is lost.
+fn float_ext_text(value: f64) -> [u8; 31] {
+    let formatted = format!("{:.20e}", value);
+    let (mantissa, exponent) = formatted.split_at(formatted.find('e').expect("Exponent notation contains an 'e'"));
+    let exponent: i32 = exponent[1..].parse().expect("Exponent is an integer");
+
+    let text = format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs());
+
+    let mut result = [0u8; 31];
+    result[..text.len()].copy_from_slice(text.as_bytes());
+
+    result
+}
+
 fn lossless_abs(num: i128) -> u128 {
     if num >= 0 {
         num as u128
-    } else if num == i128::min_value() {
+    } else if num == i128::MIN {
         0x80_00_00_00_00_00_00_00_00_00_00_00_00_00_00_00u128
     } else {
         -num as u128
@@ -397,6 +1302,9 @@ fn lossless_abs(num: i128) -> u128 {
 
 #[cfg(test)]
 mod tests {
+    use super::{ AtomEncoding, EncodeOptions, FloatEncoding, ToExternalBinary };
+    use super::super::{ Convention, EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, EPort, EString, ETuple, ETF_VERSION };
+    use super::super::decode::{ decode, DecodeOptions };
     use super::ETerm;
     use super::lossless_abs;
 
@@ -432,29 +1340,29 @@ mod tests {
                 test!([97, 0], false, 0),
 
                 // i8 and u8 type boundaries
-                test!([98, 255, 255, 255, 128], true, i8::min_value()),
-                test!([97, 127], false, i8::max_value()),
-                test!([97, 255], false, u8::max_value()),
+                test!([98, 255, 255, 255, 128], true, i8::MIN),
+                test!([97, 127], false, i8::MAX),
+                test!([97, 255], false, u8::MAX),
 
                 // i16 and u16 type boundaries
-                test!([98, 255,255, 128, 0], true, i16::min_value()),
-                test!([98, 0, 0, 127, 255], false, i16::max_value()),
-                test!([98, 0, 0, 255, 255], false, u16::max_value()),
+                test!([98, 255,255, 128, 0], true, i16::MIN),
+                test!([98, 0, 0, 127, 255], false, i16::MAX),
+                test!([98, 0, 0, 255, 255], false, u16::MAX),
 
                 // i32 and u32 type boundaries
-                test!([98, 128, 0, 0, 0], true, i32::min_value()),
-                test!([98, 127, 255, 255, 255], false, i32::max_value()),
-                test!([110, 4, 0, 255, 255, 255, 255], false, u32::max_value()),
+                test!([98, 128, 0, 0, 0], true, i32::MIN),
+                test!([98, 127, 255, 255, 255], false, i32::MAX),
+                test!([110, 4, 0, 255, 255, 255, 255], false, u32::MAX),
 
                 // i64 and u64 type boundaries
-                test!([110, 8, 1, 0, 0, 0, 0, 0, 0, 0, 128], true, i64::min_value()),
-                test!([110, 8, 0, 255, 255, 255, 255, 255, 255, 255, 127], false, i64::max_value()),
-                test!([110, 8, 0, 255, 255, 255, 255, 255, 255, 255, 255], false, u64::max_value()),
+                test!([110, 8, 1, 0, 0, 0, 0, 0, 0, 0, 128], true, i64::MIN),
+                test!([110, 8, 0, 255, 255, 255, 255, 255, 255, 255, 127], false, i64::MAX),
+                test!([110, 8, 0, 255, 255, 255, 255, 255, 255, 255, 255], false, u64::MAX),
 
                 // i128 and u128 type boundaries
-                test!([110, 16, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128], true, i128::min_value()),
-                test!([110, 16, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 127], false, i128::max_value()),
-                test!([110, 16, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255], false ,u128::max_value()),
+                test!([110, 16, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128], true, i128::MIN),
+                test!([110, 16, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 127], false, i128::MAX),
+                test!([110, 16, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255], false ,u128::MAX),
             ] {
                 test.run(signed, bits);
             }
@@ -462,25 +1370,25 @@ mod tests {
 
         fn run(&self, signed: bool, bits: u8) {
             let bin = match (signed, bits, self.is_negative, self.number) {
-                (true, 8, s, n) if n <= (i8::max_value() as u128) || (s && n <= lossless_abs(i8::min_value() as i128)) =>
+                (true, 8, s, n) if n <= (i8::MAX as u128) || (s && n <= lossless_abs(i8::MIN as i128)) =>
                     (self.with_sign() as i8).to_external_binary(),
-                (false, 8, false, n) if n <= (u8::max_value() as u128) =>
+                (false, 8, false, n) if n <= (u8::MAX as u128) =>
                     (self.number as u8).to_external_binary(),
-                (true, 16, s, n) if n <= (i16::max_value() as u128) || (s && n <= lossless_abs(i16::min_value() as i128)) =>
+                (true, 16, s, n) if n <= (i16::MAX as u128) || (s && n <= lossless_abs(i16::MIN as i128)) =>
                     (self.with_sign() as i16).to_external_binary(),
-                (false, 16, false, n) if n <= (u16::max_value() as u128) =>
+                (false, 16, false, n) if n <= (u16::MAX as u128) =>
                     (self.number as u16).to_external_binary(),
-                (true, 32, s, n) if n <= (i32::max_value() as u128) || (s && n <= lossless_abs(i32::min_value() as i128)) =>
+                (true, 32, s, n) if n <= (i32::MAX as u128) || (s && n <= lossless_abs(i32::MIN as i128)) =>
                     (self.with_sign() as i32).to_external_binary(),
-                (false, 32, false, n) if n <= (u32::max_value() as u128) =>
+                (false, 32, false, n) if n <= (u32::MAX as u128) =>
                     (self.number as u32).to_external_binary(),
-                (true, 64, s, n) if n <= (i64::max_value() as u128) || (s && n <= lossless_abs(i64::min_value() as i128)) =>
+                (true, 64, s, n) if n <= (i64::MAX as u128) || (s && n <= lossless_abs(i64::MIN as i128)) =>
                     (self.with_sign() as i64).to_external_binary(),
-                (false, 64, false, n) if n <= (u64::max_value() as u128) =>
+                (false, 64, false, n) if n <= (u64::MAX as u128) =>
                     (self.number as u64).to_external_binary(),
-                (true, 128, s, n) if n <= (i128::max_value() as u128) || (s && n <= lossless_abs(i128::min_value() as i128)) =>
-                    (self.with_sign() as i128).to_external_binary(),
-                (false, 128, false, n) if n <= u128::max_value() =>
+                (true, 128, s, n) if n <= (i128::MAX as u128) || (s && n <= lossless_abs(i128::MIN)) =>
+                    self.with_sign().to_external_binary(),
+                (false, 128, false, _) =>
                     self.number.to_external_binary(),
                 #[cfg(feature="bigint")]
                 (true, 0, s, n) =>
@@ -499,8 +1407,8 @@ mod tests {
         }
 
         fn with_sign(&self) -> i128 {
-            if self.is_negative && self.number == lossless_abs(i128::min_value()) {
-                i128::min_value()
+            if self.is_negative && self.number == lossless_abs(i128::MIN) {
+                i128::MIN
             } else if self.is_negative {
                 -(self.number as i128)
             } else {
@@ -755,7 +1663,7 @@ mod tests {
     //  floats.
     //  We are not doing any arithmetic (so therefore also not any arithmetic
     //  that disturps the exactness of these floats), so comparing is safe.
-    #[allow(clippy::float_cmp)]
+    #[allow(clippy::float_cmp, clippy::excessive_precision)]
     fn floats() {
         // Note: Clippy warns about excessive precision here.
         //  That warning is correct, but we are including the precision
@@ -784,4 +1692,517 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn latin1_atoms() {
+        let options = EncodeOptions::default().with_atom_encoding(AtomEncoding::Latin1);
+
+        assert_eq!(vec![115, 2, b'o', b'k'], EAtom("ok".to_string()).to_external_binary_with(&options).unwrap());
+        assert_eq!(vec![115, 1, 0xe9], EAtom("é".to_string()).to_external_binary_with(&options).unwrap());
+        assert_eq!(vec![119, 2, 0xc4, 0x81], EAtom("ā".to_string()).to_external_binary_with(&options).unwrap());
+
+        let long = EAtom("a".repeat(256));
+        assert_eq!(&[100, 1, 0], &long.to_external_binary_with(&options).unwrap()[..3]);
+
+        let nested = ETuple(vec![Box::new(EAtom("é".to_string()))]);
+        let bytes = nested.to_external_binary_with(&options).unwrap();
+        assert_eq!(vec![104, 1, 115, 1, 0xe9], bytes);
+        assert_eq!("{é}", decode(&mut bytes.as_slice(), &DecodeOptions::default()).unwrap().to_string());
+    }
+
+    #[test]
+    fn conventions() {
+        fn encode(value: &dyn ToExternalBinary, options: &EncodeOptions) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            value.to_writer_with(&mut bytes, options).unwrap();
+
+            assert_eq!(bytes.len(), value.encoded_size_with(options).unwrap());
+            bytes
+        }
+
+        let erlang = EncodeOptions::default();
+        let elixir = EncodeOptions::default().with_convention(Convention::Elixir);
+        let gleam = EncodeOptions::default().with_convention(Convention::Gleam);
+
+        assert_eq!(vec![119, 4, b't', b'r', b'u', b'e'], encode(&true, &erlang));
+        assert_eq!(vec![119, 2, b'o', b'k'], encode(&(), &erlang));
+        assert_eq!(vec![97, 97], encode(&'a', &erlang));
+        assert_eq!(vec![98, 0, 0, 0x20, 0xac], encode(&'€', &erlang));
+
+        assert_eq!(vec![106], encode(&"", &erlang));
+        assert_eq!(vec![107, 0, 2, b'h', 0xe9], encode(&"hé", &erlang));
+        assert_eq!(vec![108, 0, 0, 0, 1, 98, 0, 0, 0x20, 0xac, 106], encode(&"€", &erlang));
+        assert_eq!(vec![109, 0, 0, 0, 2, 0xc3, 0xa9], encode(&"é".to_string(), &elixir));
+        assert_eq!(vec![109, 0, 0, 0, 1, b'a'], encode(&"a", &gleam));
+
+        assert_eq!(vec![119, 9, b'u', b'n', b'd', b'e', b'f', b'i', b'n', b'e', b'd'], encode(&None::<u8>, &erlang));
+        assert_eq!(vec![119, 3, b'n', b'i', b'l'], encode(&None::<u8>, &elixir));
+        assert_eq!(vec![119, 4, b'n', b'o', b'n', b'e'], encode(&None::<u8>, &gleam));
+        assert_eq!(vec![97, 1], encode(&Some(1u8), &erlang));
+        assert_eq!(vec![97, 1], encode(&Some(1u8), &elixir));
+        assert_eq!(vec![104, 2, 119, 4, b's', b'o', b'm', b'e', 97, 1], encode(&Some(1u8), &gleam));
+
+        assert_eq!(vec![104, 2, 119, 2, b'o', b'k', 97, 1], encode(&Ok::<u8, ()>(1), &erlang));
+        assert_eq!(vec![104, 2, 119, 5, b'e', b'r', b'r', b'o', b'r', 106], encode(&Err::<u8, &str>(""), &erlang));
+        assert_eq!(vec![119, 2, b'o', b'k'], encode(&Ok::<(), u8>(()), &erlang));
+        assert_eq!(vec![119, 5, b'e', b'r', b'r', b'o', b'r'], encode(&Err::<u8, ()>(()), &erlang));
+        assert_eq!(vec![119, 2, b'o', b'k'], encode(&Ok::<&(), u8>(&()), &erlang));
+
+        let reason = String::from("no");
+        let borrowed: Result<u8, &str> = Err(reason.as_str());
+        assert_eq!(vec![104, 2, 119, 5, b'e', b'r', b'r', b'o', b'r', 107, 0, 2, b'n', b'o'], encode(&borrowed, &erlang));
+
+        let values: Vec<Box<dyn ToExternalBinary>> = vec![
+            Box::new(false),
+            Box::new(()),
+            Box::new('\u{10ffff}'),
+            Box::new(""),
+            Box::new("hello"),
+            Box::new("x".repeat(70000)),
+            Box::new("ā€"),
+            Box::new(None::<u8>),
+            Box::new(Some("text")),
+            Box::new(Ok::<_, ()>(EBinary::new(vec![1]))),
+            Box::new(Err::<(), _>("reason")),
+            Box::new(Ok::<(), ()>(())),
+        ];
+
+        for value in values.iter() {
+            encode(&**value, &erlang);
+            encode(&**value, &elixir);
+            encode(&**value, &gleam);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn text_floats() {
+        let options = EncodeOptions::default().with_float_encoding(FloatEncoding::Text);
+
+        let mut expected = vec![99];
+        expected.extend_from_slice(b"5.00000000000000000000e-01");
+        expected.resize(32, 0);
+        assert_eq!(expected, 0.5.to_external_binary_with(&options).unwrap());
+
+        let mut expected = vec![99];
+        expected.extend_from_slice(b"-1.79769313486231570815e+308");
+        expected.resize(32, 0);
+        assert_eq!(expected, f64::MIN.to_external_binary_with(&options).unwrap());
+
+        for value in &[0.0, -0.0, 0.1, 1.0 / 3.0, 1e-300, f64::MIN_POSITIVE, 5e-324, f64::MAX, 123_456_789.987_654_3] {
+            let bytes = value.to_external_binary_with(&options).unwrap();
+            let decoded = decode(&mut bytes.as_slice(), &DecodeOptions::default()).unwrap();
+            let decoded: &dyn std::any::Any = &*decoded;
+
+            assert_eq!(value.to_bits(), decoded.downcast_ref::<f64>().unwrap().to_bits(), "{}", value);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn padded_text_floats() {
+        for padding in &[0u8, b' '] {
+            let mut bytes = vec![99];
+            bytes.extend_from_slice(b"1.50000000000000000000e+01");
+            bytes.resize(32, *padding);
+
+            let decoded = decode(&mut bytes.as_slice(), &DecodeOptions::default()).unwrap();
+            let decoded: &dyn std::any::Any = &*decoded;
+            assert_eq!(Some(&15.0), decoded.downcast_ref::<f64>());
+        }
+    }
+
+    fn atom(name: &str) -> Box<dyn ETerm> {
+        Box::new(EAtom(name.to_string()))
+    }
+
+    fn bytes(values: &[u8]) -> Box<dyn ETerm> {
+        Box::new(EList(values.iter().map(|b| Box::new(*b) as Box<dyn ETerm>).collect()))
+    }
+
+    fn deterministic(term: &dyn ETerm) -> Vec<u8> {
+        let mut result = vec![ETF_VERSION];
+        result.extend(term.to_external_binary_with(&EncodeOptions::default().with_deterministic(true)).unwrap());
+
+        result
+    }
+
+    #[test]
+    fn deterministic_fixtures() {
+        // Each fixture holds the output of `term_to_binary(Term, [deterministic])`.
+        let fixtures: Vec<(&[u8], EMap)> = vec![
+            // #{b => 1, a => 2, aa => 3}
+            (include_bytes!("../../tests/fixtures/deterministic/atom_keys.etf"), EMap(vec![
+                (atom("b"), Box::new(1u8)),
+                (atom("a"), Box::new(2u8)),
+                (atom("aa"), Box::new(3u8)),
+            ])),
+            // #{x => 4, {x} => 3, 1.5 => 5, 2 => 6, [] => 7, #{} => 8, [1,2] => 2}
+            (include_bytes!("../../tests/fixtures/deterministic/mixed_keys.etf"), EMap(vec![
+                (atom("x"), Box::new(4u8)),
+                (Box::new(ETuple(vec![atom("x")])), Box::new(3u8)),
+                (Box::new(1.5f64), Box::new(5u8)),
+                (Box::new(2i64), Box::new(6u8)),
+                (Box::new(ENil), Box::new(7u8)),
+                (Box::new(EMap(vec![])), Box::new(8u8)),
+                (bytes(&[1, 2]), Box::new(2u8)),
+            ])),
+            // #{1000 => a, -1 => b, 18446744073709551616 => c, 0 => d}
+            (include_bytes!("../../tests/fixtures/deterministic/integer_keys.etf"), EMap(vec![
+                (Box::new(1000i32), atom("a")),
+                (Box::new(-1i8), atom("b")),
+                (Box::new(1u128 << 64), atom("c")),
+                (Box::new(0u64), atom("d")),
+            ])),
+            // #{m => #{z => 1, a => 2}, list => [1,2,3]}
+            (include_bytes!("../../tests/fixtures/deterministic/nested.etf"), EMap(vec![
+                (atom("m"), Box::new(EMap(vec![(atom("z"), Box::new(1u8)), (atom("a"), Box::new(2u8))]))),
+                (atom("list"), bytes(&[1, 2, 3])),
+            ])),
+            // #{[1,2] => b, [1|2] => a}
+            (include_bytes!("../../tests/fixtures/deterministic/list_keys.etf"), EMap(vec![
+                (Box::new(EString("\u{1}\u{2}".to_string())), atom("b")),
+                (Box::new(ENonProperList { data: vec![Box::new(1u8)], tail: Box::new(2u8) }), atom("a")),
+            ])),
+        ];
+
+        for (expected, term) in fixtures {
+            assert_eq!(expected, deterministic(&term).as_slice(), "{}", term);
+        }
+    }
+
+    #[test]
+    fn deterministic_lists() {
+        // Empty strings are [], and nested tails are merged into the list.
+        assert_eq!(vec![ETF_VERSION, 106], deterministic(&EString(String::new())));
+        assert_eq!(vec![ETF_VERSION, 106], deterministic(&ENonProperList { data: vec![], tail: Box::new(EString(String::new())) }));
+
+        let nested = ENonProperList {
+            data: vec![Box::new(1u8)],
+            tail: Box::new(ENonProperList { data: vec![Box::new(2u8)], tail: Box::new(EString("ab".to_string())) }),
+        };
+        assert_eq!(vec![ETF_VERSION, 107, 0, 4, 1, 2, b'a', b'b'], deterministic(&nested));
+
+        let improper = ENonProperList {
+            data: vec![atom("a")],
+            tail: Box::new(ENonProperList { data: vec![Box::new(2u8)], tail: atom("b") }),
+        };
+        assert_eq!(
+            vec![ETF_VERSION, 108, 0, 0, 0, 2, 119, 1, b'a', 97, 2, 119, 1, b'b'],
+            deterministic(&improper),
+        );
+
+        let mixed = EList(vec![Box::new(1u8), Box::new(256i32)]);
+        assert_eq!(vec![ETF_VERSION, 108, 0, 0, 0, 2, 97, 1, 98, 0, 0, 1, 0, 106], deterministic(&mixed));
+    }
+
+    #[test]
+    fn deterministic_is_opt_in() {
+        let map = EMap(vec![(atom("b"), Box::new(1u8)), (atom("a"), Box::new(2u8))]);
+
+        assert_eq!(&[119, 1, b'b'], &map.to_external_binary().unwrap()[5..8]);
+        assert_eq!(vec![107, 0, 1, 1], bytes(&[1]).to_external_binary_with(&EncodeOptions::default().with_deterministic(true)).unwrap());
+        assert_eq!(vec![108, 0, 0, 0, 1, 97, 1, 106], bytes(&[1]).to_external_binary().unwrap());
+    }
+
+    #[test]
+    fn minor_versions() {
+        let term = ETuple(vec![atom("ok"), Box::new(0.5)]);
+
+        let mut expected = vec![104, 2, 115, 2, b'o', b'k', 99];
+        expected.extend_from_slice(b"5.00000000000000000000e-01");
+        expected.resize(38, 0);
+        assert_eq!(expected, term.to_external_binary_with(&EncodeOptions::default().with_minor_version(0)).unwrap());
+
+        assert_eq!(
+            vec![104, 2, 115, 2, b'o', b'k', 70, 63, 224, 0, 0, 0, 0, 0, 0],
+            term.to_external_binary_with(&EncodeOptions::default().with_minor_version(1)).unwrap(),
+        );
+        assert_eq!(term.to_external_binary().unwrap(), term.to_external_binary_with(&EncodeOptions::default().with_minor_version(2)).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_minor_version() {
+        EncodeOptions::default().with_minor_version(3);
+    }
+
+    #[test]
+    fn version_byte() {
+        let options = EncodeOptions::default().with_version_byte(true);
+        let term = ETuple(vec![Box::new(ETuple(vec![Box::new(1u8)]))]);
+
+        // Only the outermost term is prefixed.
+        assert_eq!(vec![ETF_VERSION, 104, 1, 104, 1, 97, 1], term.to_external_binary_with(&options).unwrap());
+
+        let mut written = Vec::new();
+        assert_eq!(7, term.write_to_with(&mut written, &options).unwrap());
+        assert_eq!(vec![ETF_VERSION, 104, 1, 104, 1, 97, 1], written);
+    }
+
+    #[test]
+    #[cfg(feature="compression")]
+    fn compression() {
+        use flate2::read::ZlibDecoder;
+        use std::io::Read;
+
+        let options = EncodeOptions::default().with_compression(6);
+        let term = EString("a".repeat(1000));
+
+        let bytes = term.to_external_binary_with(&options).unwrap();
+        assert_eq!(&[ETF_VERSION, 80, 0, 0, 3, 235], &bytes[..6]);
+        assert!(bytes.len() < 100);
+
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(&bytes[6..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(term.to_external_binary().unwrap(), decompressed);
+
+        // Terms that do not get smaller are left uncompressed.
+        assert_eq!(vec![ETF_VERSION, 97, 1], 1u8.to_external_binary_with(&options).unwrap());
+        assert_eq!(vec![ETF_VERSION, 97, 1], 1u8.to_external_binary_with(&options.with_version_byte(true).with_compression(9)).unwrap());
+    }
+
+    #[test]
+    fn messages() {
+        use super::super::decode::{ decode_message, DecodeOptions };
+
+        let mut stream = ETuple(vec![atom("ok"), Box::new(1u8)]).to_external_binary_with(&EncodeOptions::default().with_version_byte(true)).unwrap();
+
+        #[cfg(feature="compression")]
+        stream.extend(EString("a".repeat(1000)).to_external_binary_with(&EncodeOptions::default().with_compression(6)).unwrap());
+
+        stream.extend(&[ETF_VERSION, 106]);
+
+        let mut input = stream.as_slice();
+        assert_eq!("{ok,1}", decode_message(&mut input, &DecodeOptions::default()).unwrap().to_string());
+
+        #[cfg(feature="compression")]
+        assert_eq!(1000, decode_message(&mut input, &DecodeOptions::default()).unwrap().downcast_ref::<EList>().unwrap().len());
+
+        assert_eq!("[]", decode_message(&mut input, &DecodeOptions::default()).unwrap().to_string());
+        assert!(input.is_empty());
+
+        assert!(decode_message(&mut &[130, 97, 1][..], &DecodeOptions::default()).is_err());
+        assert!(decode_message(&mut &[ETF_VERSION, 104][..], &DecodeOptions::default()).is_err());
+        assert!(decode_message(&mut &[ETF_VERSION, 80, 0, 0, 0, 2, 1, 2][..], &DecodeOptions::default()).is_err());
+    }
+
+    #[test]
+    #[cfg(feature="compression")]
+    fn compressed_sizes() {
+        use super::super::decode::{ decode_message, DecodeOptions };
+        use flate2::Compression;
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        let compressed = |bytes: &[u8], size: u32| {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(bytes).unwrap();
+
+            let mut message = vec![ETF_VERSION, 80];
+            message.extend(&size.to_be_bytes());
+            message.extend(encoder.finish().unwrap());
+            message
+        };
+        let error = |message: Vec<u8>| decode_message(&mut message.as_slice(), &DecodeOptions::default()).unwrap_err().to_string();
+
+        assert_eq!(
+            "Compressed term is 2 bytes, not the 4294967295 bytes its header states",
+            error(compressed(&[97, 1], u32::MAX)),
+        );
+        assert_eq!(
+            "Compressed term is larger than the 2 bytes its header states",
+            error(compressed(&vec![0; 1 << 20], 2)),
+        );
+    }
+
+    #[test]
+    fn local() {
+        use super::super::decode::{ decode_message, DecodeOptions };
+
+        let options = EncodeOptions::default().with_version_byte(true).with_local(true);
+        let bytes = atom("ok").to_external_binary_with(&options).unwrap();
+        assert_eq!(vec![ETF_VERSION, 121, 119, 2, b'o', b'k'], bytes);
+        assert_eq!("ok", decode_message(&mut bytes.as_slice(), &DecodeOptions::default()).unwrap().to_string());
+
+        assert_eq!(vec![121, 97, 1], 1u8.to_external_binary_with(&EncodeOptions::default().with_local(true)).unwrap());
+
+        #[cfg(feature="compression")]
+        {
+            let bytes = EString("a".repeat(1000)).to_external_binary_with(&options.with_compression(6)).unwrap();
+            assert_eq!(&[ETF_VERSION, 121, 80], &bytes[..3]);
+            assert_eq!(1000, decode_message(&mut bytes.as_slice(), &DecodeOptions::default()).unwrap().downcast_ref::<EList>().unwrap().len());
+        }
+    }
+
+    #[test]
+    fn encoded_sizes() {
+        let terms: Vec<Box<dyn ETerm>> = vec![
+            Box::new(0u8),
+            Box::new(-1i8),
+            Box::new(300i16),
+            Box::new(u32::MAX),
+            Box::new(i64::MIN),
+            Box::new(u128::MAX),
+            Box::new(0.1f64),
+            Box::new(ENil),
+            bytes(&[]),
+            bytes(&[1, 2, 3]),
+            Box::new(EList(vec![Box::new(1u8), Box::new(1000i32)])),
+            Box::new(ENonProperList { data: vec![Box::new(1u8)], tail: Box::new(EString("ab".to_string())) }),
+            Box::new(ENonProperList { data: vec![], tail: atom("t") }),
+            atom("ok"),
+            atom("é"),
+            atom("ā"),
+            Box::new(EAtom("é".repeat(200))),
+            Box::new(EAtom("a".repeat(256))),
+            Box::new(EExport { module: EAtom("lists".to_string()), function: EAtom("map".to_string()), arity: 2 }),
+            Box::new(ETuple((0..300u16).map(|i| Box::new(i) as Box<dyn ETerm>).collect())),
+            Box::new(EString(String::new())),
+            Box::new(EString("hello".to_string())),
+            Box::new(EString("x".repeat(70000))),
+            Box::new(EPort { node: EAtom("a@b".to_string()), id: 1, creation: 2 }),
+            Box::new(EPid { node: EAtom("a@b".to_string()), id: 1, serial: 2, creation: 3 }),
+            Box::new(EMap(vec![(atom("b"), bytes(&[1])), (atom("a"), Box::new(EMap(vec![])))])),
+            Box::new(EBinary(vec![1, 2, 3])),
+        ];
+
+        #[cfg(feature="bigint")]
+        let terms = {
+            let mut terms = terms;
+            terms.push(Box::new(BigInt::from(-5)));
+            terms.push(Box::new(BigInt::from(u128::MAX) * BigInt::from(-3)));
+            terms.push(Box::new(BigUint::from(1u8) << 2048));
+            terms
+        };
+
+        let options = [
+            EncodeOptions::default(),
+            EncodeOptions::default().with_minor_version(0),
+            EncodeOptions::default().with_deterministic(true),
+        ];
+
+        for term in terms.iter() {
+            for options in options.iter() {
+                let mut bytes = Vec::new();
+                term.to_writer_with(&mut bytes, options).unwrap();
+
+                assert_eq!(bytes.len(), term.encoded_size_with(options).unwrap(), "{} with {:?}", term, options);
+            }
+        }
+
+        assert!(EAtom("a".repeat(70000)).encoded_size().is_err());
+        assert!(f64::NAN.encoded_size().is_err());
+    }
+
+    #[test]
+    fn default_encoded_size() {
+        struct Custom;
+
+        impl ToExternalBinary for Custom {
+            fn to_writer_with(&self, writer: &mut dyn std::io::Write, options: &EncodeOptions) -> Result<usize, super::Error> {
+                ETuple(vec![atom("custom")]).to_writer_with(writer, options)
+            }
+        }
+
+        assert_eq!(10, Custom.encoded_size().unwrap());
+    }
+
+    macro_rules! conformance_fixture {
+        ($name:expr) => {
+            &include_bytes!(concat!("../../tests/fixtures/conformance/", $name, ".etf"))[..]
+        };
+    }
+
+    #[test]
+    fn conformance() {
+        // Each fixture holds the output of `term_to_binary(Term)`.
+        let fixtures: Vec<(&[u8], Box<dyn ETerm>)> = vec![
+            // 255
+            (conformance_fixture!("small_integer"), Box::new(255u8)),
+            // 256
+            (conformance_fixture!("integer"), Box::new(256u16)),
+            // -1
+            (conformance_fixture!("negative_integer"), Box::new(-1i64)),
+            // 2147483648
+            (conformance_fixture!("small_big"), Box::new(2_147_483_648u32)),
+            // -2147483649
+            (conformance_fixture!("negative_small_big"), Box::new(-2_147_483_649i64)),
+            // 1.5
+            (conformance_fixture!("new_float"), Box::new(1.5f32)),
+            // ok
+            (conformance_fixture!("atom"), atom("ok")),
+            // 'é'
+            (conformance_fixture!("unicode_atom"), atom("é")),
+            // []
+            (conformance_fixture!("nil"), Box::new(ENil)),
+            // "abc"
+            (conformance_fixture!("string"), Box::new(EString("abc".to_string()))),
+            // [a, b]
+            (conformance_fixture!("list"), Box::new(EList(vec![atom("a"), atom("b")]))),
+            // [1 | 2]
+            (conformance_fixture!("improper_list"), Box::new(ENonProperList { data: vec![Box::new(1u8)], tail: Box::new(2u8) })),
+            // {ok, 1}
+            (conformance_fixture!("small_tuple"), Box::new(ETuple(vec![atom("ok"), Box::new(1u8)]))),
+            // list_to_tuple(lists:seq(0, 255))
+            (conformance_fixture!("large_tuple"), Box::new(ETuple((0..=255u8).map(|i| Box::new(i) as Box<dyn ETerm>).collect()))),
+            // #{a => 1}
+            (conformance_fixture!("map"), Box::new(EMap(vec![(atom("a"), Box::new(1u8))]))),
+            // <<1, 2, 3>>
+            (conformance_fixture!("binary"), Box::new(EBinary(vec![1, 2, 3]))),
+            // <<>>
+            (conformance_fixture!("empty_binary"), Box::new(EBinary(vec![]))),
+            // fun lists:map/2
+            (conformance_fixture!("export"), Box::new(EExport { module: EAtom("lists".to_string()), function: EAtom("map".to_string()), arity: 2 })),
+            // A pid on node 'a@b' with id 1, serial 2 and creation 3
+            (conformance_fixture!("pid"), Box::new(EPid { node: EAtom("a@b".to_string()), id: 1, serial: 2, creation: 3 })),
+            // A port on node 'a@b' with id 1 and creation 2
+            (conformance_fixture!("port"), Box::new(EPort { node: EAtom("a@b".to_string()), id: 1, creation: 2 })),
+        ];
+
+        let options = EncodeOptions::default().with_version_byte(true);
+
+        for (expected, term) in fixtures.iter() {
+            let mut written = Vec::new();
+
+            assert_eq!(expected.len(), term.write_to_with(&mut written, &options).unwrap(), "{}", term);
+            assert_eq!(*expected, written.as_slice(), "{}", term);
+            assert_eq!(expected.len(), 1 + term.encoded_size().unwrap(), "{}", term);
+        }
+    }
+
+    /// A writer that accepts a single byte per call.
+    struct Trickle(Vec<u8>);
+
+    impl std::io::Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            match buf.first() {
+                Some(byte) => {
+                    self.0.push(*byte);
+                    Ok(1)
+                },
+                None => Ok(0),
+            }
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn short_writes() {
+        let term = ETuple(vec![
+            atom("data"),
+            Box::new(EBinary(vec![7; 100])),
+            Box::new(EMap(vec![(Box::new(u128::MAX), Box::new(EString("text".to_string())))])),
+            Box::new(0.25),
+        ]);
+
+        let mut trickle = Trickle(Vec::new());
+        let written = term.to_writer(&mut trickle).unwrap();
+
+        assert_eq!(term.to_external_binary().unwrap(), trickle.0);
+        assert_eq!(trickle.0.len(), written);
+    }
 }
@@ -0,0 +1,379 @@
+//! Gleam's representation of values, and formatting terms in Gleam syntax.
+//!
+//! Gleam values are represented on the BEAM as follows:
+//!
+//! | Gleam                                | Term                      |
+//! |--------------------------------------|---------------------------|
+//! | `Int`, `Float`                       | Integers and floats       |
+//! | `True`, `False`, `Nil`               | `true`, `false`, `nil`    |
+//! | `String`                             | A UTF-8 binary            |
+//! | `BitArray`                           | A binary                  |
+//! | `List(a)`                            | A proper list             |
+//! | `#(a, b)`                            | A tuple                   |
+//! | `Dict(k, v)`                         | A map                     |
+//! | `Ok(value)`, `Error(reason)`         | `{ok, Value}`, `{error, Reason}` |
+//! | `Some(value)`, `None`                | `{some, Value}`, `none`   |
+//! | `HttpError(404)`, `Red`              | `{http_error, 404}`, `red` |
+//!
+//! The functions in this module build terms like these; [`Convention::Gleam`]
+//! makes the conversions from and to Rust values follow them.
+//! [`inspect`] formats terms like Gleam's `string.inspect`:
+//!
+//! ```
+//! use rust_eterm::terms::gleam::{ self, inspect };
+//!
+//! let user = gleam::constructor("User", vec![
+//!     Box::new(gleam::string("Lucy")),
+//!     Box::new(gleam::some(gleam::list(vec![1u8, 2, 3]))),
+//! ]);
+//! assert_eq!("User(\"Lucy\", Some([1, 2, 3]))", inspect(&*user));
+//! assert_eq!("Ok(Nil)", inspect(&gleam::ok(gleam::nil())));
+//! ```
+//!
+//! [`Convention::Gleam`]: ../enum.Convention.html#variant.Gleam
+//! [`inspect`]: fn.inspect.html
+
+use super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, ETerm, ETuple, EString };
+use super::convert::{ integer, FromETerm };
+use super::pretty::format_float;
+use super::printing::{ code_points, reencode, sorted_entries, write_separated };
+
+use std::str;
+
+/// Gleam's `Nil`.
+pub fn nil() -> EAtom {
+    EAtom::new("nil")
+}
+
+/// Gleam's `True` or `False`.
+pub fn bool(value: bool) -> EAtom {
+    EAtom::new(if value { "true" } else { "false" })
+}
+
+/// A Gleam `String`.
+pub fn string(value: &str) -> EBinary {
+    EBinary::from(value.as_bytes())
+}
+
+/// A Gleam `List`.
+pub fn list<T: ETerm>(elements: Vec<T>) -> EList {
+    elements.into_iter().map(|element| Box::new(element) as Box<dyn ETerm>).collect()
+}
+
+/// `Ok(value)`.
+pub fn ok<T: ETerm>(value: T) -> ETuple {
+    ETuple::from((EAtom::new("ok"), value))
+}
+
+/// `Error(reason)`.
+pub fn error<T: ETerm>(reason: T) -> ETuple {
+    ETuple::from((EAtom::new("error"), reason))
+}
+
+/// `Some(value)` of Gleam's `Option`.
+pub fn some<T: ETerm>(value: T) -> ETuple {
+    ETuple::from((EAtom::new("some"), value))
+}
+
+/// `None` of Gleam's `Option`.
+pub fn none() -> EAtom {
+    EAtom::new("none")
+}
+
+/// A value of a custom type, like `Person("Joe", 42)`: an atom with the
+/// snake case `name` of the constructor if it has no fields, and a tuple
+/// tagged with that atom if it does.
+///
+/// ```
+/// use rust_eterm::terms::gleam::constructor;
+///
+/// assert_eq!("red", constructor("Red", vec![]).to_string());
+/// assert_eq!("{http_error,404}", constructor("HttpError", vec![Box::new(404u16)]).to_string());
+/// ```
+pub fn constructor(name: &str, fields: Vec<Box<dyn ETerm>>) -> Box<dyn ETerm> {
+    let tag = EAtom::new(snake_case(name));
+
+    if fields.is_empty() {
+        Box::new(tag)
+    } else {
+        let mut elements: Vec<Box<dyn ETerm>> = Vec::with_capacity(fields.len() + 1);
+        elements.push(Box::new(tag));
+        elements.extend(fields);
+
+        Box::new(ETuple::new(elements))
+    }
+}
+
+/// Converts a constructor name like `HttpRequest` or `HTTPRequest` to
+/// `http_request`.
+///
+/// The derive macros name tags with a copy of this function, and their tests
+/// check that both agree.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            if previous.is_lowercase() || previous.is_numeric() || (previous.is_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_lowercase());
+    }
+
+    result
+}
+
+/// The name of the constructor that an atom represents, like `HttpError` for
+/// `http_error`, if there is one.
+fn constructor_name(atom: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut previous = None;
+
+    for c in atom.chars() {
+        match (previous, c) {
+            (None, 'a'..='z') => result.push(c.to_ascii_uppercase()),
+            (None, _) | (Some('_'), '_') => return None,
+            (Some(_), '_') => {},
+            (Some('_'), 'a'..='z' | '0'..='9') => result.push(c.to_ascii_uppercase()),
+            (Some(_), 'a'..='z' | '0'..='9') => result.push(c),
+            _ => return None,
+        }
+        previous = Some(c);
+    }
+
+    match previous {
+        Some('_') | None => None,
+        Some(_) => Some(result),
+    }
+}
+
+/// Formats `term` in Gleam syntax, like Gleam's `string.inspect`.
+///
+/// Terms that have no Gleam syntax, like pids and improper lists, are
+/// written as `//erl(...)` with their Erlang representation, and functions
+/// as `//fn(a, b) { ... }`.
+/// Functions with more arguments than there are letters name them `a1`,
+/// `a2` and so on.
+pub fn inspect(term: &dyn ETerm) -> String {
+    let mut out = String::new();
+    write_term(term, &mut out, false);
+
+    out
+}
+
+/// Writes `term` to `out`, with `decoded` as described for [`reencode`].
+fn write_term(term: &dyn ETerm, out: &mut String, decoded: bool) {
+    if let Some(atom) = term.downcast_ref::<EAtom>() {
+        match constructor_name(atom.as_str()) {
+            Some(name) => out.push_str(&name),
+            None => {
+                out.push_str("atom.create_from_string(");
+                write_quoted(atom.as_str(), out);
+                out.push(')');
+            },
+        }
+    } else if let Some(list) = term.downcast_ref::<EList>() {
+        write_list(list.as_slice(), out);
+    } else if let Some(string) = term.downcast_ref::<EString>() {
+        write_list(&code_points(string), out);
+    } else if term.is::<ENil>() {
+        out.push_str("[]");
+    } else if let Some(tuple) = term.downcast_ref::<ETuple>() {
+        let constructor = tuple.as_slice().split_first()
+            .and_then(|(tag, fields)| Some((constructor_name(tag.downcast_ref::<EAtom>()?.as_str())?, fields)));
+
+        match constructor {
+            Some((name, fields)) => {
+                out.push_str(&name);
+                out.push('(');
+                write_separated(fields, out, |field, out| write_term(&**field, out, false));
+                out.push(')');
+            },
+            None => {
+                out.push_str("#(");
+                write_separated(tuple.iter(), out, |element, out| write_term(&**element, out, false));
+                out.push(')');
+            },
+        }
+    } else if let Some(map) = term.downcast_ref::<EMap>() {
+        let entries = sorted_entries(map);
+
+        out.push_str("dict.from_list([");
+        write_separated(entries, out, |(key, value), out| {
+            out.push_str("#(");
+            write_term(&**key, out, false);
+            out.push_str(", ");
+            write_term(&**value, out, false);
+            out.push(')');
+        });
+        out.push_str("])");
+    } else if let Some(binary) = term.downcast_ref::<EBinary>() {
+        match str::from_utf8(binary.as_bytes()) {
+            Ok(text) => write_quoted(text, out),
+            Err(_) => {
+                out.push_str("<<");
+                write_separated(binary.as_bytes(), out, |byte, out| out.push_str(&byte.to_string()));
+                out.push_str(">>");
+            },
+        }
+    } else if let Some(export) = term.downcast_ref::<EExport>() {
+        let arguments: Vec<String> = match export.arity() {
+            arity @ 0..=26 => (b'a'..).take(arity.into()).map(|letter| (letter as char).to_string()).collect(),
+            arity => (1..=arity).map(|i| format!("a{}", i)).collect(),
+        };
+        out.push_str(&format!("//fn({}) {{ ... }}", arguments.join(", ")));
+    } else if let Some(&value) = term.downcast_ref::<f64>() {
+        out.push_str(&format_float(value));
+    } else if let Some(&value) = term.downcast_ref::<f32>() {
+        out.push_str(&format_float(value.into()));
+    } else if integer(term).is_some() {
+        out.push_str(&term.to_string());
+    } else if decoded || term.is::<ENonProperList>() {
+        out.push_str(&format!("//erl({})", term));
+    } else {
+        match reencode(term) {
+            Some(decoded_term) => write_term(&*decoded_term, out, true),
+            None => out.push_str(&format!("//erl({})", term)),
+        }
+    }
+}
+
+/// Writes a list, or `charlist.from_string("...")` for a list of printable
+/// ASCII characters.
+fn write_list(elements: &[Box<dyn ETerm>], out: &mut String) {
+    let chars: Option<String> = elements.iter()
+        .map(|element| match u8::from_eterm(&**element) {
+            Ok(c @ (b'\t' | b'\n' | b'\r' | 0x0c | b' '..=b'~')) => Some(c as char),
+            _ => None,
+        })
+        .collect();
+
+    match chars {
+        Some(chars) if !chars.is_empty() => {
+            out.push_str("charlist.from_string(");
+            write_quoted(&chars, out);
+            out.push(')');
+        },
+        _ => {
+            out.push('[');
+            write_separated(elements, out, |element, out| write_term(&**element, out, false));
+            out.push(']');
+        },
+    }
+}
+
+/// Writes `text` as a Gleam string literal.
+fn write_quoted(text: &str, out: &mut String) {
+    out.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{c}' => out.push_str("\\f"),
+            '\0'..='\u{1f}' | '\u{7f}' => out.push_str(&format!("\\u{{{:04X}}}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ constructor, constructor_name, inspect, snake_case };
+    use super::super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, ETuple, EString };
+    use super::super::decode::DecodeOptions;
+    use super::super::{ Convention, FromETerm };
+
+    #[test]
+    fn names() {
+        assert_eq!("person", snake_case("Person"));
+        assert_eq!("http_error", snake_case("HTTPError"));
+        assert_eq!("point3_d", snake_case("Point3D"));
+
+        let cases = [
+            ("ok", Some("Ok")),
+            ("nil", Some("Nil")),
+            ("http_error", Some("HttpError")),
+            ("point3_d", Some("Point3D")),
+            ("v_1", Some("V1")),
+            ("", None),
+            ("Ok", None),
+            ("_ok", None),
+            ("1ok", None),
+            ("ok_", None),
+            ("a__b", None),
+            ("a-b", None),
+            ("été", None),
+        ];
+
+        for (atom, expected) in cases.iter() {
+            assert_eq!(expected.map(String::from), constructor_name(atom), "{}", atom);
+        }
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!("{ok,1}", super::ok(1u8).to_string());
+        assert_eq!("{error,nil}", super::error(super::nil()).to_string());
+        assert_eq!("{some,<<104,105>>}", super::some(super::string("hi")).to_string());
+        assert_eq!("none", super::none().to_string());
+        assert_eq!("false", super::bool(false).to_string());
+        assert_eq!("[]", super::list::<u8>(vec![]).to_string());
+        assert_eq!("{person,<<74>>,42}", constructor("Person", vec![Box::new(super::string("J")), Box::new(42u8)]).to_string());
+
+        let options = DecodeOptions::default().with_convention(Convention::Gleam);
+        assert_eq!(Some(5), Option::<u8>::from_eterm_with(&super::some(5u8), &options).unwrap());
+        assert_eq!(None, Option::<u8>::from_eterm_with(&super::none(), &options).unwrap());
+        assert_eq!("Lucy", String::from_eterm_with(&super::string("Lucy"), &options).unwrap());
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!("True", inspect(&EAtom::new("true")));
+        assert_eq!("Nil", inspect(&EAtom::new("nil")));
+        assert_eq!("Red", inspect(&EAtom::new("red")));
+        assert_eq!("atom.create_from_string(\"Hello world\")", inspect(&EAtom::new("Hello world")));
+        assert_eq!("42", inspect(&42u8));
+        assert_eq!("-1.5", inspect(&-1.5f64));
+        assert_eq!("1.0e20", inspect(&1e20f64));
+
+        assert_eq!("\"José\"", inspect(&EBinary::from("José".as_bytes())));
+        assert_eq!("\"\"", inspect(&EBinary::new(vec![])));
+        assert_eq!("\"a\\\"b\\\\c\\n\\u{001B}\"", inspect(&EBinary::from(&b"a\"b\\c\n\x1b"[..])));
+        assert_eq!("<<255, 0>>", inspect(&EBinary::new(vec![255, 0])));
+
+        assert_eq!("[]", inspect(&ENil));
+        assert_eq!("[1, 2, 3]", inspect(&super::list(vec![1u8, 2, 3])));
+        assert_eq!("charlist.from_string(\"hi\")", inspect(&EString::new("hi")));
+        assert_eq!("//erl([1|2])", inspect(&ENonProperList::new(vec![Box::new(1u8)], Box::new(2u8))));
+
+        assert_eq!("#()", inspect(&ETuple::new(vec![])));
+        assert_eq!("#(1, \"a\")", inspect(&ETuple::from((1u8, super::string("a")))));
+        assert_eq!("#(atom.create_from_string(\"A\"), 1)", inspect(&ETuple::from((EAtom::new("A"), 1u8))));
+        assert_eq!("Error(HttpError(404))", inspect(&super::error(ETuple::from((EAtom::new("http_error"), 404u16)))));
+
+        let dict = EMap::new(vec![
+            (Box::new(super::string("b")), Box::new(2u8)),
+            (Box::new(super::string("a")), Box::new(1u8)),
+        ]);
+        assert_eq!("dict.from_list([#(\"a\", 1), #(\"b\", 2)])", inspect(&dict));
+
+        assert_eq!("//fn(a, b) { ... }", inspect(&EExport::new("lists", "map", 2)));
+        assert_eq!("//fn() { ... }", inspect(&EExport::new("erlang", "self", 0)));
+        assert!(inspect(&EExport::new("m", "f", 26)).ends_with("y, z) { ... }"));
+        assert!(inspect(&EExport::new("m", "f", 27)).starts_with("//fn(a1, a2, "));
+        assert!(inspect(&EExport::new("m", "f", 255)).ends_with(", a254, a255) { ... }"));
+        assert_eq!("//erl(<node@host.1.2>)", inspect(&EPid::new("node@host", 1, 2, 3)));
+        assert_eq!("[True, Nil]", inspect(&EList::new(vec![Box::new(true), Box::new(EAtom::new("nil"))])));
+    }
+}
@@ -0,0 +1,634 @@
+//! Converting terms to and from JSON.
+//!
+//! [`to_json`] and [`from_json`] convert between terms and
+//! `serde_json::Value`s, following the mappings chosen in [`JsonOptions`]:
+//!
+//! | Term                        | JSON                                               |
+//! |-----------------------------|----------------------------------------------------|
+//! | Integers and floats         | Numbers                                            |
+//! | `true`, `false`, `null`     | `true`, `false`, `null`                            |
+//! | Other atoms                 | `"name"` or `{"$atom": "name"}`                    |
+//! | Lists                       | Arrays                                             |
+//! | Tuples                      | Arrays or `{"$tuple": [..]}`                       |
+//! | Binaries                    | UTF-8 strings or base64 strings                    |
+//! | Maps                        | Objects, or `[[key, value], ..]` or `{"$map": [[key, value], ..]}` |
+//! | Integers beyond 64 bits     | `"123.."` or `{"$integer": "123.."}`               |
+//! | Improper lists              | `{"$improper": {"elements": [..], "tail": ..}}`    |
+//! | Pids, ports and exports     | `{"$pid": {..}}`, `{"$port": {..}}`, `{"$export": {..}}` |
+//!
+//! Objects with a single key that is one of the tags above are always
+//! converted to the tagged term, whatever the options.
+//!
+//! The default options produce the JSON an HTTP client would expect, which
+//! loses information: `"ok"` can't be told apart from `<<"ok">>`.
+//! [`JsonOptions::lossless`] tags everything that would otherwise be
+//! ambiguous, so that converting the JSON back gives a term that is equal to
+//! the original:
+//!
+//! ```
+//! use rust_eterm::terms::{ EAtom, ETerm, ETuple };
+//! use rust_eterm::terms::json::{ from_json, to_json, JsonOptions };
+//! use rust_eterm::terms::order::compare;
+//! use std::cmp::Ordering;
+//!
+//! let term = ETuple::from((EAtom::new("ok"), 42u8));
+//!
+//! let json = to_json(&term, &JsonOptions::default()).unwrap();
+//! assert_eq!(r#"["ok",42]"#, json.to_string());
+//!
+//! let options = JsonOptions::lossless();
+//! let json = to_json(&term, &options).unwrap();
+//! assert_eq!(r#"{"$tuple":[{"$atom":"ok"},42]}"#, json.to_string());
+//! assert_eq!(Ordering::Equal, compare(&term, &*from_json(&json, &options).unwrap()).unwrap());
+//! ```
+//!
+//! [`to_json`]: fn.to_json.html
+//! [`from_json`]: fn.from_json.html
+//! [`JsonOptions`]: struct.JsonOptions.html
+//! [`JsonOptions::lossless`]: struct.JsonOptions.html#method.lossless
+
+#[cfg(feature="bigint")]
+use {
+    num_bigint::BigInt,
+    std::str::FromStr,
+};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{ Map, Number, Value };
+
+use super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, EPort, ETerm, ETuple, EString };
+use super::convert::{ integer, Integer };
+use super::printing::reencode;
+use super::super::error::Error;
+
+use std::convert::TryFrom;
+use std::str;
+
+/// How atoms other than `true`, `false` and `null` are converted to JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtomMapping {
+    /// `"name"`.
+    String,
+    /// `{"$atom": "name"}`.
+    Tagged,
+}
+
+/// How tuples are converted to JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TupleMapping {
+    /// `[a, b]`.
+    Array,
+    /// `{"$tuple": [a, b]}`.
+    Tagged,
+}
+
+/// How binaries are converted to and from JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryMapping {
+    /// The text of UTF-8 binaries, and `{"$binary": "base64"}` for binaries
+    /// that are not valid UTF-8.
+    /// Strings are converted to UTF-8 binaries.
+    Utf8,
+    /// The binary in base64, and strings are decoded from base64.
+    Base64,
+}
+
+/// How maps are converted to JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapMapping {
+    /// An object when all keys convert to distinct strings, and a list of
+    /// `[key, value]` pairs otherwise.
+    Object,
+    /// `{"$map": [[key, value], ..]}`.
+    Tagged,
+}
+
+/// How integers that don't fit in 64 bits are converted to JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BigIntegerMapping {
+    /// `"123.."`.
+    String,
+    /// `{"$integer": "123.."}`.
+    Tagged,
+}
+
+/// Options that choose how terms are mapped to JSON.
+///
+/// The defaults produce plain JSON: atoms as strings, tuples as arrays,
+/// binaries as UTF-8 strings, maps as objects and big integers as strings.
+#[derive(Clone, Debug)]
+pub struct JsonOptions {
+    atoms: AtomMapping,
+    tuples: TupleMapping,
+    binaries: BinaryMapping,
+    maps: MapMapping,
+    big_integers: BigIntegerMapping,
+}
+
+impl Default for JsonOptions {
+    fn default() -> JsonOptions {
+        JsonOptions {
+            atoms: AtomMapping::String,
+            tuples: TupleMapping::Array,
+            binaries: BinaryMapping::Utf8,
+            maps: MapMapping::Object,
+            big_integers: BigIntegerMapping::String,
+        }
+    }
+}
+
+impl JsonOptions {
+    /// Options that tag every term that would otherwise be ambiguous, so
+    /// that [`from_json`] gives back a term that is equal to the one passed
+    /// to [`to_json`].
+    ///
+    /// Lists of integers, like the ones decoded from a `STRING_EXT`, come
+    /// back as lists of integers, which are equal to the original.
+    ///
+    /// [`from_json`]: fn.from_json.html
+    /// [`to_json`]: fn.to_json.html
+    pub fn lossless() -> JsonOptions {
+        JsonOptions {
+            atoms: AtomMapping::Tagged,
+            tuples: TupleMapping::Tagged,
+            binaries: BinaryMapping::Utf8,
+            maps: MapMapping::Tagged,
+            big_integers: BigIntegerMapping::Tagged,
+        }
+    }
+
+    pub fn atoms(&self) -> AtomMapping {
+        self.atoms
+    }
+
+    pub fn with_atoms(mut self, atoms: AtomMapping) -> JsonOptions {
+        self.atoms = atoms;
+        self
+    }
+
+    pub fn tuples(&self) -> TupleMapping {
+        self.tuples
+    }
+
+    pub fn with_tuples(mut self, tuples: TupleMapping) -> JsonOptions {
+        self.tuples = tuples;
+        self
+    }
+
+    pub fn binaries(&self) -> BinaryMapping {
+        self.binaries
+    }
+
+    pub fn with_binaries(mut self, binaries: BinaryMapping) -> JsonOptions {
+        self.binaries = binaries;
+        self
+    }
+
+    pub fn maps(&self) -> MapMapping {
+        self.maps
+    }
+
+    pub fn with_maps(mut self, maps: MapMapping) -> JsonOptions {
+        self.maps = maps;
+        self
+    }
+
+    pub fn big_integers(&self) -> BigIntegerMapping {
+        self.big_integers
+    }
+
+    pub fn with_big_integers(mut self, big_integers: BigIntegerMapping) -> JsonOptions {
+        self.big_integers = big_integers;
+        self
+    }
+}
+
+fn tagged(tag: &str, value: Value) -> Value {
+    let mut object = Map::new();
+    object.insert(tag.to_string(), value);
+
+    Value::Object(object)
+}
+
+/// Converts `term` to JSON.
+///
+/// Fails for floats that JSON can't represent, and for terms of a type that
+/// this crate can't decode.
+pub fn to_json(term: &dyn ETerm, options: &JsonOptions) -> Result<Value, Error> {
+    if let Some(atom) = term.downcast_ref::<EAtom>() {
+        return Ok(match atom.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            name => match options.atoms {
+                AtomMapping::String => Value::String(name.to_string()),
+                AtomMapping::Tagged => tagged("$atom", Value::String(name.to_string())),
+            },
+        });
+    }
+
+    if let Some(&value) = term.downcast_ref::<bool>() {
+        return Ok(Value::Bool(value));
+    }
+
+    if let Some(list) = term.downcast_ref::<EList>() {
+        return array(list.iter(), options);
+    }
+
+    if let Some(string) = term.downcast_ref::<EString>() {
+        return Ok(Value::Array(string.as_str().chars().map(|c| Value::from(c as u32)).collect()));
+    }
+
+    if term.is::<ENil>() {
+        return Ok(Value::Array(vec![]));
+    }
+
+    if let Some(list) = term.downcast_ref::<ENonProperList>() {
+        let mut object = Map::new();
+        object.insert("elements".to_string(), array(list.data().iter(), options)?);
+        object.insert("tail".to_string(), to_json(list.tail(), options)?);
+
+        return Ok(tagged("$improper", Value::Object(object)));
+    }
+
+    if let Some(tuple) = term.downcast_ref::<ETuple>() {
+        let elements = array(tuple.iter(), options)?;
+
+        return Ok(match options.tuples {
+            TupleMapping::Array => elements,
+            TupleMapping::Tagged => tagged("$tuple", elements),
+        });
+    }
+
+    if let Some(map) = term.downcast_ref::<EMap>() {
+        return map_to_json(map, options);
+    }
+
+    if let Some(binary) = term.downcast_ref::<EBinary>() {
+        let base64 = || Value::String(BASE64.encode(binary.as_bytes()));
+
+        return Ok(match (options.binaries, str::from_utf8(binary.as_bytes())) {
+            (BinaryMapping::Utf8, Ok(text)) => Value::String(text.to_string()),
+            (BinaryMapping::Utf8, Err(_)) => tagged("$binary", base64()),
+            (BinaryMapping::Base64, _) => base64(),
+        });
+    }
+
+    if let Some(pid) = term.downcast_ref::<EPid>() {
+        let mut object = Map::new();
+        object.insert("node".to_string(), Value::from(pid.node().as_str()));
+        object.insert("id".to_string(), Value::from(pid.id()));
+        object.insert("serial".to_string(), Value::from(pid.serial()));
+        object.insert("creation".to_string(), Value::from(pid.creation()));
+
+        return Ok(tagged("$pid", Value::Object(object)));
+    }
+
+    if let Some(port) = term.downcast_ref::<EPort>() {
+        let mut object = Map::new();
+        object.insert("node".to_string(), Value::from(port.node().as_str()));
+        object.insert("id".to_string(), Value::from(port.id()));
+        object.insert("creation".to_string(), Value::from(port.creation()));
+
+        return Ok(tagged("$port", Value::Object(object)));
+    }
+
+    if let Some(export) = term.downcast_ref::<EExport>() {
+        let mut object = Map::new();
+        object.insert("module".to_string(), Value::from(export.module().as_str()));
+        object.insert("function".to_string(), Value::from(export.function().as_str()));
+        object.insert("arity".to_string(), Value::from(export.arity()));
+
+        return Ok(tagged("$export", Value::Object(object)));
+    }
+
+    let float = term.downcast_ref::<f64>().copied()
+        .or_else(|| term.downcast_ref::<f32>().map(|&value| value.into()));
+
+    if let Some(value) = float {
+        return Number::from_f64(value)
+            .map(Value::Number)
+            .ok_or_else(|| Error::Message(format!("Float {} can't be represented in JSON", value)));
+    }
+
+    if let Some(value) = integer(term) {
+        let small = match value {
+            Integer::Signed(value) => i64::try_from(value).ok().map(Value::from),
+            Integer::Unsigned(value) => u64::try_from(value).ok().map(Value::from),
+            #[cfg(feature="bigint")]
+            Integer::Big => None,
+        };
+
+        return Ok(small.unwrap_or_else(|| {
+            let digits = Value::String(term.to_string());
+
+            match options.big_integers {
+                BigIntegerMapping::String => digits,
+                BigIntegerMapping::Tagged => tagged("$integer", digits),
+            }
+        }));
+    }
+
+    match reencode(term) {
+        Some(decoded) => to_json(&*decoded, options),
+        _ => Err(Error::Message(format!("Can't convert {} to JSON", term))),
+    }
+}
+
+fn array<'a, I>(elements: I, options: &JsonOptions) -> Result<Value, Error>
+    where I: Iterator<Item = &'a Box<dyn ETerm>>
+{
+    elements.map(|element| to_json(&**element, options))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Value::Array)
+}
+
+fn map_to_json(map: &EMap, options: &JsonOptions) -> Result<Value, Error> {
+    let mut pairs = Vec::new();
+    for (key, value) in map.iter() {
+        pairs.push((to_json(&**key, options)?, to_json(&**value, options)?));
+    }
+
+    if options.maps == MapMapping::Object {
+        let mut object = Map::new();
+
+        for (key, value) in pairs.iter() {
+            match key {
+                Value::String(key) if !object.contains_key(key) => object.insert(key.clone(), value.clone()),
+                _ => break,
+            };
+        }
+
+        if object.len() == pairs.len() {
+            return Ok(Value::Object(object));
+        }
+    }
+
+    let pairs = Value::Array(pairs.into_iter().map(|(key, value)| Value::Array(vec![key, value])).collect());
+
+    Ok(match options.maps {
+        MapMapping::Object => pairs,
+        MapMapping::Tagged => tagged("$map", pairs),
+    })
+}
+
+/// Converts JSON to a term.
+///
+/// Numbers become integers or floats, strings become binaries, arrays
+/// become lists, and objects become maps with binary keys, unless they are
+/// one of the tagged objects that [`to_json`] writes.
+///
+/// [`to_json`]: fn.to_json.html
+pub fn from_json(value: &Value, options: &JsonOptions) -> Result<Box<dyn ETerm>, Error> {
+    Ok(match value {
+        Value::Null => Box::new(EAtom::new("null")),
+        Value::Bool(value) => Box::new(EAtom::new(if *value { "true" } else { "false" })),
+        Value::Number(number) => number_from_json(number),
+        Value::String(text) => Box::new(binary_from_json(text, options.binaries)?),
+        Value::Array(elements) if elements.is_empty() => Box::new(ENil),
+        Value::Array(elements) => Box::new(EList::new(list_from_json(elements, options)?)),
+        Value::Object(object) => {
+            if object.len() == 1 {
+                let (tag, value) = object.iter().next().unwrap();
+
+                if let Some(term) = tagged_from_json(tag, value, options)? {
+                    return Ok(term);
+                }
+            }
+
+            let mut entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)> = Vec::with_capacity(object.len());
+            for (key, value) in object.iter() {
+                entries.push((Box::new(binary_from_json(key, options.binaries)?), from_json(value, options)?));
+            }
+
+            Box::new(EMap::new(entries))
+        },
+    })
+}
+
+/// Converts a number to the type [`decode`] would give it.
+///
+/// [`decode`]: ../decode/fn.decode.html
+fn number_from_json(number: &Number) -> Box<dyn ETerm> {
+    if let Some(value) = number.as_u64() {
+        if let Ok(value) = u8::try_from(value) {
+            return Box::new(value);
+        }
+    }
+
+    match (number.as_i64(), number.as_u64()) {
+        (Some(value), _) => match i32::try_from(value) {
+            Ok(value) => Box::new(value),
+            Err(_) => Box::new(value),
+        },
+        (None, Some(value)) => Box::new(value),
+        (None, None) => Box::new(number.as_f64().unwrap_or(0.0)),
+    }
+}
+
+fn binary_from_json(text: &str, binaries: BinaryMapping) -> Result<EBinary, Error> {
+    match binaries {
+        BinaryMapping::Utf8 => Ok(EBinary::from(text.as_bytes())),
+        BinaryMapping::Base64 => base64_from_json(text),
+    }
+}
+
+fn base64_from_json(text: &str) -> Result<EBinary, Error> {
+    BASE64.decode(text)
+        .map(EBinary::new)
+        .map_err(|e| Error::Message(format!("Invalid base64 {:?}: {}", text, e)))
+}
+
+fn list_from_json(elements: &[Value], options: &JsonOptions) -> Result<Vec<Box<dyn ETerm>>, Error> {
+    elements.iter().map(|element| from_json(element, options)).collect()
+}
+
+/// Converts `{tag: value}` to a term, if `tag` is one of the tags that
+/// [`to_json`] writes.
+///
+/// [`to_json`]: fn.to_json.html
+fn tagged_from_json(tag: &str, value: &Value, options: &JsonOptions) -> Result<Option<Box<dyn ETerm>>, Error> {
+    let invalid = || Error::Message(format!("Invalid {} value: {}", tag, value));
+    let string = || value.as_str().ok_or_else(invalid);
+    let array = || value.as_array().ok_or_else(invalid);
+    let field = |name: &str| value.get(name).ok_or_else(invalid);
+    let atom_field = |name: &str| field(name)?.as_str().map(EAtom::new).ok_or_else(invalid);
+    let u32_field = |name: &str| field(name)?.as_u64().and_then(|n| u32::try_from(n).ok()).ok_or_else(invalid);
+
+    Ok(Some(match tag {
+        "$atom" => Box::new(EAtom::new(string()?)),
+        "$tuple" => Box::new(ETuple::new(list_from_json(array()?, options)?)),
+        "$binary" => Box::new(base64_from_json(string()?)?),
+        "$integer" => integer_from_json(string()?).ok_or_else(invalid)?,
+        "$map" => {
+            let mut entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)> = Vec::new();
+            for pair in array()? {
+                match pair.as_array().map(Vec::as_slice) {
+                    Some([key, value]) => entries.push((from_json(key, options)?, from_json(value, options)?)),
+                    _ => return Err(invalid()),
+                }
+            }
+
+            Box::new(EMap::new(entries))
+        },
+        "$improper" => {
+            let elements = field("elements")?.as_array().ok_or_else(invalid)?;
+            Box::new(ENonProperList::new(list_from_json(elements, options)?, from_json(field("tail")?, options)?))
+        },
+        "$pid" => Box::new(EPid::new(atom_field("node")?, u32_field("id")?, u32_field("serial")?, u32_field("creation")?)),
+        "$port" => Box::new(EPort::new(atom_field("node")?, u32_field("id")?, u32_field("creation")?)),
+        "$export" => {
+            let arity = field("arity")?.as_u64().and_then(|n| u8::try_from(n).ok()).ok_or_else(invalid)?;
+            Box::new(EExport::new(atom_field("module")?, atom_field("function")?, arity))
+        },
+        _ => return Ok(None),
+    }))
+}
+
+fn integer_from_json(digits: &str) -> Option<Box<dyn ETerm>> {
+    if let Ok(value) = digits.parse::<i128>() {
+        return Some(Box::new(value));
+    }
+
+    if let Ok(value) = digits.parse::<u128>() {
+        return Some(Box::new(value));
+    }
+
+    #[cfg(feature="bigint")]
+    {
+        if let Ok(value) = BigInt::from_str(digits) {
+            return Some(Box::new(value));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ from_json, to_json, AtomMapping, BinaryMapping, JsonOptions, MapMapping, TupleMapping };
+    use super::super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, EPort, ETerm, ETuple, EString };
+    use super::super::order::compare;
+
+    use serde_json::{ json, Value };
+    use std::cmp::Ordering;
+
+    fn map(entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)>) -> EMap {
+        EMap::new(entries)
+    }
+
+    #[test]
+    fn plain() {
+        let options = JsonOptions::default();
+        let cases: Vec<(Box<dyn ETerm>, Value)> = vec![
+            (Box::new(1u8), json!(1)),
+            (Box::new(-70000i32), json!(-70000)),
+            (Box::new(1.5f64), json!(1.5)),
+            (Box::new(u64::MAX), json!(u64::MAX)),
+            (Box::new(u128::MAX), json!(u128::MAX.to_string())),
+            (Box::new(EAtom::new("true")), json!(true)),
+            (Box::new(false), json!(false)),
+            (Box::new(EAtom::new("null")), json!(null)),
+            (Box::new(EAtom::new("ok")), json!("ok")),
+            (Box::new(ENil), json!([])),
+            (Box::new(EString::new("ab")), json!([97, 98])),
+            (Box::new(ETuple::from((EAtom::new("ok"), 1u8))), json!(["ok", 1])),
+            (Box::new(EBinary::from("José".as_bytes())), json!("José")),
+            (Box::new(EBinary::new(vec![255])), json!({"$binary": "/w=="})),
+            (Box::new(map(vec![(Box::new(EAtom::new("a")), Box::new(1u8)), (Box::new(EBinary::from(&b"b"[..])), Box::new(2u8))])), json!({"a": 1, "b": 2})),
+            (Box::new(map(vec![(Box::new(1u8), Box::new(2u8))])), json!([[1, 2]])),
+            (Box::new(map(vec![(Box::new(EAtom::new("a")), Box::new(1u8)), (Box::new(EBinary::from(&b"a"[..])), Box::new(2u8))])), json!([["a", 1], ["a", 2]])),
+            (Box::new(ENonProperList::new(vec![Box::new(1u8)], Box::new(2u8))), json!({"$improper": {"elements": [1], "tail": 2}})),
+            (Box::new(EPid::new("a@b", 1, 2, 3)), json!({"$pid": {"node": "a@b", "id": 1, "serial": 2, "creation": 3}})),
+            (Box::new(EPort::new("a@b", 1, 3)), json!({"$port": {"node": "a@b", "id": 1, "creation": 3}})),
+            (Box::new(EExport::new("lists", "map", 2)), json!({"$export": {"module": "lists", "function": "map", "arity": 2}})),
+        ];
+
+        for (term, expected) in cases {
+            assert_eq!(expected, to_json(&*term, &options).unwrap(), "{}", term);
+        }
+
+        assert!(to_json(&f64::NAN, &options).is_err());
+    }
+
+    #[test]
+    fn mappings() {
+        let term = ETuple::from((EAtom::new("ok"), EBinary::new(vec![1, 2, 3]), map(vec![(Box::new(EAtom::new("a")), Box::new(1u8))])));
+
+        let options = JsonOptions::default()
+            .with_atoms(AtomMapping::Tagged)
+            .with_tuples(TupleMapping::Tagged)
+            .with_binaries(BinaryMapping::Base64)
+            .with_maps(MapMapping::Tagged);
+        let json = json!({"$tuple": [{"$atom": "ok"}, "AQID", {"$map": [[{"$atom": "a"}, 1]]}]});
+        assert_eq!(json, to_json(&term, &options).unwrap());
+
+        let decoded = from_json(&json, &options).unwrap();
+        assert_eq!(Ordering::Equal, compare(&term, &*decoded).unwrap());
+        assert!(from_json(&json!("not base64!"), &options).is_err());
+    }
+
+    #[test]
+    fn plain_from_json() {
+        let options = JsonOptions::default();
+        let term = from_json(&json!({"name": "José", "tags": [1, 300, -1, 1.5, 5000000000u64, null, true]}), &options).unwrap();
+
+        let map = term.downcast_ref::<EMap>().unwrap();
+        let name = map.iter().find(|(key, _)| key.to_string() == "<<110,97,109,101>>").unwrap();
+        assert_eq!("José".as_bytes(), name.1.downcast_ref::<EBinary>().unwrap().as_bytes());
+
+        let tags = map.iter().find(|(key, _)| key.downcast_ref::<EBinary>().unwrap().as_bytes() == b"tags").unwrap();
+        assert_eq!("[1,300,-1,1.5,5000000000,null,true]", tags.1.to_string());
+        let tags = tags.1.downcast_ref::<EList>().unwrap();
+        assert!(tags.as_slice()[0].is::<u8>());
+        assert!(tags.as_slice()[1].is::<i32>());
+        assert!(tags.as_slice()[4].is::<i64>());
+
+        assert!(from_json(&json!([]), &options).unwrap().is::<ENil>());
+        assert!(from_json(&json!({"$pid": {"node": "a@b"}}), &options).is_err());
+    }
+
+    #[test]
+    fn lossless() {
+        let options = JsonOptions::lossless();
+        let terms: Vec<Box<dyn ETerm>> = vec![
+            Box::new(EAtom::new("ok")),
+            Box::new(EAtom::new("null")),
+            Box::new(EBinary::from(&b"ok"[..])),
+            Box::new(EBinary::new(vec![0, 255])),
+            Box::new(EString::new("text")),
+            Box::new(i128::MIN),
+            Box::new(u128::MAX),
+            Box::new(-0.5f64),
+            Box::new(ETuple::new(vec![])),
+            Box::new(ETuple::from((EAtom::new("error"), EList::new(vec![Box::new(1u8), Box::new(ETuple::from((2u8,)))])))),
+            Box::new(map(vec![
+                (Box::new(EAtom::new("$atom")), Box::new(EBinary::from(&b"x"[..]))),
+                (Box::new(ETuple::from((1u8, 2u8))), Box::new(ENil)),
+            ])),
+            Box::new(map(vec![(Box::new(EBinary::from(&b"$tuple"[..])), Box::new(ENil))])),
+            Box::new(ENonProperList::new(vec![Box::new(EAtom::new("a"))], Box::new(EAtom::new("b")))),
+            Box::new(EPid::new("a@b", 1, 2, 3)),
+            Box::new(EExport::new("lists", "map", 2)),
+        ];
+
+        for term in terms {
+            let json = to_json(&*term, &options).unwrap();
+            let decoded = from_json(&serde_json::from_str(&json.to_string()).unwrap(), &options).unwrap();
+
+            assert_eq!(Ordering::Equal, compare(&*term, &*decoded).unwrap(), "{} became {} via {}", term, decoded, json);
+        }
+    }
+
+    #[cfg(feature="bigint")]
+    #[test]
+    fn big_integers() {
+        use num_bigint::BigInt;
+
+        let big = BigInt::from(u128::MAX) * BigInt::from(1000);
+        let json = to_json(&big, &JsonOptions::lossless()).unwrap();
+        assert_eq!(json!({"$integer": big.to_string()}), json);
+        assert_eq!(Ordering::Equal, compare(&big, &*from_json(&json, &JsonOptions::lossless()).unwrap()).unwrap());
+    }
+}
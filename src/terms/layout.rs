@@ -0,0 +1,194 @@
+//! Describes how the bytes of every encoded term are laid out, so that a term
+//! can be delimited without decoding it.
+//!
+//! Every term tag is followed by a fixed-size header, after which come some
+//! raw bytes, some nested terms and again some raw bytes, in that order.
+//! The sizes of those parts are determined by the tag and its header.
+
+use super::TermTag;
+use super::super::error::Error;
+
+use std::convert::TryInto;
+use std::io;
+
+/// The sizes of the parts following the header of an encoded term.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Layout {
+    /// The amount of raw bytes directly following the header.
+    pub leading: usize,
+    /// The amount of nested terms following the leading bytes.
+    pub terms: usize,
+    /// The amount of raw bytes following the nested terms.
+    pub trailing: usize,
+}
+
+impl Layout {
+    fn bytes(amount: usize) -> Layout {
+        Layout { leading: amount, terms: 0, trailing: 0 }
+    }
+
+    fn terms(amount: usize) -> Layout {
+        Layout { leading: 0, terms: amount, trailing: 0 }
+    }
+
+    fn terms_then_bytes(terms: usize, trailing: usize) -> Layout {
+        Layout { leading: 0, terms, trailing }
+    }
+}
+
+/// The amount of bytes between the tag and the variable-length part of a term.
+pub(crate) fn header_size(tag: TermTag) -> usize {
+    match tag {
+        TermTag::AtomCacheRef => 0,
+        TermTag::SmallInteger => 0,
+        TermTag::Integer => 0,
+        TermTag::Float => 0,
+        TermTag::NewFloat => 0,
+        TermTag::Port => 0,
+        TermTag::NewPort => 0,
+        TermTag::Pid => 0,
+        TermTag::NewPid => 0,
+        TermTag::SmallTuple => 1,
+        TermTag::LargeTuple => 4,
+        TermTag::Map => 4,
+        TermTag::Nil => 0,
+        TermTag::String => 2,
+        TermTag::List => 4,
+        TermTag::Binary => 4,
+        TermTag::BitBinary => 5,
+        TermTag::SmallBig => 2,
+        TermTag::LargeBig => 5,
+        TermTag::Reference => 0,
+        TermTag::NewReference => 2,
+        TermTag::NewerReference => 2,
+        TermTag::Fun => 4,
+        TermTag::NewFun => 4,
+        TermTag::Export => 0,
+        TermTag::AtomUtf8 => 2,
+        TermTag::SmallAtomUtf8 => 1,
+        TermTag::Atom => 2,
+        TermTag::SmallAtom => 1,
+    }
+}
+
+/// Computes the layout of a term from its tag and its header, which must be
+/// exactly [`header_size`] bytes long.
+///
+/// [`header_size`]: fn.header_size.html
+pub(crate) fn layout(tag: TermTag, header: &[u8]) -> Result<Layout, Error> {
+    debug_assert_eq!(header.len(), header_size(tag));
+
+    Ok(match tag {
+        TermTag::AtomCacheRef => Layout::bytes(1),
+        TermTag::SmallInteger => Layout::bytes(1),
+        TermTag::Integer => Layout::bytes(4),
+        TermTag::Float => Layout::bytes(31),
+        TermTag::NewFloat => Layout::bytes(8),
+        TermTag::Port => Layout::terms_then_bytes(1, 5),
+        TermTag::NewPort => Layout::terms_then_bytes(1, 8),
+        TermTag::Pid => Layout::terms_then_bytes(1, 9),
+        TermTag::NewPid => Layout::terms_then_bytes(1, 12),
+        TermTag::SmallTuple => Layout::terms(header[0] as usize),
+        TermTag::LargeTuple => Layout::terms(be_u32(header)),
+        TermTag::Map => Layout::terms(be_u32(header).checked_mul(2)
+            .ok_or_else(|| Error::Message("Map arity is too large".to_string()))?),
+        TermTag::Nil => Layout::bytes(0),
+        TermTag::String => Layout::bytes(be_u16(header)),
+        TermTag::List => Layout::terms(be_u32(header).checked_add(1)
+            .ok_or_else(|| Error::Message("List length is too large".to_string()))?),
+        TermTag::Binary => Layout::bytes(be_u32(header)),
+        TermTag::BitBinary => Layout::bytes(be_u32(&header[..4])),
+        TermTag::SmallBig => Layout::bytes(header[0] as usize),
+        TermTag::LargeBig => Layout::bytes(be_u32(&header[..4])),
+        TermTag::Reference => Layout::terms_then_bytes(1, 5),
+        TermTag::NewReference => Layout::terms_then_bytes(1, 1 + 4 * be_u16(header)),
+        TermTag::NewerReference => Layout::terms_then_bytes(1, 4 + 4 * be_u16(header)),
+        // Pid, Module, Index, Uniq and the free variables.
+        TermTag::Fun => Layout::terms(be_u32(header).checked_add(4)
+            .ok_or_else(|| Error::Message("FUN_EXT has too many free variables".to_string()))?),
+        TermTag::NewFun => {
+            // Size includes the size field itself.
+            let size = be_u32(header).checked_sub(4)
+                .ok_or_else(|| Error::Message("NEW_FUN_EXT size is too small".to_string()))?;
+            Layout::bytes(size)
+        },
+        TermTag::Export => Layout::terms(3),
+        TermTag::AtomUtf8 => Layout::bytes(be_u16(header)),
+        TermTag::SmallAtomUtf8 => Layout::bytes(header[0] as usize),
+        TermTag::Atom => Layout::bytes(be_u16(header)),
+        TermTag::SmallAtom => Layout::bytes(header[0] as usize),
+    })
+}
+
+/// Computes the amount of bytes taken by the encoded term at the start of
+/// `bytes`, without decoding it.
+pub(crate) fn term_size(bytes: &[u8]) -> Result<usize, Error> {
+    walk(bytes, |_, _, _| Ok(()))
+}
+
+/// Walks over the encoded term at the start of `bytes`, calling `visit` with
+/// the tag, the header and the leading bytes of every (nested) term in
+/// encoded order.
+///
+/// Returns the amount of bytes taken by the term.
+pub(crate) fn walk<F>(bytes: &[u8], mut visit: F) -> Result<usize, Error>
+    where F: FnMut(TermTag, &[u8], &[u8]) -> Result<(), Error>
+{
+    enum Step {
+        Terms(usize),
+        Bytes(usize),
+    }
+
+    let mut position = 0;
+    let mut steps = vec![Step::Terms(1)];
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Bytes(amount) => position = advance(bytes, position, amount)?,
+            Step::Terms(0) => {},
+            Step::Terms(amount) => {
+                steps.push(Step::Terms(amount - 1));
+
+                let tag = read_tag(bytes, position)?;
+                let start = advance(bytes, position, 1)?;
+                let end = advance(bytes, start, header_size(tag))?;
+                let header = &bytes[start..end];
+
+                let layout = layout(tag, header)?;
+                position = advance(bytes, end, layout.leading)?;
+
+                visit(tag, header, &bytes[end..position])?;
+
+                // These are handled last-in, first-out.
+                steps.push(Step::Bytes(layout.trailing));
+                steps.push(Step::Terms(layout.terms));
+            },
+        }
+    }
+
+    Ok(position)
+}
+
+/// Reads the tag at `position` in `bytes`.
+pub(crate) fn read_tag(bytes: &[u8], position: usize) -> Result<TermTag, Error> {
+    bytes.get(position)
+        .copied()
+        .ok_or_else(|| Error::Io(io::ErrorKind::UnexpectedEof.into()))?
+        .try_into()
+        .map_err(|_| Error::Message("Unsupported term type".to_string()))
+}
+
+fn advance(bytes: &[u8], position: usize, amount: usize) -> Result<usize, Error> {
+    match position.checked_add(amount) {
+        Some(end) if end <= bytes.len() => Ok(end),
+        _ => Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+fn be_u16(bytes: &[u8]) -> usize {
+    u16::from_be_bytes([bytes[0], bytes[1]]) as usize
+}
+
+fn be_u32(bytes: &[u8]) -> usize {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+}
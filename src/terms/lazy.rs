@@ -0,0 +1,362 @@
+//! Navigating encoded terms without decoding them.
+//!
+//! A [`LazyTerm`] is a view on the bytes of a single encoded term.
+//! Tuples can be indexed, maps can be searched and lists can be iterated,
+//! each of which only skips over the sub-terms that are not of interest using
+//! the size information in their tags.
+//! Only the leaves that are actually used need to be decoded, with
+//! [`LazyTerm::decode`].
+//!
+//! ```
+//! use rust_eterm::terms::borrowed::BorrowedTerm;
+//! use rust_eterm::terms::lazy::LazyTerm;
+//!
+//! // {reply, #{status => 200}, <<"body">>}
+//! let bytes = [
+//!     104, 3,
+//!     119, 5, b'r', b'e', b'p', b'l', b'y',
+//!     116, 0, 0, 0, 1, 119, 6, b's', b't', b'a', b't', b'u', b's', 97, 200,
+//!     109, 0, 0, 0, 4, b'b', b'o', b'd', b'y',
+//! ];
+//!
+//! let term = LazyTerm::new(&bytes).unwrap();
+//! let status = term.element(2).unwrap()
+//!     .get(&BorrowedTerm::Atom("status".into())).unwrap()
+//!     .unwrap();
+//!
+//! assert_eq!(BorrowedTerm::Integer(200), status.decode().unwrap());
+//! ```
+//!
+//! [`LazyTerm`]: struct.LazyTerm.html
+//! [`LazyTerm::decode`]: struct.LazyTerm.html#method.decode
+
+use super::TermTag;
+use super::borrowed::{ self, BorrowedTerm };
+use super::layout::{ header_size, layout, read_tag, term_size, Layout };
+use super::order::compare_keys;
+use super::super::error::Error;
+
+use std::cmp::Ordering;
+
+/// A view on the bytes of a single encoded term.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LazyTerm<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> LazyTerm<'a> {
+    /// Delimits the (unversioned) term at the start of `bytes`.
+    ///
+    /// Any bytes after that term are ignored.
+    /// Delimiting walks the tags of all sub-terms once, which guarantees that
+    /// navigating the resulting view never runs out of bounds.
+    pub fn new(bytes: &'a [u8]) -> Result<LazyTerm<'a>, Error> {
+        let size = term_size(bytes)?;
+
+        Ok(LazyTerm { bytes: &bytes[..size] })
+    }
+
+    /// Creates a view on a sub-term of an already delimited term.
+    fn delimited(bytes: &'a [u8]) -> LazyTerm<'a> {
+        LazyTerm::new(bytes).expect("Sub-terms are delimited along with their parent")
+    }
+
+    /// The encoded bytes of this term.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The tag this term is encoded with.
+    pub fn tag(&self) -> TermTag {
+        read_tag(self.bytes, 0).expect("The tag was read on construction")
+    }
+
+    /// Fully decodes this term.
+    pub fn decode(&self) -> Result<BorrowedTerm<'a>, Error> {
+        borrowed::decode(&mut &self.bytes[..])
+    }
+
+    /// The arity of this tuple.
+    pub fn arity(&self) -> Result<usize, Error> {
+        match self.tag() {
+            TermTag::SmallTuple | TermTag::LargeTuple => Ok(self.layout().terms),
+            _ => Err(self.mismatch("a tuple")),
+        }
+    }
+
+    /// Returns the `index`th element of this tuple, counting from 1 just like
+    /// `erlang:element/2`.
+    pub fn element(&self, index: usize) -> Result<LazyTerm<'a>, Error> {
+        let arity = self.arity()?;
+
+        if index == 0 || index > arity {
+            return Err(Error::Message(format!("Index {} is out of range for a tuple of arity {}", index, arity)));
+        }
+
+        Ok(self.children().nth(index - 1).expect("Index is within the arity"))
+    }
+
+    /// Iterates over the elements of this tuple.
+    pub fn elements(&self) -> Result<Children<'a>, Error> {
+        self.arity()?;
+
+        Ok(self.children())
+    }
+
+    /// The amount of pairs in this map.
+    pub fn map_size(&self) -> Result<usize, Error> {
+        match self.tag() {
+            TermTag::Map => Ok(self.layout().terms / 2),
+            _ => Err(self.mismatch("a map")),
+        }
+    }
+
+    /// Iterates over the key-value pairs of this map in encoded order.
+    pub fn pairs(&self) -> Result<Pairs<'a>, Error> {
+        self.map_size()?;
+
+        Ok(Pairs { children: self.children() })
+    }
+
+    /// Looks up the value associated with `key` in this map.
+    ///
+    /// Only the keys are decoded.
+    /// Keys are compared after decoding, like `=:=`, so a key matches
+    /// regardless of which of the equivalent tags it is encoded with:
+    /// a string matches the same list of integers, and an integer matches
+    /// the same integer encoded as a big number.
+    pub fn get(&self, key: &BorrowedTerm) -> Result<Option<LazyTerm<'a>>, Error> {
+        for (k, v) in self.pairs()? {
+            if compare_keys(&k.decode()?, key) == Ordering::Equal {
+                return Ok(Some(v));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Iterates over the elements of this proper or improper list.
+    ///
+    /// Lists encoded as `STRING_EXT` have no encoded elements to navigate;
+    /// those must be decoded as a whole using [`decode`] instead.
+    ///
+    /// [`decode`]: #method.decode
+    pub fn list(&self) -> Result<LazyList<'a>, Error> {
+        match self.tag() {
+            TermTag::Nil => Ok(LazyList {
+                elements: Children { rest: &[], remaining: 0 },
+                tail: *self,
+            }),
+            TermTag::List => {
+                let mut elements = self.children();
+                // The tail is encoded as the last of the nested terms.
+                elements.remaining -= 1;
+                let tail = self.children().nth(elements.remaining).expect("Lists are followed by a tail");
+
+                Ok(LazyList { elements, tail })
+            },
+            TermTag::String => Err(Error::Message("Lists encoded as STRING_EXT must be decoded".to_string())),
+            _ => Err(self.mismatch("a list")),
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        let header = &self.bytes[1..1 + header_size(self.tag())];
+
+        layout(self.tag(), header).expect("The layout was computed on construction")
+    }
+
+    fn children(&self) -> Children<'a> {
+        let layout = self.layout();
+        let start = 1 + header_size(self.tag()) + layout.leading;
+
+        Children {
+            rest: &self.bytes[start..],
+            remaining: layout.terms,
+        }
+    }
+
+    fn mismatch(&self, expected: &str) -> Error {
+        Error::Message(format!("Expected {}, but found {:?}", expected, self.tag()))
+    }
+}
+
+/// An iterator over consecutive encoded terms.
+#[derive(Clone, Debug)]
+pub struct Children<'a> {
+    rest: &'a [u8],
+    remaining: usize,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = LazyTerm<'a>;
+
+    fn next(&mut self) -> Option<LazyTerm<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let term = LazyTerm::delimited(self.rest);
+        self.rest = &self.rest[term.bytes.len()..];
+        self.remaining -= 1;
+
+        Some(term)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Children<'a> {}
+
+/// An iterator over the key-value pairs of an encoded map.
+#[derive(Clone, Debug)]
+pub struct Pairs<'a> {
+    children: Children<'a>,
+}
+
+impl<'a> Iterator for Pairs<'a> {
+    type Item = (LazyTerm<'a>, LazyTerm<'a>);
+
+    fn next(&mut self) -> Option<(LazyTerm<'a>, LazyTerm<'a>)> {
+        Some((self.children.next()?, self.children.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.children.remaining / 2, Some(self.children.remaining / 2))
+    }
+}
+
+impl<'a> ExactSizeIterator for Pairs<'a> {}
+
+/// An iterator over the elements of an encoded list, which also gives access
+/// to the tail of the list.
+#[derive(Clone, Debug)]
+pub struct LazyList<'a> {
+    elements: Children<'a>,
+    tail: LazyTerm<'a>,
+}
+
+impl<'a> LazyList<'a> {
+    /// The tail of the list, which is `[]` for proper lists.
+    pub fn tail(&self) -> LazyTerm<'a> {
+        self.tail
+    }
+
+    /// Whether the tail of this list is `[]`.
+    pub fn is_proper(&self) -> bool {
+        self.tail.tag() == TermTag::Nil
+    }
+}
+
+impl<'a> Iterator for LazyList<'a> {
+    type Item = LazyTerm<'a>;
+
+    fn next(&mut self) -> Option<LazyTerm<'a>> {
+        self.elements.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for LazyList<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // {ok, [1, {a, b} | c], #{1 => x, <<"k">> => [2]}, "str"}
+    const SAMPLE: &[u8] = &[
+        104, 4,
+        119, 2, b'o', b'k',
+        108, 0, 0, 0, 2,
+            97, 1,
+            104, 2, 119, 1, b'a', 119, 1, b'b',
+            119, 1, b'c',
+        116, 0, 0, 0, 2,
+            97, 1, 119, 1, b'x',
+            109, 0, 0, 0, 1, b'k', 108, 0, 0, 0, 1, 97, 2, 106,
+        107, 0, 3, b's', b't', b'r',
+    ];
+
+    #[test]
+    fn delimits_first_term() {
+        let mut bytes = SAMPLE.to_vec();
+        bytes.extend_from_slice(&[97, 1]);
+
+        assert_eq!(SAMPLE, LazyTerm::new(&bytes).unwrap().as_bytes());
+        assert!(LazyTerm::new(&SAMPLE[..SAMPLE.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn tuple_elements() {
+        let term = LazyTerm::new(SAMPLE).unwrap();
+
+        assert_eq!(4, term.arity().unwrap());
+        assert_eq!(BorrowedTerm::Atom("ok".into()), term.element(1).unwrap().decode().unwrap());
+        assert_eq!(BorrowedTerm::String(b"str"), term.element(4).unwrap().decode().unwrap());
+        assert!(term.element(0).is_err());
+        assert!(term.element(5).is_err());
+        assert_eq!(4, term.elements().unwrap().count());
+        assert!(term.element(1).unwrap().element(1).is_err());
+    }
+
+    #[test]
+    fn list_elements() {
+        let list = LazyTerm::new(SAMPLE).unwrap().element(2).unwrap().list().unwrap();
+
+        assert!(!list.is_proper());
+        assert_eq!(BorrowedTerm::Atom("c".into()), list.tail().decode().unwrap());
+
+        let elements: Vec<LazyTerm> = list.collect();
+        assert_eq!(2, elements.len());
+        assert_eq!(BorrowedTerm::Integer(1), elements[0].decode().unwrap());
+        assert_eq!(BorrowedTerm::Atom("b".into()), elements[1].element(2).unwrap().decode().unwrap());
+
+        let empty = LazyTerm::new(&[106]).unwrap().list().unwrap();
+        assert!(empty.is_proper());
+        assert_eq!(0, empty.count());
+
+        assert!(LazyTerm::new(&[107, 0, 1, 1]).unwrap().list().is_err());
+    }
+
+    #[test]
+    fn map_lookup() {
+        let map = LazyTerm::new(SAMPLE).unwrap().element(3).unwrap();
+
+        assert_eq!(2, map.map_size().unwrap());
+        assert_eq!(
+            BorrowedTerm::Atom("x".into()),
+            map.get(&BorrowedTerm::Integer(1)).unwrap().unwrap().decode().unwrap(),
+        );
+
+        let value = map.get(&BorrowedTerm::Binary(b"k")).unwrap().unwrap();
+        assert_eq!(1, value.list().unwrap().count());
+
+        assert!(map.get(&BorrowedTerm::Integer(2)).unwrap().is_none());
+    }
+
+    #[test]
+    fn map_lookup_with_equivalent_keys() {
+        // #{"ab" => x, 300 => y}, with 300 as a zero-padded SMALL_BIG_EXT
+        let bytes = [
+            116, 0, 0, 0, 2,
+            107, 0, 2, b'a', b'b', 119, 1, b'x',
+            110, 3, 0, 44, 1, 0, 119, 1, b'y',
+        ];
+        let map = LazyTerm::new(&bytes).unwrap();
+
+        let ab = BorrowedTerm::List(vec![BorrowedTerm::Integer(97), BorrowedTerm::Integer(98)], Box::new(BorrowedTerm::Nil));
+        assert_eq!(BorrowedTerm::Atom("x".into()), map.get(&ab).unwrap().unwrap().decode().unwrap());
+
+        assert_eq!(BorrowedTerm::Atom("y".into()), map.get(&BorrowedTerm::Integer(300)).unwrap().unwrap().decode().unwrap());
+        assert_eq!(
+            BorrowedTerm::Atom("y".into()),
+            map.get(&BorrowedTerm::BigInteger { negative: false, digits: &[44, 1] }).unwrap().unwrap().decode().unwrap(),
+        );
+        assert!(map.get(&BorrowedTerm::Float(300.0)).unwrap().is_none());
+    }
+}
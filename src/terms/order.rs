@@ -0,0 +1,824 @@
+//! Erlang's term order, equality and hashing.
+//!
+//! Terms of different types are ordered as
+//! `number < atom < reference < fun < port < pid < tuple < map < nil < list < bitstring`.
+//! Numbers are compared by value, so `1 < 1.5` and `1 == 1.0`.
+//! Tuples are ordered by arity first, maps by size first and then by their
+//! sorted keys and the corresponding values; lists and bitstrings are
+//! compared element by element.
+//!
+//! The keys of maps are ordered slightly differently: every integer is
+//! smaller than every float, so `1` and `1.0` are distinct keys.
+//!
+//! [`compare`] and [`equal`] implement Erlang's `<` and `==` operators, and
+//! [`exactly_equal`] implements `=:=`.
+//! Terms also implement `Eq`, `Ord` and `Hash`, for boxed `dyn ETerm` values
+//! as well as the term structs.
+//! Those follow `=:=`: `1` and `1.0` are different, and `1` is ordered
+//! before `1.0`.
+//! Terms that cannot be encoded, such as NaN floats, are ordered after all
+//! other terms, by their text.
+//!
+//! ```
+//! use rust_eterm::terms::ETerm;
+//! use rust_eterm::terms::order::{ compare, equal, exactly_equal };
+//! use std::cmp::Ordering;
+//!
+//! assert_eq!(Ordering::Less, compare(&1u8, &1.5f64).unwrap());
+//! assert!(equal(&1u8, &1.0f64).unwrap());
+//! assert!(!exactly_equal(&1u8, &1.0f64).unwrap());
+//!
+//! let mut terms: Vec<Box<dyn ETerm>> = vec![Box::new(2.5f64), Box::new(1u8), Box::new(1.0f64)];
+//! terms.sort();
+//! assert!(exactly_equal(&*terms[0], &1u8).unwrap());
+//! assert!(exactly_equal(&*terms[1], &1.0f64).unwrap());
+//! assert!(exactly_equal(&*terms[2], &2.5f64).unwrap());
+//! ```
+//!
+//! [`compare`]: fn.compare.html
+//! [`equal`]: fn.equal.html
+//! [`exactly_equal`]: fn.exactly_equal.html
+
+use super::{
+    EAtom,
+    EBinary,
+    EExport,
+    EList,
+    EMap,
+    ENil,
+    ENonProperList,
+    EPid,
+    EPort,
+    EString,
+    ETerm,
+    ETuple,
+};
+use super::borrowed::{ self, BorrowedTerm, MAX_DEPTH };
+use super::encode::EncodeOptions;
+use super::super::error::Error;
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::hash::{ Hash, Hasher };
+
+/// Compares two terms like Erlang's comparison operators do.
+///
+/// Integers and floats of equal value compare equal.
+pub fn compare(a: &dyn ETerm, b: &dyn ETerm) -> Result<Ordering, Error> {
+    compare_with(a, b, Numbers::Arithmetic)
+}
+
+/// Whether two terms are equal like Erlang's `==`, which considers `1` and
+/// `1.0` equal.
+pub fn equal(a: &dyn ETerm, b: &dyn ETerm) -> Result<bool, Error> {
+    Ok(compare(a, b)? == Ordering::Equal)
+}
+
+/// Whether two terms are exactly equal like Erlang's `=:=`, which considers
+/// `1` and `1.0` different.
+pub fn exactly_equal(a: &dyn ETerm, b: &dyn ETerm) -> Result<bool, Error> {
+    Ok(compare_with(a, b, Numbers::Exact)? == Ordering::Equal)
+}
+
+fn compare_with(a: &dyn ETerm, b: &dyn ETerm, numbers: Numbers) -> Result<Ordering, Error> {
+    with_borrowed(a, |a| with_borrowed(b, |b| compare_terms(a, b, numbers)))?
+}
+
+/// Compares two terms in the order of the keys of a map, where every integer
+/// is smaller than every float, with terms that cannot be compared last.
+pub(crate) fn compare_map_keys(a: &dyn ETerm, b: &dyn ETerm) -> Ordering {
+    compare_with(a, b, Numbers::MapKeys).unwrap_or_else(|_| a.cmp(b))
+}
+
+/// Calls `f` with `term` as a `BorrowedTerm`.
+///
+/// The term types of this crate are converted directly, and other terms by
+/// encoding and decoding them.
+pub(crate) fn with_borrowed<R>(term: &dyn ETerm, f: impl FnOnce(&BorrowedTerm) -> R) -> Result<R, Error> {
+    if let Some(term) = lower(term, 0) {
+        return Ok(f(&term));
+    }
+
+    let bytes = term.to_external_binary()?;
+    Ok(f(&borrowed::decode(&mut bytes.as_slice())?))
+}
+
+/// Converts a term into the `BorrowedTerm` that decoding its encoding would
+/// return, or returns `None` if it contains terms that are not converted
+/// directly, such as big integers, or terms that cannot be encoded.
+fn lower(term: &dyn ETerm, depth: usize) -> Option<BorrowedTerm<'_>> {
+    let any = term.as_any();
+
+    macro_rules! integers {
+        ($($type:ty),*) => {
+            $(
+                if let Some(i) = any.downcast_ref::<$type>() {
+                    return i32::try_from(*i).ok().map(BorrowedTerm::Integer);
+                }
+            )*
+        };
+    }
+
+    integers!(u8, i8, u16, i16, i32, u32, i64, u64, i128, u128, isize, usize);
+
+    if let Some(f) = any.downcast_ref::<f64>() {
+        f.is_finite().then_some(BorrowedTerm::Float(*f))
+    } else if let Some(f) = any.downcast_ref::<f32>() {
+        f.is_finite().then_some(BorrowedTerm::Float(*f as f64))
+    } else if let Some(atom) = any.downcast_ref::<EAtom>() {
+        lower_atom(&atom.0)
+    } else if let Some(b) = any.downcast_ref::<bool>() {
+        lower_atom(if *b { "true" } else { "false" })
+    } else if any.is::<ENil>() {
+        Some(BorrowedTerm::Nil)
+    } else if let Some(string) = any.downcast_ref::<EString>() {
+        Some(BorrowedTerm::String(string.0.as_bytes()))
+    } else if let Some(binary) = any.downcast_ref::<EBinary>() {
+        Some(BorrowedTerm::Binary(&binary.0))
+    } else if let Some(tuple) = any.downcast_ref::<ETuple>() {
+        Some(BorrowedTerm::Tuple(lower_all(&tuple.0, depth)?))
+    } else if let Some(list) = any.downcast_ref::<EList>() {
+        match list.0.is_empty() {
+            true => Some(BorrowedTerm::Nil),
+            false => Some(BorrowedTerm::List(lower_all(&list.0, depth)?, Box::new(BorrowedTerm::Nil))),
+        }
+    } else if let Some(list) = any.downcast_ref::<ENonProperList>() {
+        let tail = lower_all(std::slice::from_ref(&list.tail), depth)?.pop()?;
+        Some(BorrowedTerm::List(lower_all(&list.data, depth)?, Box::new(tail)))
+    } else if let Some(map) = any.downcast_ref::<EMap>() {
+        if depth == MAX_DEPTH {
+            return None;
+        }
+
+        map.0.iter()
+            .map(|(k, v)| Some((lower(&**k, depth + 1)?, lower(&**v, depth + 1)?)))
+            .collect::<Option<_>>()
+            .map(BorrowedTerm::Map)
+    } else if let Some(export) = any.downcast_ref::<EExport>() {
+        Some(BorrowedTerm::Export {
+            module: lower_atom_name(&export.module.0)?,
+            function: lower_atom_name(&export.function.0)?,
+            arity: export.arity,
+        })
+    } else if let Some(pid) = any.downcast_ref::<EPid>() {
+        Some(BorrowedTerm::Pid { node: lower_atom_name(&pid.node.0)?, id: pid.id, serial: pid.serial, creation: pid.creation })
+    } else if let Some(port) = any.downcast_ref::<EPort>() {
+        Some(BorrowedTerm::Port { node: lower_atom_name(&port.node.0)?, id: port.id, creation: port.creation })
+    } else {
+        None
+    }
+}
+
+/// Converts the elements of a term at `depth`.
+fn lower_all(terms: &[Box<dyn ETerm>], depth: usize) -> Option<Vec<BorrowedTerm<'_>>> {
+    if depth == MAX_DEPTH {
+        return None;
+    }
+
+    terms.iter().map(|term| lower(&**term, depth + 1)).collect()
+}
+
+fn lower_atom(name: &str) -> Option<BorrowedTerm<'_>> {
+    lower_atom_name(name).map(BorrowedTerm::Atom)
+}
+
+/// Longer atoms are left to the encoder, which decides whether they fit.
+fn lower_atom_name(name: &str) -> Option<Cow<'_, str>> {
+    (name.len() <= u16::MAX.into()).then_some(Cow::Borrowed(name))
+}
+
+impl PartialEq for dyn ETerm {
+    fn eq(&self, other: &dyn ETerm) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for dyn ETerm {}
+
+impl PartialOrd for dyn ETerm {
+    fn partial_cmp(&self, other: &dyn ETerm) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for dyn ETerm {
+    fn cmp(&self, other: &dyn ETerm) -> Ordering {
+        // Terms that cannot be compared, such as NaN floats, come after all
+        // others, in the order of their text.
+        match (compare_with(self, other, Numbers::Exact), comparable(self), comparable(other)) {
+            (Ok(order), _, _) => order,
+            (Err(_), true, false) => Ordering::Less,
+            (Err(_), false, true) => Ordering::Greater,
+            (Err(_), _, _) => self.to_string().cmp(&other.to_string()),
+        }
+    }
+}
+
+impl Hash for dyn ETerm {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Exactly equal terms have identical deterministic encodings.
+        let encoded = match comparable(self) {
+            true => self.to_external_binary_with(&EncodeOptions::default().with_deterministic(true)).ok(),
+            false => None,
+        };
+
+        match encoded {
+            Some(bytes) => bytes.hash(state),
+            None => self.to_string().hash(state),
+        }
+    }
+}
+
+/// Whether a term can be compared by [`compare_terms`], which requires that
+/// it can be encoded and decoded.
+fn comparable(term: &dyn ETerm) -> bool {
+    with_borrowed(term, |_| ()).is_ok()
+}
+
+macro_rules! impl_term_order {
+    ($($name:ident),*) => {
+        $(
+            impl PartialEq for $name {
+                fn eq(&self, other: &$name) -> bool {
+                    (self as &dyn ETerm) == (other as &dyn ETerm)
+                }
+            }
+
+            impl Eq for $name {}
+
+            impl PartialOrd for $name {
+                fn partial_cmp(&self, other: &$name) -> Option<Ordering> {
+                    Some(self.cmp(other))
+                }
+            }
+
+            impl Ord for $name {
+                fn cmp(&self, other: &$name) -> Ordering {
+                    (self as &dyn ETerm).cmp(other as &dyn ETerm)
+                }
+            }
+
+            impl Hash for $name {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    (self as &dyn ETerm).hash(state)
+                }
+            }
+        )*
+    };
+}
+
+// ENil, EAtom, EString and EBinary derive these, which orders them the same.
+impl_term_order!(EList, ENonProperList, EExport, ETuple, EPort, EPid, EMap);
+
+/// How numbers of different types are compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Numbers {
+    /// By value, so `1 == 1.0`.
+    Arithmetic,
+    /// By value, but with integers before floats of equal value.
+    Exact,
+    /// Every integer before every float, like the keys of maps.
+    MapKeys,
+}
+
+/// Compares two terms in the order in which `term_to_binary(T, [deterministic])`
+/// writes the keys of a map.
+pub(crate) fn compare_keys(a: &BorrowedTerm, b: &BorrowedTerm) -> Ordering {
+    compare_terms(a, b, Numbers::MapKeys)
+}
+
+/// Whether two terms are exactly equal like Erlang's `=:=`.
+pub(crate) fn exactly_equal_terms(a: &BorrowedTerm, b: &BorrowedTerm) -> bool {
+    compare_terms(a, b, Numbers::Exact) == Ordering::Equal
+}
+
+fn compare_terms(a: &BorrowedTerm, b: &BorrowedTerm, numbers: Numbers) -> Ordering {
+    let kinds = kind(a).cmp(&kind(b));
+
+    if kinds != Ordering::Equal {
+        return kinds;
+    }
+
+    match (a, b) {
+        (BorrowedTerm::Atom(a), BorrowedTerm::Atom(b)) => a.cmp(b),
+        (
+            BorrowedTerm::Export { module: m1, function: f1, arity: a1 },
+            BorrowedTerm::Export { module: m2, function: f2, arity: a2 },
+        ) => (m1, f1, a1).cmp(&(m2, f2, a2)),
+        (
+            BorrowedTerm::Port { node: n1, id: i1, creation: c1 },
+            BorrowedTerm::Port { node: n2, id: i2, creation: c2 },
+        ) => (n1, c1, i1).cmp(&(n2, c2, i2)),
+        (
+            BorrowedTerm::Pid { node: n1, id: i1, serial: s1, creation: c1 },
+            BorrowedTerm::Pid { node: n2, id: i2, serial: s2, creation: c2 },
+        ) => (n1, c1, s1, i1).cmp(&(n2, c2, s2, i2)),
+        (BorrowedTerm::Tuple(a), BorrowedTerm::Tuple(b)) => a.len().cmp(&b.len())
+            .then_with(|| compare_all(a.iter(), b.iter(), numbers)),
+        (BorrowedTerm::Map(a), BorrowedTerm::Map(b)) => a.len().cmp(&b.len())
+            .then_with(|| compare_maps(a, b, numbers)),
+        (BorrowedTerm::Binary(a), BorrowedTerm::Binary(b)) => a.cmp(b),
+        _ => match kind(a) {
+            Kind::Number => compare_numbers(a, b, numbers),
+            Kind::Nil => Ordering::Equal,
+            Kind::List => compare_lists(ListView::of(a), ListView::of(b), numbers),
+            _ => unreachable!("Terms of the same kind are compared above"),
+        },
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Number,
+    Atom,
+    Fun,
+    Port,
+    Pid,
+    Tuple,
+    Map,
+    Nil,
+    List,
+    Bitstring,
+}
+
+fn kind(term: &BorrowedTerm) -> Kind {
+    match term {
+        BorrowedTerm::Integer(_) | BorrowedTerm::BigInteger { .. } | BorrowedTerm::Float(_) => Kind::Number,
+        BorrowedTerm::Atom(_) => Kind::Atom,
+        BorrowedTerm::Export { .. } => Kind::Fun,
+        BorrowedTerm::Port { .. } => Kind::Port,
+        BorrowedTerm::Pid { .. } => Kind::Pid,
+        BorrowedTerm::Tuple(_) => Kind::Tuple,
+        BorrowedTerm::Map(_) => Kind::Map,
+        BorrowedTerm::Nil => Kind::Nil,
+        BorrowedTerm::String([]) => Kind::Nil,
+        BorrowedTerm::String(_) | BorrowedTerm::List(_, _) => Kind::List,
+        BorrowedTerm::Binary(_) => Kind::Bitstring,
+    }
+}
+
+fn compare_all<'t, 'a: 't, I, J>(a: I, b: J, numbers: Numbers) -> Ordering
+    where I: Iterator<Item = &'t BorrowedTerm<'a>>,
+          J: Iterator<Item = &'t BorrowedTerm<'a>>
+{
+    a.zip(b)
+        .map(|(a, b)| compare_terms(a, b, numbers))
+        .find(|o| *o != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Compares two maps of the same size: first all keys in order, then the
+/// values in the order of their keys.
+///
+/// Keys are always compared as map keys, so `#{1 => a} /= #{1.0 => a}`.
+fn compare_maps(a: &[(BorrowedTerm, BorrowedTerm)], b: &[(BorrowedTerm, BorrowedTerm)], numbers: Numbers) -> Ordering {
+    let (a, b) = (sorted_by_key(a), sorted_by_key(b));
+
+    compare_all(a.iter().map(|(k, _)| k), b.iter().map(|(k, _)| k), Numbers::MapKeys)
+        .then_with(|| compare_all(a.iter().map(|(_, v)| v), b.iter().map(|(_, v)| v), numbers))
+}
+
+fn sorted_by_key<'t, 'a>(pairs: &'t [(BorrowedTerm<'a>, BorrowedTerm<'a>)]) -> Vec<&'t (BorrowedTerm<'a>, BorrowedTerm<'a>)> {
+    let mut result: Vec<_> = pairs.iter().collect();
+    result.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+
+    result
+}
+
+fn compare_numbers(a: &BorrowedTerm, b: &BorrowedTerm, numbers: Numbers) -> Ordering {
+    match (a, b, numbers) {
+        (BorrowedTerm::Float(a), BorrowedTerm::Float(b), Numbers::Arithmetic) =>
+            a.partial_cmp(b).expect("Decoded floats are finite"),
+        (BorrowedTerm::Float(a), BorrowedTerm::Float(b), _) => a.total_cmp(b),
+        (BorrowedTerm::Float(_), _, Numbers::MapKeys) => Ordering::Greater,
+        (_, BorrowedTerm::Float(_), Numbers::MapKeys) => Ordering::Less,
+        (BorrowedTerm::Float(a), _, _) => compare_integer_to_float(b, *a, numbers).reverse(),
+        (_, BorrowedTerm::Float(b), _) => compare_integer_to_float(a, *b, numbers),
+        _ => compare_integers(a, b),
+    }
+}
+
+fn compare_integers(a: &BorrowedTerm, b: &BorrowedTerm) -> Ordering {
+    let (a_negative, a) = integer_parts(a);
+    let (b_negative, b) = integer_parts(b);
+
+    compare_signed(a_negative, b_negative, compare_magnitudes(&a, &b))
+}
+
+/// Compares an integer to a float by value, without losing precision.
+fn compare_integer_to_float(integer: &BorrowedTerm, float: f64, numbers: Numbers) -> Ordering {
+    let (negative, magnitude) = integer_parts(integer);
+
+    let integer_sign = if magnitude.is_empty() { 0 } else if negative { -1 } else { 1 };
+    let float_sign = if float == 0.0 { 0 } else if float < 0.0 { -1 } else { 1 };
+
+    let order = if integer_sign != float_sign || integer_sign == 0 {
+        integer_sign.cmp(&float_sign)
+    } else {
+        let whole = float.abs().trunc();
+        let fraction = if float.abs() > whole { Ordering::Less } else { Ordering::Equal };
+
+        compare_signed(negative, negative, compare_magnitudes(&magnitude, &float_magnitude(whole)).then(fraction))
+    };
+
+    match order {
+        Ordering::Equal if numbers == Numbers::Exact => Ordering::Less,
+        order => order,
+    }
+}
+
+/// Orders two integers given their signs and the order of their magnitudes.
+fn compare_signed(a_negative: bool, b_negative: bool, magnitudes: Ordering) -> Ordering {
+    match (a_negative, b_negative) {
+        (false, false) => magnitudes,
+        (true, true) => magnitudes.reverse(),
+        (false, true) => Ordering::Greater,
+        (true, false) => Ordering::Less,
+    }
+}
+
+/// Compares two magnitudes without trailing zeroes.
+fn compare_magnitudes(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+/// The magnitude of a non-negative, whole float, stored least-significant
+/// byte first without trailing zeroes.
+fn float_magnitude(whole: f64) -> Vec<u8> {
+    if whole == 0.0 {
+        return Vec::new();
+    }
+
+    let bits = whole.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1075;
+    let mantissa = bits & ((1 << 52) - 1) | if bits >> 52 == 0 { 0 } else { 1 << 52 };
+
+    let mut result = if exponent <= 0 {
+        // Whole floats with a negative exponent fit in the mantissa.
+        (mantissa >> -exponent).to_le_bytes().to_vec()
+    } else {
+        let mut result = vec![0; exponent as usize / 8];
+        result.extend_from_slice(&((mantissa as u128) << (exponent % 8)).to_le_bytes());
+        result
+    };
+
+    let length = result.iter().rposition(|d| *d != 0).map_or(0, |i| i + 1);
+    result.truncate(length);
+
+    result
+}
+
+/// Splits an integer into its sign and its magnitude, stored
+/// least-significant byte first without trailing zeroes.
+fn integer_parts<'t>(term: &'t BorrowedTerm) -> (bool, Cow<'t, [u8]>) {
+    let (negative, mut magnitude) = match term {
+        BorrowedTerm::Integer(i) => (*i < 0, Cow::Owned(i.unsigned_abs().to_le_bytes().to_vec())),
+        BorrowedTerm::BigInteger { negative, digits } => (*negative, Cow::Borrowed(*digits)),
+        _ => unreachable!("Only integers are compared as integers"),
+    };
+
+    let length = magnitude.iter().rposition(|d| *d != 0).map_or(0, |i| i + 1);
+
+    match magnitude {
+        Cow::Borrowed(ref mut digits) => *digits = &digits[..length],
+        Cow::Owned(ref mut digits) => digits.truncate(length),
+    }
+
+    (negative && length > 0, magnitude)
+}
+
+/// A (possibly improper) list, or the part of one that remains after
+/// skipping some elements.
+#[derive(Clone, Copy)]
+struct ListView<'t, 'a> {
+    elements: Elements<'t, 'a>,
+    tail: Option<&'t BorrowedTerm<'a>>,
+}
+
+#[derive(Clone, Copy)]
+enum Elements<'t, 'a> {
+    Bytes(&'a [u8]),
+    Terms(&'t [BorrowedTerm<'a>]),
+}
+
+impl<'t, 'a> ListView<'t, 'a> {
+    fn of(term: &'t BorrowedTerm<'a>) -> ListView<'t, 'a> {
+        match term {
+            BorrowedTerm::String(bytes) => ListView { elements: Elements::Bytes(bytes), tail: None },
+            BorrowedTerm::List(elements, tail) => ListView { elements: Elements::Terms(elements), tail: Some(tail) },
+            _ => unreachable!("Only lists are viewed as lists"),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.elements {
+            Elements::Bytes(bytes) => bytes.len(),
+            Elements::Terms(terms) => terms.len(),
+        }
+    }
+
+    fn get(&self, index: usize) -> Cow<'t, BorrowedTerm<'a>> {
+        match self.elements {
+            Elements::Bytes(bytes) => Cow::Owned(BorrowedTerm::Integer(bytes[index].into())),
+            Elements::Terms(terms) => Cow::Borrowed(&terms[index]),
+        }
+    }
+
+    fn skip(&self, amount: usize) -> ListView<'t, 'a> {
+        let elements = match self.elements {
+            Elements::Bytes(bytes) => Elements::Bytes(&bytes[amount..]),
+            Elements::Terms(terms) => Elements::Terms(&terms[amount..]),
+        };
+
+        ListView { elements, tail: self.tail }
+    }
+}
+
+fn compare_lists(a: ListView, b: ListView, numbers: Numbers) -> Ordering {
+    let common = a.len().min(b.len());
+
+    for i in 0..common {
+        let order = compare_terms(&a.get(i), &b.get(i), numbers);
+
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+
+    let (a_rest, b_rest) = (a.skip(common), b.skip(common));
+
+    match (a_rest.len(), b_rest.len()) {
+        (0, 0) => match (a.tail, b.tail) {
+            (Some(a), Some(b)) => compare_terms(a, b, numbers),
+            (Some(a), None) => compare_terms(a, &BorrowedTerm::Nil, numbers),
+            (None, Some(b)) => compare_terms(&BorrowedTerm::Nil, b, numbers),
+            (None, None) => Ordering::Equal,
+        },
+        (0, _) => compare_tail_to_list(a.tail, b_rest, numbers),
+        _ => compare_tail_to_list(b.tail, a_rest, numbers).reverse(),
+    }
+}
+
+/// Compares the tail of a list that ran out of elements to the remaining
+/// elements of the other list.
+fn compare_tail_to_list(tail: Option<&BorrowedTerm>, rest: ListView, numbers: Numbers) -> Ordering {
+    match tail {
+        Some(tail) if kind(tail) == Kind::List => compare_lists(ListView::of(tail), rest, numbers),
+        Some(tail) => kind(tail).cmp(&Kind::List),
+        None => Ordering::Less,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(name: &str) -> BorrowedTerm<'_> {
+        BorrowedTerm::Atom(name.into())
+    }
+
+    fn list<'a>(elements: Vec<BorrowedTerm<'a>>, tail: BorrowedTerm<'a>) -> BorrowedTerm<'a> {
+        BorrowedTerm::List(elements, Box::new(tail))
+    }
+
+    #[test]
+    fn orders_by_type_first() {
+        let ascending = vec![
+            BorrowedTerm::Integer(i32::MAX),
+            BorrowedTerm::Float(-1.0),
+            atom("a"),
+            BorrowedTerm::Export { module: "m".into(), function: "f".into(), arity: 0 },
+            BorrowedTerm::Port { node: "a@b".into(), id: 1, creation: 0 },
+            BorrowedTerm::Pid { node: "a@b".into(), id: 1, serial: 0, creation: 0 },
+            BorrowedTerm::Tuple(vec![]),
+            BorrowedTerm::Map(vec![]),
+            BorrowedTerm::Nil,
+            BorrowedTerm::String(b"a"),
+            BorrowedTerm::Binary(b""),
+        ];
+
+        for (i, a) in ascending.iter().enumerate() {
+            for (j, b) in ascending.iter().enumerate() {
+                assert_eq!(i.cmp(&j), compare_keys(a, b), "{:?} <=> {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn integers() {
+        let big = |negative, digits| BorrowedTerm::BigInteger { negative, digits };
+
+        assert_eq!(Ordering::Less, compare_keys(&big(true, &[0, 0, 0, 0, 1]), &BorrowedTerm::Integer(i32::MIN)));
+        assert_eq!(Ordering::Less, compare_keys(&BorrowedTerm::Integer(-1), &BorrowedTerm::Integer(0)));
+        assert_eq!(Ordering::Less, compare_keys(&BorrowedTerm::Integer(i32::MAX), &big(false, &[0, 0, 0, 0, 1])));
+        assert_eq!(Ordering::Equal, compare_keys(&BorrowedTerm::Integer(256), &big(false, &[0, 1, 0])));
+        assert_eq!(Ordering::Equal, compare_keys(&BorrowedTerm::Integer(0), &big(true, &[0])));
+    }
+
+    #[test]
+    fn integers_before_floats() {
+        assert_eq!(Ordering::Less, compare_keys(&BorrowedTerm::Integer(2), &BorrowedTerm::Float(1.5)));
+        assert_eq!(Ordering::Less, compare_keys(&BorrowedTerm::Integer(1), &BorrowedTerm::Float(1.0)));
+        assert_eq!(Ordering::Less, compare_keys(&BorrowedTerm::Float(-0.5), &BorrowedTerm::Float(0.5)));
+    }
+
+    #[test]
+    fn atoms_and_tuples() {
+        assert_eq!(Ordering::Less, compare_keys(&atom("aa"), &atom("b")));
+        assert_eq!(
+            Ordering::Less,
+            compare_keys(&BorrowedTerm::Tuple(vec![atom("z")]), &BorrowedTerm::Tuple(vec![atom("a"), atom("a")])),
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let one_two = list(vec![BorrowedTerm::Integer(1), BorrowedTerm::Integer(2)], BorrowedTerm::Nil);
+
+        assert_eq!(Ordering::Equal, compare_keys(&one_two, &BorrowedTerm::String(&[1, 2])));
+        assert_eq!(Ordering::Less, compare_keys(&BorrowedTerm::String(&[1]), &one_two));
+        assert_eq!(Ordering::Greater, compare_keys(&BorrowedTerm::String(&[1, 3]), &one_two));
+
+        // [1 | 2] < [1, 2], as 2 < [2].
+        let improper = list(vec![BorrowedTerm::Integer(1)], BorrowedTerm::Integer(2));
+        assert_eq!(Ordering::Less, compare_keys(&improper, &one_two));
+        assert_eq!(Ordering::Greater, compare_keys(&one_two, &improper));
+
+        // [1 | [2]] is just [1, 2].
+        let nested = list(vec![BorrowedTerm::Integer(1)], BorrowedTerm::String(&[2]));
+        assert_eq!(Ordering::Equal, compare_keys(&nested, &one_two));
+    }
+
+    #[test]
+    fn maps() {
+        let map = |pairs: Vec<(BorrowedTerm<'static>, BorrowedTerm<'static>)>| BorrowedTerm::Map(pairs);
+
+        let a = map(vec![(atom("b"), BorrowedTerm::Integer(1)), (atom("a"), BorrowedTerm::Integer(2))]);
+        let b = map(vec![(atom("a"), BorrowedTerm::Integer(2)), (atom("b"), BorrowedTerm::Integer(1))]);
+        let c = map(vec![(atom("a"), BorrowedTerm::Integer(1)), (atom("c"), BorrowedTerm::Integer(1))]);
+        let d = map(vec![(atom("a"), BorrowedTerm::Integer(1))]);
+
+        assert_eq!(Ordering::Equal, compare_keys(&a, &b));
+        // Keys are compared before values.
+        assert_eq!(Ordering::Less, compare_keys(&a, &c));
+        assert_eq!(Ordering::Greater, compare_keys(&a, &d));
+    }
+
+    #[test]
+    fn integers_and_floats_by_value() {
+        let compare = |a: &BorrowedTerm, b: &BorrowedTerm| compare_terms(a, b, Numbers::Arithmetic);
+        let exact = |a: &BorrowedTerm, b: &BorrowedTerm| compare_terms(a, b, Numbers::Exact);
+
+        assert_eq!(Ordering::Less, compare(&BorrowedTerm::Integer(1), &BorrowedTerm::Float(1.5)));
+        assert_eq!(Ordering::Greater, compare(&BorrowedTerm::Integer(2), &BorrowedTerm::Float(1.5)));
+        assert_eq!(Ordering::Less, compare(&BorrowedTerm::Integer(-2), &BorrowedTerm::Float(-1.5)));
+        assert_eq!(Ordering::Greater, compare(&BorrowedTerm::Integer(-1), &BorrowedTerm::Float(-1.5)));
+        assert_eq!(Ordering::Greater, compare(&BorrowedTerm::Integer(0), &BorrowedTerm::Float(-0.5)));
+        assert_eq!(Ordering::Greater, compare(&BorrowedTerm::Float(0.5), &BorrowedTerm::Integer(0)));
+        assert_eq!(Ordering::Equal, compare(&BorrowedTerm::Integer(1), &BorrowedTerm::Float(1.0)));
+        assert_eq!(Ordering::Equal, compare(&BorrowedTerm::Integer(0), &BorrowedTerm::Float(-0.0)));
+        assert_eq!(Ordering::Equal, compare(&BorrowedTerm::Float(0.0), &BorrowedTerm::Float(-0.0)));
+
+        // Integers come before floats of equal value when comparing exactly.
+        assert_eq!(Ordering::Less, exact(&BorrowedTerm::Integer(1), &BorrowedTerm::Float(1.0)));
+        assert_eq!(Ordering::Greater, exact(&BorrowedTerm::Float(1.0), &BorrowedTerm::Integer(1)));
+        assert_eq!(Ordering::Greater, exact(&BorrowedTerm::Integer(2), &BorrowedTerm::Float(1.0)));
+    }
+
+    #[test]
+    fn big_integers_and_floats() {
+        let compare = |a: &BorrowedTerm, b: &BorrowedTerm| compare_terms(a, b, Numbers::Arithmetic);
+
+        // 2^64 and 2^64 + 1, of which only the former is exactly a float.
+        let two_64 = BorrowedTerm::BigInteger { negative: false, digits: &[0, 0, 0, 0, 0, 0, 0, 0, 1] };
+        let two_64_plus_1 = BorrowedTerm::BigInteger { negative: false, digits: &[1, 0, 0, 0, 0, 0, 0, 0, 1] };
+        let float = BorrowedTerm::Float(18_446_744_073_709_551_616.0);
+
+        assert_eq!(Ordering::Equal, compare(&two_64, &float));
+        assert_eq!(Ordering::Greater, compare(&two_64_plus_1, &float));
+
+        let huge = BorrowedTerm::BigInteger { negative: true, digits: &[1; 200] };
+        assert_eq!(Ordering::Less, compare(&huge, &BorrowedTerm::Float(f64::MIN)));
+        assert_eq!(Ordering::Greater, compare(&BorrowedTerm::Float(1e300), &BorrowedTerm::Integer(i32::MAX)));
+    }
+
+    #[test]
+    fn map_keys_are_compared_exactly() {
+        let a = BorrowedTerm::Map(vec![(BorrowedTerm::Integer(1), atom("a"))]);
+        let b = BorrowedTerm::Map(vec![(BorrowedTerm::Float(1.0), atom("a"))]);
+        let c = BorrowedTerm::Map(vec![(BorrowedTerm::Integer(1), BorrowedTerm::Float(1.0))]);
+        let d = BorrowedTerm::Map(vec![(BorrowedTerm::Integer(1), BorrowedTerm::Integer(1))]);
+
+        assert_eq!(Ordering::Less, compare_terms(&a, &b, Numbers::Arithmetic));
+        // Values are compared like any other term.
+        assert_eq!(Ordering::Equal, compare_terms(&c, &d, Numbers::Arithmetic));
+    }
+
+    #[test]
+    fn boxed_terms() {
+        use super::super::{ EAtom, EString, ETuple };
+        use std::collections::HashSet;
+
+        let one: Box<dyn ETerm> = Box::new(1u8);
+        let also_one: Box<dyn ETerm> = Box::new(1i64);
+        let one_float: Box<dyn ETerm> = Box::new(1.0f64);
+
+        assert!(one == also_one);
+        assert!(one.ne(&one_float));
+        assert!(one.lt(&one_float));
+        assert!(equal(&*one, &*one_float).unwrap());
+        assert!(!exactly_equal(&*one, &*one_float).unwrap());
+
+        let mut terms: Vec<Box<dyn ETerm>> = vec![
+            Box::new(EString("ab".to_string())),
+            Box::new(EAtom("ok".to_string())),
+            Box::new(ETuple(vec![])),
+            Box::new(-3i32),
+            Box::new(EList(vec![Box::new(b'a'), Box::new(b'b')])),
+            Box::new(2.5f64),
+        ];
+        terms.sort();
+        assert_eq!(
+            vec!["-3", "2.5", "ok", "{}", "\"ab\"", "[97,98]"],
+            terms.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+        );
+
+        let unique: HashSet<Box<dyn ETerm>> = terms.into_iter().collect();
+        assert_eq!(5, unique.len());
+    }
+
+    #[test]
+    fn concrete_terms() {
+        use super::super::EAtom;
+        use std::collections::BTreeSet;
+
+        let map = |pairs: Vec<(&str, u8)>| EMap(pairs.into_iter()
+            .map(|(k, v)| (Box::new(EAtom(k.to_string())) as Box<dyn ETerm>, Box::new(v) as Box<dyn ETerm>))
+            .collect());
+
+        assert!(map(vec![("a", 1), ("b", 2)]) == map(vec![("b", 2), ("a", 1)]));
+        assert!(map(vec![("a", 1)]) < map(vec![("a", 2)]));
+        assert!(map(vec![("z", 1)]) < map(vec![("a", 1), ("b", 1)]));
+
+        let set: BTreeSet<EMap> = vec![map(vec![("a", 1)]), map(vec![("a", 1)]), map(vec![])].into_iter().collect();
+        assert_eq!(2, set.len());
+    }
+
+    #[test]
+    fn lowering_matches_decoding() {
+        let node = || EAtom("a@b".to_string());
+        let terms: Vec<Box<dyn ETerm>> = vec![
+            Box::new(ETuple(vec![Box::new(-1i8), Box::new(300u16), Box::new(1.5f32), Box::new(true)])),
+            Box::new(EList(vec![Box::new(EString("ab".to_string())), Box::new(EList(vec![])), Box::new(ENil)])),
+            Box::new(ENonProperList { data: vec![Box::new(1u8)], tail: Box::new(EBinary(vec![1, 2])) }),
+            Box::new(EMap(vec![(Box::new(EAtom("k".to_string())), Box::new(7u32))])),
+            Box::new(EExport { module: EAtom("m".to_string()), function: EAtom("f".to_string()), arity: 2 }),
+            Box::new(EPid { node: node(), id: 1, serial: 2, creation: 3 }),
+            Box::new(EPort { node: node(), id: 1, creation: 3 }),
+        ];
+
+        for term in terms {
+            let bytes = term.to_external_binary().unwrap();
+            let decoded = borrowed::decode(&mut bytes.as_slice()).unwrap();
+            let lowered = lower(&*term, 0);
+
+            assert!(lowered.as_ref().is_some_and(|lowered| exactly_equal_terms(lowered, &decoded)), "{}", term);
+        }
+
+        assert!(lower(&u32::MAX, 0).is_none());
+        assert!(lower(&f64::NAN, 0).is_none());
+    }
+
+    #[test]
+    fn unencodable_terms() {
+        use std::collections::{ BTreeSet, HashSet };
+
+        let long = EAtom("a".repeat(70_000));
+        let terms = || -> Vec<Box<dyn ETerm>> {
+            vec![
+                Box::new(f64::NAN),
+                Box::new(ETuple(vec![Box::new(f64::INFINITY)])),
+                Box::new(long.clone()),
+                Box::new(EAtom("ok".to_string())),
+                Box::new(1u8),
+                Box::new(f64::NAN),
+            ]
+        };
+
+        assert!(compare(&f64::NAN, &1u8).is_err());
+
+        let mut sorted = terms();
+        sorted.sort();
+        assert_eq!("1", sorted[0].to_string());
+        assert_eq!("ok", sorted[1].to_string());
+        assert_eq!(Ordering::Greater, (&*sorted[2] as &dyn ETerm).cmp(&*sorted[1]));
+        assert!(sorted.windows(2).all(|pair| pair[0].cmp(&pair[1]) != Ordering::Greater));
+
+        let nan: Box<dyn ETerm> = Box::new(f64::NAN);
+        assert!(nan == Box::new(f64::NAN) as Box<dyn ETerm>);
+        assert_eq!(5, terms().into_iter().collect::<BTreeSet<_>>().len());
+        assert_eq!(5, terms().into_iter().collect::<HashSet<_>>().len());
+    }
+}
@@ -0,0 +1,798 @@
+//! Parsing terms from Erlang syntax.
+//!
+//! [`parse`] reads a single term, like `erl_parse:parse_term/1`, and
+//! [`parse_all`] reads a sequence of terms that each end with a `.`, like
+//! `file:consult/1`:
+//!
+//! ```
+//! use rust_eterm::terms::parse::{ parse, parse_all };
+//!
+//! let term = parse("{reply, #{status => <<\"ok\">>}, [1, 2.5, 'Quoted atom']}").unwrap();
+//! assert_eq!("{reply,#{status=><<111,107>>},[1,2.5,'Quoted atom']}", term.to_string());
+//!
+//! let terms = parse_all("% Two terms\n{a, 1}.\n[$a, -16#ff].\n").unwrap();
+//! assert_eq!("[97,-255]", terms[1].to_string());
+//! ```
+//!
+//! Integers become the types that [`decode`] would give them, strings
+//! become an [`EString`] when they only contain ASCII characters and a list
+//! of integers otherwise.
+//! Pids, ports and exports can be written the way this crate displays them:
+//! `<node@host.1.2>`, `#Port<node@host.3>` and `fun lists:map/2`.
+//! Binaries may contain strings and bytes, which can be encoded in UTF-8
+//! with `/utf8`, like `<<"José"/utf8, 0>>`.
+//! Like [`borrowed::decode`], parsing fails for terms that are nested more
+//! than [`MAX_DEPTH`] levels deep.
+//!
+//! [`parse`]: fn.parse.html
+//! [`parse_all`]: fn.parse_all.html
+//! [`decode`]: ../decode/fn.decode.html
+//! [`EString`]: ../struct.EString.html
+//! [`borrowed::decode`]: ../borrowed/fn.decode.html
+//! [`MAX_DEPTH`]: ../borrowed/constant.MAX_DEPTH.html
+
+#[cfg(feature="bigint")]
+use num_bigint::BigInt;
+
+use super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, EPid, EPort, ETerm, ETuple, EString };
+use super::borrowed::MAX_DEPTH;
+use super::super::error::Error;
+
+use std::char;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The kinds of tokens of Erlang's term syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TokenKind {
+    /// An atom, and whether it was quoted.
+    Atom(String, bool),
+    /// A variable, which is not part of a term but of a pattern.
+    Variable(String),
+    /// The digits of an integer in `radix`.
+    Integer(String, u32),
+    Char(u32),
+    Float(f64),
+    String(Vec<u32>),
+    Punctuation(&'static str),
+    /// The `.` that ends a term, which is followed by whitespace.
+    Dot,
+    End,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Atom(name, _) => write!(f, "atom {}", EAtom::new(name.as_str())),
+            TokenKind::Variable(name) => write!(f, "variable {}", name),
+            TokenKind::Integer(digits, 10) => write!(f, "integer {}", digits),
+            TokenKind::Integer(digits, radix) => write!(f, "integer {}#{}", radix, digits),
+            TokenKind::Char(_) => f.write_str("character literal"),
+            TokenKind::Float(value) => write!(f, "float {}", value),
+            TokenKind::String(_) => f.write_str("string"),
+            TokenKind::Punctuation(text) => write!(f, "'{}'", text),
+            TokenKind::Dot => f.write_str("'.'"),
+            TokenKind::End => f.write_str("end of input"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    /// The line and column where the token starts.
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    pub(crate) fn error(&self, message: &str) -> Error {
+        Error::Message(format!("{} at line {}, column {}", message, self.line, self.column))
+    }
+
+    pub(crate) fn unexpected(&self, expected: &str) -> Error {
+        self.error(&format!("Expected {}, found {}", expected, self.kind))
+    }
+}
+
+const PUNCTUATION: [&str; 21] = [
+    "#Port", "<<", ">>", "=>", ":=",
+    "{", "}", "[", "]", "(", ")", ",", "|", "#", "/", ":", "-", "+", "<", ">", ".",
+];
+
+/// Splits `text` into tokens, ending with a `TokenKind::End`.
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+
+    loop {
+        while let Some(&c) = chars.get(position) {
+            if c == '%' {
+                while chars.get(position).is_some_and(|&c| c != '\n') {
+                    position += 1;
+                }
+            } else if c.is_whitespace() {
+                if c == '\n' {
+                    line += 1;
+                    line_start = position + 1;
+                }
+                position += 1;
+            } else {
+                break;
+            }
+        }
+
+        let column = position - line_start + 1;
+        let error = move |message: &str| Error::Message(format!("{} at line {}, column {}", message, line, column));
+        let start = position;
+
+        let c = match chars.get(position) {
+            Some(&c) => c,
+            None => {
+                tokens.push(Token { kind: TokenKind::End, line, column });
+                return Ok(tokens);
+            },
+        };
+
+        let kind = if c.is_ascii_digit() {
+            number(&chars, &mut position, tokens.last().map(|token: &Token| &token.kind)).map_err(|e| error(&e))?
+        } else if is_lowercase(c) || is_uppercase(c) || c == '_' {
+            while chars.get(position).is_some_and(|&c| c.is_alphanumeric() || c == '_' || c == '@') {
+                position += 1;
+            }
+
+            let name: String = chars[start..position].iter().collect();
+            if is_lowercase(c) {
+                TokenKind::Atom(name, false)
+            } else {
+                TokenKind::Variable(name)
+            }
+        } else if c == '\'' || c == '"' {
+            position += 1;
+            let mut value = Vec::new();
+
+            loop {
+                match chars.get(position) {
+                    None => return Err(error("Unterminated quoted text")),
+                    Some(&end) if end == c => {
+                        position += 1;
+                        break;
+                    },
+                    Some('\\') => {
+                        position += 1;
+                        value.push(escape(&chars, &mut position).map_err(|e| error(&e))?);
+                    },
+                    Some(&other) => {
+                        if other == '\n' {
+                            line += 1;
+                            line_start = position + 1;
+                        }
+                        value.push(other as u32);
+                        position += 1;
+                    },
+                }
+            }
+
+            if c == '"' {
+                TokenKind::String(value)
+            } else {
+                let name = value.into_iter().map(|c| char::from_u32(c).ok_or_else(|| error("Invalid character in atom"))).collect::<Result<String, _>>()?;
+                TokenKind::Atom(name, true)
+            }
+        } else if c == '$' {
+            position += 1;
+            match chars.get(position) {
+                None => return Err(error("Missing character after $")),
+                Some('\\') => {
+                    position += 1;
+                    TokenKind::Char(escape(&chars, &mut position).map_err(|e| error(&e))?)
+                },
+                Some(&c) => {
+                    position += 1;
+                    TokenKind::Char(c as u32)
+                },
+            }
+        } else if c == '.' && chars.get(position + 1).is_none_or(|c| c.is_whitespace() || *c == '%') {
+            position += 1;
+            TokenKind::Dot
+        } else {
+            let rest: String = chars[position..chars.len().min(position + 5)].iter().collect();
+            let punctuation = PUNCTUATION.iter().find(|p| rest.starts_with(**p))
+                .ok_or_else(|| error(&format!("Unexpected character {:?}", c)))?;
+            position += punctuation.chars().count();
+            TokenKind::Punctuation(punctuation)
+        };
+
+        tokens.push(Token { kind, line, column });
+    }
+}
+
+/// Unquoted atoms may start with these letters, like in Erlang.
+fn is_lowercase(c: char) -> bool {
+    c.is_ascii_lowercase() || (('ß'..='ÿ').contains(&c) && c != '÷')
+}
+
+fn is_uppercase(c: char) -> bool {
+    c.is_ascii_uppercase() || (('À'..='Þ').contains(&c) && c != '×')
+}
+
+/// Reads an integer, a based integer like `16#ff` or a float.
+///
+/// The numbers in a pid like `<node@host.1.2>` are not read as a float,
+/// which is recognized by the `.` token before them.
+fn number(chars: &[char], position: &mut usize, previous: Option<&TokenKind>) -> Result<TokenKind, String> {
+    let digits = |position: &mut usize, radix: u32| {
+        let start = *position;
+        while chars.get(*position).is_some_and(|c| c.is_digit(radix) || (*c == '_' && *position > start)) {
+            *position += 1;
+        }
+
+        let digits: String = chars[start..*position].iter().filter(|&&c| c != '_').collect();
+        if digits.is_empty() { None } else { Some(digits) }
+    };
+
+    let integer = digits(position, 10).expect("Numbers start with a digit");
+
+    if chars.get(*position) == Some(&'#') {
+        let radix = integer.parse::<u32>().ok().filter(|radix| (2..=36).contains(radix))
+            .ok_or_else(|| format!("Invalid radix {}", integer))?;
+        *position += 1;
+
+        let digits = digits(position, radix).ok_or_else(|| format!("Missing digits after {}#", radix))?;
+        return Ok(TokenKind::Integer(digits.to_lowercase(), radix));
+    }
+
+    let in_pid = previous == Some(&TokenKind::Punctuation("."));
+    if in_pid || chars.get(*position) != Some(&'.') || !chars.get(*position + 1).is_some_and(char::is_ascii_digit) {
+        return Ok(TokenKind::Integer(integer, 10));
+    }
+
+    *position += 1;
+    let mut text = format!("{}.{}", integer, digits(position, 10).expect("Checked for a digit"));
+
+    if chars.get(*position).is_some_and(|&c| c == 'e' || c == 'E') {
+        let mut end = *position + 1;
+        let sign = match chars.get(end) {
+            Some(&c) if c == '+' || c == '-' => {
+                end += 1;
+                c.to_string()
+            },
+            _ => String::new(),
+        };
+
+        if let Some(exponent) = digits(&mut end, 10) {
+            text = format!("{}e{}{}", text, sign, exponent);
+            *position = end;
+        }
+    }
+
+    text.parse::<f64>()
+        .map(TokenKind::Float)
+        .map_err(|e| e.to_string())
+}
+
+/// Reads the escape sequence after a `\`.
+fn escape(chars: &[char], position: &mut usize) -> Result<u32, String> {
+    let c = *chars.get(*position).ok_or("Unterminated escape sequence")?;
+    *position += 1;
+
+    Ok(match c {
+        'b' => 0x08,
+        'd' => 0x7f,
+        'e' => 0x1b,
+        'f' => 0x0c,
+        'n' => 0x0a,
+        'r' => 0x0d,
+        's' => 0x20,
+        't' => 0x09,
+        'v' => 0x0b,
+        '0'..='7' => {
+            let start = *position - 1;
+            while *position < start + 3 && chars.get(*position).is_some_and(|c| c.is_digit(8)) {
+                *position += 1;
+            }
+
+            let digits: String = chars[start..*position].iter().collect();
+            u32::from_str_radix(&digits, 8).expect("Octal digits")
+        },
+        'x' if chars.get(*position) == Some(&'{') => {
+            let end = chars[*position..].iter().position(|&c| c == '}').ok_or("Unterminated \\x{...}")? + *position;
+            let digits: String = chars[*position + 1..end].iter().collect();
+            *position = end + 1;
+
+            u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid escape sequence \\x{{{}}}", digits))?
+        },
+        'x' => {
+            let digits: String = chars.get(*position..*position + 2).ok_or("Unterminated \\x escape sequence")?.iter().collect();
+            *position += 2;
+
+            u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid escape sequence \\x{}", digits))?
+        },
+        '^' => {
+            let c = *chars.get(*position).ok_or("Unterminated \\^ escape sequence")?;
+            *position += 1;
+
+            c as u32 % 32
+        },
+        other => other as u32,
+    })
+}
+
+/// Converts the digits of an integer to the type [`decode`] would give it.
+///
+/// [`decode`]: ../decode/fn.decode.html
+fn integer_term(negative: bool, digits: &str, radix: u32) -> Option<Box<dyn ETerm>> {
+    let signed = if negative { format!("-{}", digits) } else { digits.to_string() };
+
+    if let Ok(value) = i128::from_str_radix(&signed, radix) {
+        return Some(match (u8::try_from(value), i32::try_from(value), i64::try_from(value)) {
+            (Ok(value), _, _) => Box::new(value),
+            (_, Ok(value), _) => Box::new(value),
+            (_, _, Ok(value)) => Box::new(value),
+            _ => Box::new(value),
+        });
+    }
+
+    if let (false, Ok(value)) = (negative, u128::from_str_radix(digits, radix)) {
+        return Some(Box::new(value));
+    }
+
+    #[cfg(feature="bigint")]
+    {
+        if let Some(value) = BigInt::parse_bytes(signed.as_bytes(), radix) {
+            return Some(Box::new(value));
+        }
+    }
+
+    None
+}
+
+/// A string literal as a term: an [`EString`] when it only contains ASCII,
+/// and a list of integers otherwise.
+///
+/// [`EString`]: ../struct.EString.html
+fn string_term(chars: &[u32]) -> Box<dyn ETerm> {
+    if chars.is_empty() {
+        Box::new(ENil)
+    } else if chars.iter().all(|&c| c < 0x80) {
+        Box::new(EString::new(chars.iter().map(|&c| c as u8 as char).collect::<String>()))
+    } else {
+        Box::new(EList::new(chars.iter().map(|&c| integer_term(false, &c.to_string(), 10).expect("Code points are small")).collect()))
+    }
+}
+
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// How many terms are being parsed that contain the next one.
+    depth: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(text: &str) -> Result<Parser, Error> {
+        Ok(Parser { tokens: tokenize(text)?, position: 0, depth: 0 })
+    }
+
+    pub(crate) fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    /// The token `offset` tokens after the next one, or the end of input.
+    pub(crate) fn peek_at(&self, offset: usize) -> &Token {
+        &self.tokens[(self.position + offset).min(self.tokens.len() - 1)]
+    }
+
+    pub(crate) fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    /// Consumes the next token if it is the punctuation `text`.
+    pub(crate) fn accept(&mut self, text: &str) -> bool {
+        if matches!(&self.peek().kind, TokenKind::Punctuation(p) if *p == text) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn expect(&mut self, text: &str) -> Result<(), Error> {
+        if self.accept(text) {
+            Ok(())
+        } else {
+            Err(self.peek().unexpected(&format!("'{}'", text)))
+        }
+    }
+
+    pub(crate) fn at_end(&self) -> bool {
+        self.peek().kind == TokenKind::End
+    }
+
+    /// Parses a term.
+    pub(crate) fn term(&mut self) -> Result<Box<dyn ETerm>, Error> {
+        self.nested(Parser::unnested_term)
+    }
+
+    /// Calls `parse` for something that is nested in the terms being parsed,
+    /// failing instead when those are nested more than [`MAX_DEPTH`] levels
+    /// deep, like [`borrowed::decode`] does.
+    ///
+    /// [`MAX_DEPTH`]: ../borrowed/constant.MAX_DEPTH.html
+    /// [`borrowed::decode`]: ../borrowed/fn.decode.html
+    pub(crate) fn nested<T>(&mut self, parse: impl FnOnce(&mut Parser) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.peek().error(&format!("Terms are nested more than {} levels deep", MAX_DEPTH)));
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    // The terms that contain other terms are parsed apart from the others,
+    // which keeps the stack frames of deeply nested terms small.
+    fn unnested_term(&mut self) -> Result<Box<dyn ETerm>, Error> {
+        if self.accept("{") {
+            Ok(Box::new(ETuple::new(self.sequence("}")?)))
+        } else if self.accept("[") {
+            self.list()
+        } else if matches!(self.peek().kind, TokenKind::Punctuation("#")) {
+            self.next();
+            self.expect("{")?;
+            Ok(Box::new(self.map()?))
+        } else {
+            self.leaf()
+        }
+    }
+
+    /// Parses the rest of a list after `[`.
+    fn list(&mut self) -> Result<Box<dyn ETerm>, Error> {
+        if self.accept("]") {
+            return Ok(Box::new(ENil));
+        }
+
+        let elements = self.elements()?;
+
+        if self.accept("|") {
+            let tail = self.term()?;
+            self.expect("]")?;
+
+            Ok(Box::new(ENonProperList::new(elements, tail)))
+        } else {
+            self.expect("]")?;
+            Ok(Box::new(EList::new(elements)))
+        }
+    }
+
+    /// Parses a term that does not contain other terms.
+    fn leaf(&mut self) -> Result<Box<dyn ETerm>, Error> {
+        let token = self.next();
+
+        Ok(match &token.kind {
+            TokenKind::Atom(name, quoted) if name == "fun" && !quoted => {
+                let module = self.atom()?;
+                self.expect(":")?;
+                let function = self.atom()?;
+                self.expect("/")?;
+                let arity = self.next();
+
+                match &arity.kind {
+                    TokenKind::Integer(digits, radix) => {
+                        let arity = u8::from_str_radix(digits, *radix).map_err(|_| arity.error("Arity must be at most 255"))?;
+                        Box::new(EExport::new(module, function, arity))
+                    },
+                    _ => return Err(arity.unexpected("an arity")),
+                }
+            },
+            TokenKind::Atom(name, _) => Box::new(EAtom::new(name.as_str())),
+            TokenKind::Integer(digits, radix) => integer_term(false, digits, *radix).ok_or_else(|| token.error("Integer too large"))?,
+            TokenKind::Char(c) => integer_term(false, &c.to_string(), 10).expect("Code points are small"),
+            TokenKind::Float(value) => Box::new(*value),
+            TokenKind::String(chars) => {
+                let mut chars = chars.clone();
+                while let TokenKind::String(more) = &self.peek().kind {
+                    chars.extend(more);
+                    self.position += 1;
+                }
+
+                string_term(&chars)
+            },
+            TokenKind::Punctuation(sign @ ("-" | "+")) => {
+                let number = self.next();
+
+                match (&number.kind, *sign == "-") {
+                    (TokenKind::Integer(digits, radix), negative) => integer_term(negative, digits, *radix).ok_or_else(|| number.error("Integer too large"))?,
+                    (TokenKind::Char(c), negative) => integer_term(negative, &c.to_string(), 10).expect("Code points are small"),
+                    (TokenKind::Float(value), true) => Box::new(-value),
+                    (TokenKind::Float(value), false) => Box::new(*value),
+                    _ => return Err(number.unexpected("a number")),
+                }
+            },
+            TokenKind::Punctuation("#Port") => {
+                self.expect("<")?;
+                let node = self.atom()?;
+                self.expect(".")?;
+                let id = self.u32()?;
+                self.expect(">")?;
+
+                Box::new(EPort::new(node, id, 0))
+            },
+            TokenKind::Punctuation("<<") => Box::new(self.binary()?),
+            TokenKind::Punctuation("<") => {
+                let node = self.atom()?;
+                self.expect(".")?;
+                let id = self.u32()?;
+                self.expect(".")?;
+                let serial = self.u32()?;
+                self.expect(">")?;
+
+                Box::new(EPid::new(node, id, serial, 0))
+            },
+            _ => return Err(token.unexpected("a term")),
+        })
+    }
+
+    fn atom(&mut self) -> Result<EAtom, Error> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Atom(name, _) => Ok(EAtom::new(name)),
+            _ => Err(token.unexpected("an atom")),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Integer(digits, radix) => u32::from_str_radix(digits, *radix).map_err(|_| token.error("Integer too large")),
+            _ => Err(token.unexpected("an integer")),
+        }
+    }
+
+    /// Parses terms separated by commas, up to and including `close`.
+    fn sequence(&mut self, close: &str) -> Result<Vec<Box<dyn ETerm>>, Error> {
+        if self.accept(close) {
+            return Ok(vec![]);
+        }
+
+        let elements = self.elements()?;
+        self.expect(close)?;
+
+        Ok(elements)
+    }
+
+    /// Parses one or more terms separated by commas.
+    fn elements(&mut self) -> Result<Vec<Box<dyn ETerm>>, Error> {
+        let mut elements = Vec::new();
+
+        loop {
+            elements.push(self.term()?);
+
+            if !self.accept(",") {
+                return Ok(elements);
+            }
+        }
+    }
+
+    fn map(&mut self) -> Result<EMap, Error> {
+        let mut entries: Vec<(Box<dyn ETerm>, Box<dyn ETerm>)> = Vec::new();
+
+        if !self.accept("}") {
+            loop {
+                let key = self.term()?;
+                self.expect("=>")?;
+                entries.push((key, self.term()?));
+
+                if !self.accept(",") {
+                    break;
+                }
+            }
+            self.expect("}")?;
+        }
+
+        Ok(EMap::new(entries))
+    }
+
+    /// Parses the segments of a binary after `<<`.
+    fn binary(&mut self) -> Result<EBinary, Error> {
+        let mut bytes = Vec::new();
+
+        if self.accept(">>") {
+            return Ok(EBinary::new(bytes));
+        }
+
+        loop {
+            let token = self.next();
+            let chars = match &token.kind {
+                TokenKind::String(chars) => chars.clone(),
+                TokenKind::Integer(digits, radix) => vec![u32::from_str_radix(digits, *radix).map_err(|_| token.error("Integer too large"))?],
+                TokenKind::Char(c) => vec![*c],
+                _ => return Err(token.unexpected("a string or an integer")),
+            };
+
+            if self.accept("/") {
+                let kind = self.next();
+                match &kind.kind {
+                    TokenKind::Atom(name, false) if name == "utf8" => {
+                        for c in chars {
+                            let c = char::from_u32(c).ok_or_else(|| token.error("Invalid code point"))?;
+                            bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                    },
+                    _ => return Err(kind.unexpected("utf8")),
+                }
+            } else {
+                for c in chars {
+                    bytes.push(u8::try_from(c).map_err(|_| token.error("Byte too large, use /utf8 for code points"))?);
+                }
+            }
+
+            if !self.accept(",") {
+                break;
+            }
+        }
+        self.expect(">>")?;
+
+        Ok(EBinary::new(bytes))
+    }
+}
+
+/// Parses a single term, which may be followed by a `.`.
+pub fn parse(text: &str) -> Result<Box<dyn ETerm>, Error> {
+    let mut parser = Parser::new(text)?;
+    let term = parser.term()?;
+
+    if parser.peek().kind == TokenKind::Dot {
+        parser.next();
+    }
+
+    if parser.at_end() {
+        Ok(term)
+    } else {
+        Err(parser.peek().unexpected("end of input"))
+    }
+}
+
+/// Parses a sequence of terms, which each end with a `.`.
+pub fn parse_all(text: &str) -> Result<Vec<Box<dyn ETerm>>, Error> {
+    let mut parser = Parser::new(text)?;
+    let mut terms = Vec::new();
+
+    while !parser.at_end() {
+        terms.push(parser.term()?);
+
+        let token = parser.next();
+        if token.kind != TokenKind::Dot {
+            return Err(token.unexpected("'.'"));
+        }
+    }
+
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ parse, parse_all };
+    use super::super::{ EBinary, EExport, EList, EPid, EPort };
+
+    #[test]
+    fn terms() {
+        let cases = [
+            ("ok", "ok"),
+            ("'hello world'", "'hello world'"),
+            ("42", "42"),
+            ("-42", "-42"),
+            ("16#FF", "255"),
+            ("2#1010_1010", "170"),
+            ("1_000_000", "1000000"),
+            ("$a", "97"),
+            ("$\\n", "10"),
+            ("-$a", "-97"),
+            ("2.5", "2.5"),
+            ("-1.0e-3", "-0.001"),
+            ("\"abc\"", "\"abc\""),
+            ("\"a\" \"b\"", "\"ab\""),
+            ("\"\"", "[]"),
+            ("\"é\"", "[233]"),
+            ("\"\\x{1F600}\\101\\^a\"", "[128512,65,1]"),
+            ("{}", "{}"),
+            ("{ok, {1, 2}}", "{ok,{1,2}}"),
+            ("[]", "[]"),
+            ("[1, 2 | 3]", "[1,2|3]"),
+            ("[1 | [2]]", "[1|[2]]"),
+            ("#{}", "#{}"),
+            ("#{a => 1, \"b\" => [2]}", "#{a=>1,\"b\"=>[2]}"),
+            ("<<>>", "<<>>"),
+            ("<<\"hi\", 0, $!>>", "<<104,105,0,33>>"),
+            ("<<\"é\"/utf8>>", "<<195,169>>"),
+            ("[1, % a comment\n 2]", "[1,2]"),
+            ("ok.", "ok"),
+        ];
+
+        for (text, expected) in cases.iter() {
+            let term = parse(text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+            assert_eq!(*expected, term.to_string(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn types() {
+        assert!(parse("255").unwrap().is::<u8>());
+        assert!(parse("256").unwrap().is::<i32>());
+        assert!(parse("-1").unwrap().is::<i32>());
+        assert!(parse("4294967296").unwrap().is::<i64>());
+        assert!(parse("170141183460469231731687303715884105728").unwrap().is::<u128>());
+        #[cfg(feature="bigint")]
+        assert_eq!("-1000000000000000000000000000000000000000000", parse("-1000000000000000000000000000000000000000000").unwrap().to_string());
+
+        let list = parse("[1, 2]").unwrap();
+        assert_eq!(2, list.downcast_ref::<EList>().unwrap().len());
+        assert_eq!(b"\xff", parse("<<255>>").unwrap().downcast_ref::<EBinary>().unwrap().as_bytes());
+
+        let export = parse("fun lists:map/2").unwrap();
+        assert_eq!("map", export.downcast_ref::<EExport>().unwrap().function().as_str());
+
+        let pid = parse("<node@host.1.2>").unwrap();
+        let pid = pid.downcast_ref::<EPid>().unwrap();
+        assert_eq!(("node@host", 1, 2), (pid.node().as_str(), pid.id(), pid.serial()));
+
+        let port = parse("#Port<'node@host'.7>").unwrap();
+        assert_eq!(7, port.downcast_ref::<EPort>().unwrap().id());
+    }
+
+    #[test]
+    fn sequences() {
+        let terms = parse_all("{a, 1}.\n\n% comment\n[b].").unwrap();
+        assert_eq!(vec!["{a,1}", "[b]"], terms.iter().map(|t| t.to_string()).collect::<Vec<_>>());
+        assert!(parse_all("").unwrap().is_empty());
+        assert!(parse_all("{a, 1}").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("{a, 1", "Expected '}', found end of input at line 1, column 6"),
+            ("{a\n  1}", "Expected '}', found integer 1 at line 2, column 3"),
+            ("X", "Expected a term, found variable X at line 1, column 1"),
+            ("'abc", "Unterminated quoted text at line 1, column 1"),
+            ("a b", "Expected end of input, found atom b at line 1, column 3"),
+            ("<<256>>", "Byte too large, use /utf8 for code points at line 1, column 3"),
+            ("[|1]", "Expected a term, found '|' at line 1, column 2"),
+            ("1#0", "Invalid radix 1 at line 1, column 1"),
+            ("#{a}", "Expected '=>', found '}' at line 1, column 4"),
+            ("&", "Unexpected character '&' at line 1, column 1"),
+        ];
+
+        for (text, expected) in cases.iter() {
+            match parse(text) {
+                Ok(term) => panic!("{} parsed as {}", text, term),
+                Err(e) => assert_eq!(*expected, e.to_string(), "{}", text),
+            }
+        }
+    }
+
+    #[test]
+    fn nesting_limit() {
+        use super::super::borrowed::MAX_DEPTH;
+
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        let maps = |depth: usize| format!("{}b{}", "#{a => ".repeat(depth), "}".repeat(depth));
+        assert!(parse(&maps(MAX_DEPTH - 1)).is_ok());
+        assert!(parse(&maps(MAX_DEPTH)).is_err());
+
+        let expected = format!("Terms are nested more than {} levels deep at line 1, column {}", MAX_DEPTH, MAX_DEPTH + 1);
+        assert_eq!(expected, parse(&nested(MAX_DEPTH + 1)).unwrap_err().to_string());
+        assert!(parse(&nested(1_000_000)).is_err());
+        assert!(parse(&"{".repeat(1_000_000)).is_err());
+    }
+}
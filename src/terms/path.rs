@@ -0,0 +1,579 @@
+//! Paths to terms nested in tuples, lists and maps.
+//!
+//! A [`Path`] is written as its segments separated by `.`s:
+//!
+//! | Segment  | Refers to                                                  |
+//! |----------|------------------------------------------------------------|
+//! | `2`      | The second element of a tuple, numbered like `element/2`   |
+//! | `[0]`    | The first element of a list (no `.` is needed before it)   |
+//! | `[\|]`   | The tail of an improper list, after any lists it ends with |
+//! | `users`  | The value of the atom key `users` in a map                 |
+//! | `#name`  | The value of the binary key `<<"name">>` in a map          |
+//! | `(1.5)`  | The value of any other key in a map, in Erlang syntax      |
+//!
+//! So `2.users[0].#name` is the `<<"name">>` of the first of the `users` in
+//! the second element of a tuple.
+//! The empty path refers to the term itself.
+//!
+//! Paths are usually used through [`ETerm::at`] and its relatives, which
+//! find terms in [`ETuple`]s, [`EList`]s, [`ENonProperList`]s and [`EMap`]s:
+//!
+//! ```
+//! # #[macro_use] extern crate rust_eterm;
+//! # fn main() {
+//! use rust_eterm::terms::{ EList, ETerm };
+//!
+//! let mut term: Box<dyn ETerm> = Box::new(eterm!({reply, #{users => [joe], count => 1}}));
+//!
+//! term.at_mut("2.users").unwrap().downcast_mut::<EList>().unwrap().push(eterm!(mike));
+//! term.replace_at("2.count", Box::new(2)).unwrap();
+//!
+//! assert_eq!("[joe,mike]", term.at("2.users").unwrap().to_string());
+//! assert_eq!(2, term.at_as::<i32>("2.count").unwrap());
+//! # }
+//! ```
+//!
+//! [`Path`]: struct.Path.html
+//! [`ETerm::at`]: ../trait.ETerm.html#tymethod.at
+//! [`ETuple`]: ../struct.ETuple.html
+//! [`EList`]: ../struct.EList.html
+//! [`ENonProperList`]: ../struct.ENonProperList.html
+//! [`EMap`]: ../struct.EMap.html
+
+use super::{ EAtom, EBinary, EList, EMap, ENil, ENonProperList, EString, ETerm, ETuple };
+use super::parse::parse;
+use super::pretty::single_line;
+use super::super::error::Error;
+
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::slice;
+use std::vec;
+
+/// A step from a term to a term nested in it.
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    /// An element of a tuple, numbered from 1.
+    Element(usize),
+    /// An element of a list, numbered from 0.
+    Index(usize),
+    /// The tail of an improper list.
+    ///
+    /// Like [`diff`], this looks through tails that are lists themselves,
+    /// so in `[1|[2|c]]` it refers to `c`.
+    ///
+    /// [`diff`]: ../diff/fn.diff.html
+    Tail,
+    /// The value of a key in a map.
+    Key(Box<dyn ETerm>),
+}
+
+/// A sequence of [`Segment`]s, which leads from a term to a term nested in it.
+///
+/// [`Segment`]: enum.Segment.html
+#[derive(Debug, Default, PartialEq)]
+pub struct Path(Vec<Segment>);
+
+impl Path {
+    /// A path made of `segments`, which are followed in order.
+    pub fn new(segments: Vec<Segment>) -> Path {
+        Path(segments)
+    }
+
+    /// Parses a path, like `2.users[0].#name`.
+    pub fn parse(text: &str) -> Result<Path, Error> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut segments = Vec::new();
+        let mut position = 0;
+
+        let error = |position: usize, message: &str| Error::Message(format!(
+            "{} at column {} of path {:?}", message, position + 1, text,
+        ));
+
+        while position < chars.len() {
+            let start = position;
+            let scan = |position: &mut usize, accept: fn(char) -> bool| {
+                let start = *position;
+                while chars.get(*position).is_some_and(|&c| accept(c)) {
+                    *position += 1;
+                }
+
+                chars[start..*position].iter().collect::<String>()
+            };
+            let name = |position: &mut usize| scan(position, |c| c.is_ascii_alphanumeric() || c == '_' || c == '@');
+            let digits = |position: &mut usize| scan(position, |c| c.is_ascii_digit());
+
+            let segment = match chars[position] {
+                '[' if chars.get(position + 1..position + 3) == Some(&['|', ']']) => {
+                    position += 3;
+                    Segment::Tail
+                },
+                '[' => {
+                    position += 1;
+                    let index = digits(&mut position).parse().map_err(|_| error(start + 1, "Expected a list index"))?;
+
+                    if chars.get(position) != Some(&']') {
+                        return Err(error(position, "Expected ']'"));
+                    }
+                    position += 1;
+
+                    Segment::Index(index)
+                },
+                c if c.is_ascii_digit() => {
+                    match digits(&mut position).parse() {
+                        Ok(0) => return Err(error(start, "Tuple elements are numbered from 1")),
+                        Ok(n) => Segment::Element(n),
+                        Err(_) => return Err(error(start, "Expected a tuple element")),
+                    }
+                },
+                '#' => {
+                    position += 1;
+                    let key = name(&mut position);
+                    if !is_name(&key) {
+                        return Err(error(start + 1, "Expected the name of a binary key"));
+                    }
+
+                    Segment::Key(Box::new(EBinary::new(key.into_bytes())))
+                },
+                '(' => {
+                    position = closing_parenthesis(&chars, position).ok_or_else(|| error(start, "Expected ')'"))?;
+                    let key: String = chars[start + 1..position].iter().collect();
+                    position += 1;
+
+                    Segment::Key(parse(&key).map_err(|e| error(start + 1, &e.to_string()))?)
+                },
+                c if c.is_ascii_alphabetic() || c == '_' => Segment::Key(Box::new(EAtom::new(name(&mut position)))),
+                _ => return Err(error(start, "Expected a tuple element, list index or map key")),
+            };
+
+            segments.push(segment);
+
+            match chars.get(position) {
+                Some('.') if position + 1 < chars.len() => position += 1,
+                Some('[') | None => {},
+                _ => return Err(error(position, "Expected '.' or '['")),
+            }
+        }
+
+        Ok(Path(segments))
+    }
+
+    /// Returns the term at this path in `term`.
+    pub fn get<'t>(&self, term: &'t dyn ETerm) -> Result<&'t dyn ETerm, Error> {
+        let mut term = term;
+
+        for (i, segment) in self.0.iter().enumerate() {
+            term = child(term, segment).map_err(|reason| self.error(i, reason))?;
+        }
+
+        Ok(term)
+    }
+
+    /// Returns the term at this path in `term`, which can be changed in place.
+    pub fn get_mut<'t>(&self, term: &'t mut dyn ETerm) -> Result<&'t mut dyn ETerm, Error> {
+        let mut term = term;
+
+        for (i, segment) in self.0.iter().enumerate() {
+            term = &mut **child_mut(term, segment).map_err(|reason| self.error(i, reason))?;
+        }
+
+        Ok(term)
+    }
+
+    /// Replaces the term at this path in `term` by `value`, and returns the
+    /// term that was there.
+    ///
+    /// The empty path cannot be replaced, since it refers to `term` itself.
+    pub fn replace(&self, term: &mut dyn ETerm, value: Box<dyn ETerm>) -> Result<Box<dyn ETerm>, Error> {
+        let (last, parents) = self.0.split_last()
+            .ok_or_else(|| Error::Message("The empty path cannot be replaced".to_string()))?;
+
+        let mut parent = term;
+        for (i, segment) in parents.iter().enumerate() {
+            parent = &mut **child_mut(parent, segment).map_err(|reason| self.error(i, reason))?;
+        }
+
+        let slot = child_mut(parent, last).map_err(|reason| self.error(parents.len(), reason))?;
+
+        Ok(mem::replace(slot, value))
+    }
+
+    /// The error for a path of which segment `index` could not be followed.
+    fn error(&self, index: usize, reason: String) -> Error {
+        let location = match index {
+            0 => "the term".to_string(),
+            _ => format!("the term at {}", Segments(&self.0[..index])),
+        };
+
+        Error::Message(format!("No term at {}: {} {}", self, location, reason))
+    }
+
+    /// The segments of this path.
+    pub fn into_inner(self) -> Vec<Segment> {
+        self.0
+    }
+}
+
+impl Deref for Path {
+    type Target = [Segment];
+
+    fn deref(&self) -> &[Segment] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Path {
+    type Item = Segment;
+    type IntoIter = vec::IntoIter<Segment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Path {
+    type Item = &'a Segment;
+    type IntoIter = slice::Iter<'a, Segment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<Segment>> for Path {
+    fn from(segments: Vec<Segment>) -> Path {
+        Path(segments)
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Segment::Element(n) => write!(f, "{}", n),
+            Segment::Index(i) => write!(f, "[{}]", i),
+            Segment::Tail => write!(f, "[|]"),
+            Segment::Key(key) => {
+                if let Some(atom) = key.downcast_ref::<EAtom>() {
+                    if is_name(atom.as_str()) && atom.to_string() == atom.as_str() {
+                        return write!(f, "{}", atom);
+                    }
+                }
+
+                if let Some(name) = key.downcast_ref::<EBinary>().and_then(|binary| std::str::from_utf8(binary.as_bytes()).ok()) {
+                    if is_name(name) {
+                        return write!(f, "#{}", name);
+                    }
+                }
+
+                write!(f, "({})", single_line(&**key))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Segments(&self.0))
+    }
+}
+
+/// Displays segments the way a path with them is displayed.
+struct Segments<'s>(&'s [Segment]);
+
+impl fmt::Display for Segments<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Index(_) | Segment::Tail => {},
+                _ if i > 0 => write!(f, ".")?,
+                _ => {},
+            }
+
+            write!(f, "{}", segment)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the `)` that closes the `(` at `open`, skipping parentheses in
+/// strings, quoted atoms and characters.
+fn closing_parenthesis(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut position = open;
+
+    while position < chars.len() {
+        match (chars[position], quote) {
+            ('\\', Some(_)) => position += 1,
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {},
+            ('"', None) | ('\'', None) => quote = Some(chars[position]),
+            ('$', None) if chars.get(position + 1) == Some(&'\\') => position += 2,
+            ('$', None) => position += 1,
+            ('(', None) => depth += 1,
+            (')', None) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position);
+                }
+            },
+            _ => {},
+        }
+
+        position += 1;
+    }
+
+    None
+}
+
+/// Follows `segment` from `term`, or returns why it cannot be followed.
+fn child<'t>(term: &'t dyn ETerm, segment: &Segment) -> Result<&'t dyn ETerm, String> {
+    match segment {
+        Segment::Element(n) => {
+            let tuple = term.downcast_ref::<ETuple>().ok_or("is not a tuple")?;
+
+            n.checked_sub(1).and_then(|i| tuple.as_slice().get(i)).map(|element| &**element).ok_or_else(|| format!("has no element {}", n))
+        },
+        Segment::Index(i) => {
+            if let Some(list) = term.downcast_ref::<EList>() {
+                list.as_slice().get(*i).map(|element| &**element).ok_or_else(|| format!("has no element [{}]", i))
+            } else if let Some(list) = term.downcast_ref::<ENonProperList>() {
+                match list.data().get(*i) {
+                    Some(element) => Ok(&**element),
+                    None if is_list(list.tail()) => child(list.tail(), &Segment::Index(i - list.data().len()))
+                        .map_err(|_| format!("has no element [{}]", i)),
+                    None => Err(format!("has no element [{}]", i)),
+                }
+            } else {
+                Err(not_a_list(term))
+            }
+        },
+        Segment::Tail => {
+            let list = term.downcast_ref::<ENonProperList>().ok_or("is not an improper list")?;
+
+            match list.tail() {
+                tail if tail.is::<ENonProperList>() => child(tail, segment),
+                tail => Ok(tail),
+            }
+        },
+        Segment::Key(key) => {
+            let map = term.downcast_ref::<EMap>().ok_or("is not a map")?;
+
+            map.get(&**key).ok_or_else(|| format!("has no key {}", single_line(&**key)))
+        },
+    }
+}
+
+/// Follows `segment` from `term`, like `child`, to the box of a term that
+/// can be replaced.
+fn child_mut<'t>(term: &'t mut dyn ETerm, segment: &Segment) -> Result<&'t mut Box<dyn ETerm>, String> {
+    match segment {
+        Segment::Element(n) => {
+            let tuple = term.downcast_mut::<ETuple>().ok_or("is not a tuple")?;
+
+            n.checked_sub(1).and_then(move |i| tuple.as_mut_slice().get_mut(i)).ok_or_else(|| format!("has no element {}", n))
+        },
+        Segment::Index(i) => {
+            if term.is::<EList>() {
+                let list = term.downcast_mut::<EList>().expect("The type was just checked");
+                list.as_mut_slice().get_mut(*i).ok_or_else(|| format!("has no element [{}]", i))
+            } else if term.is::<ENonProperList>() {
+                let list = term.downcast_mut::<ENonProperList>().expect("The type was just checked");
+                let length = list.data().len();
+
+                if *i < length {
+                    Ok(&mut list.data_mut()[*i])
+                } else if is_list(list.tail()) {
+                    child_mut(&mut **list.tail_mut(), &Segment::Index(i - length))
+                        .map_err(|_| format!("has no element [{}]", i))
+                } else {
+                    Err(format!("has no element [{}]", i))
+                }
+            } else {
+                Err(not_a_list(term))
+            }
+        },
+        Segment::Tail => {
+            let list = term.downcast_mut::<ENonProperList>().ok_or("is not an improper list")?;
+
+            if list.tail().is::<ENonProperList>() {
+                child_mut(&mut **list.tail_mut(), segment)
+            } else {
+                Ok(list.tail_mut())
+            }
+        },
+        Segment::Key(key) => {
+            let map = term.downcast_mut::<EMap>().ok_or("is not a map")?;
+
+            map.get_mut(&**key).ok_or_else(|| format!("has no key {}", single_line(&**key)))
+        },
+    }
+}
+
+fn is_list(term: &dyn ETerm) -> bool {
+    term.is::<EList>() || term.is::<ENonProperList>()
+}
+
+fn not_a_list(term: &dyn ETerm) -> String {
+    if term.is::<ENil>() {
+        "is an empty list".to_string()
+    } else if term.is::<EString>() {
+        "is a string, of which the characters are not terms".to_string()
+    } else {
+        "is not a list".to_string()
+    }
+}
+
+/// Whether `name` can be written as a bare atom or binary key, which is the
+/// case if it starts with a letter or `_`, and only contains letters, digits,
+/// `_`s and `@`s.
+pub(crate) fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ Path, Segment };
+    use super::super::{ EAtom, EBinary, EList, EString, ETerm, ETuple };
+    use super::super::diff::diff;
+
+    #[test]
+    fn display() {
+        let path = Path::new(vec![
+            Segment::Element(2),
+            Segment::Key(Box::new(EAtom::new("users"))),
+            Segment::Index(0),
+            Segment::Key(Box::new(EBinary::from("name".as_bytes()))),
+        ]);
+        assert_eq!("2.users[0].#name", path.to_string());
+
+        let path = Path::new(vec![
+            Segment::Index(3),
+            Segment::Tail,
+            Segment::Key(Box::new(EAtom::new("Upper"))),
+            Segment::Key(Box::new(EBinary::from("two words".as_bytes()))),
+            Segment::Key(Box::new(EString::new("s"))),
+            Segment::Key(Box::new(ETuple::from((1u8, 2.5f64)))),
+            Segment::Element(1),
+        ]);
+        assert_eq!("[3][|].('Upper').(<<\"two words\">>).(\"s\").({1,2.5}).1", path.to_string());
+
+        assert_eq!("", Path::default().to_string());
+    }
+
+    #[test]
+    fn parse() {
+        let texts = [
+            "",
+            "1",
+            "2.users[0].#name",
+            "[3][|].('Upper').(<<\"two words\">>).(\"s\").({1,2.5}).1",
+            "(1).(-2.5).('a.b').(\"(\")",
+            "a@b.('_x').#_Y1",
+        ];
+
+        for text in texts.iter() {
+            assert_eq!(*text, Path::parse(text).unwrap().to_string());
+        }
+
+        let path = Path::parse("(\"s\").([1, 2]).($)).('\\'')").unwrap();
+        assert_eq!("(\"s\").([1,2]).(41).('\\'')", path.to_string());
+        assert_eq!(Path::parse("3").unwrap(), Path::new(vec![Segment::Element(3)]));
+
+        let error = |text| Path::parse(text).unwrap_err().to_string();
+        assert_eq!("Tuple elements are numbered from 1 at column 1 of path \"0\"", error("0"));
+        assert_eq!("Expected a list index at column 3 of path \"a[b]\"", error("a[b]"));
+        assert_eq!("Expected ']' at column 3 of path \"[1|]\"", error("[1|]"));
+        assert_eq!("Expected '.' or '[' at column 2 of path \"1a\"", error("1a"));
+        assert_eq!("Expected '.' or '[' at column 2 of path \"a.\"", error("a."));
+        assert_eq!("Expected a tuple element, list index or map key at column 3 of path \"a..b\"", error("a..b"));
+        assert_eq!("Expected the name of a binary key at column 2 of path \"#\"", error("#"));
+        assert_eq!("Expected ')' at column 1 of path \"(a\"", error("(a"));
+    }
+
+    #[test]
+    fn get() {
+        let term: Box<dyn ETerm> = Box::new(eterm!({reply, #{
+            users => [#{<<"name">> => <<"joe">>}, #{<<"name">> => <<"mike">>}],
+            "s" => [1, 2 | [3 | tail]],
+            {a} => (EString::new("chars")),
+            1.0 => float,
+            1 => integer
+        }}));
+
+        assert_eq!("reply", term.at("1").unwrap().to_string());
+        assert_eq!("<<109,105,107,101>>", term.at("2.users[1].#name").unwrap().to_string());
+        assert_eq!("3", term.at("2.(\"s\")[2]").unwrap().to_string());
+        assert_eq!("tail", term.at("2.(\"s\")[|]").unwrap().to_string());
+        assert_eq!("float", term.at("2.(1.0)").unwrap().to_string());
+        assert_eq!("integer", term.at("2.(1)").unwrap().to_string());
+        assert_eq!(term.to_string(), term.at("").unwrap().to_string());
+        assert_eq!("chars", term.at_as::<String>("2.({a})").unwrap());
+
+        let error = |path| term.at(path).unwrap_err().to_string();
+        assert_eq!("No term at 3: the term has no element 3", error("3"));
+        assert_eq!("No term at 1.a: the term at 1 is not a map", error("1.a"));
+        assert_eq!("No term at 2.users[2]: the term at 2.users has no element [2]", error("2.users[2]"));
+        assert_eq!("No term at 2.users[|]: the term at 2.users is not an improper list", error("2.users[|]"));
+        assert_eq!("No term at 2.(\"s\")[4]: the term at 2.(\"s\") has no element [4]", error("2.(\"s\")[4]"));
+        assert_eq!("No term at 2.#users: the term at 2 has no key <<\"users\">>", error("2.#users"));
+        assert_eq!("No term at 2.({a})[0]: the term at 2.({a}) is a string, of which the characters are not terms", error("2.({a})[0]"));
+        assert_eq!("No term at [0]: the term is not a list", error("[0]"));
+        assert_eq!("No term at 2.(\"s\")[|][|]: the term at 2.(\"s\")[|] is not an improper list", error("2.(\"s\")[|][|]"));
+        assert_eq!("No term at 0: the term has no element 0", Path::new(vec![Segment::Element(0)]).get(&*term).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut term: Box<dyn ETerm> = Box::new(eterm!({counter, 1, [a | b], #{items => [x]}}));
+
+        *term.at_mut("2").unwrap().downcast_mut::<i32>().unwrap() += 1;
+        term.at_mut("4.items").unwrap().downcast_mut::<EList>().unwrap().push(EAtom::new("y"));
+        assert_eq!("b", term.replace_at("3[|]", Box::new(EAtom::new("c"))).unwrap().to_string());
+        assert_eq!("a", term.replace_at("3[0]", Box::new(ETuple::from((1u8,)))).unwrap().to_string());
+
+        assert_eq!("{counter,2,[{1}|c],#{items=>[x,y]}}", term.to_string());
+        assert!(term.replace_at("", Box::new(EAtom::new("x"))).is_err());
+        assert_eq!(
+            "No term at 4.count: the term at 4 has no key count",
+            term.replace_at("4.count", Box::new(1)).unwrap_err().to_string(),
+        );
+    }
+
+    #[test]
+    fn diff_paths() {
+        let left = eterm!({ok, #{<<"id">> => [1, {2, 3}]}});
+        let right = eterm!({ok, #{<<"id">> => [1, {2, 4}]}});
+
+        let differences = diff(&left, &right).unwrap();
+        let path = &differences[0].path;
+
+        assert_eq!("2.#id[1].2", path.to_string());
+        assert_eq!("3", path.get(&left).unwrap().to_string());
+        assert_eq!("4", path.get(&right).unwrap().to_string());
+
+        let left = eterm!([1 | [2 | c]]);
+        let right = eterm!([1 | [2 | d]]);
+
+        let differences = diff(&left, &right).unwrap();
+        let path = &differences[0].path;
+
+        assert_eq!("[|]", path.to_string());
+        assert_eq!("c", path.get(&left).unwrap().to_string());
+        assert_eq!("d", path.get(&right).unwrap().to_string());
+    }
+
+    #[test]
+    fn segments() {
+        let path = Path::parse("1[2][|]").unwrap();
+
+        assert_eq!(&[Segment::Element(1), Segment::Index(2), Segment::Tail], &*path);
+        assert_eq!(3, (&path).into_iter().count());
+        assert_eq!(vec![Segment::Element(1), Segment::Index(2), Segment::Tail], path.into_iter().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,580 @@
+//! Matching terms against Erlang patterns.
+//!
+//! A [`Pattern`] is parsed from Erlang's pattern syntax.
+//! It consists of:
+//!
+//! | Syntax                 | Matches                                               |
+//! |------------------------|-------------------------------------------------------|
+//! | `_`                    | Any term                                              |
+//! | `Name`, `_Name`        | Any term, which is bound to the variable              |
+//! | `ok`, `1`, `"s"`, ...  | A term that is exactly equal (`=:=`) to the literal   |
+//! | `{P1, P2}`             | A tuple of the same arity, with matching elements     |
+//! | `[P1, P2]`             | A list of the same length, with matching elements     |
+//! | `[P1, P2 \| T]`        | A list with at least two elements, with the rest in `T` |
+//! | `#{key := P}`          | A map that contains at least the given keys           |
+//!
+//! Literals can be written in any syntax that [`parse`] accepts, and so can
+//! the keys of maps, which are associated with `:=` like in Erlang.
+//! A variable that occurs more than once only matches equal terms.
+//!
+//! Matching a term returns the [`Bindings`] of its variables, which makes
+//! patterns suited to handling the messages of a receive loop:
+//!
+//! ```
+//! # #[macro_use] extern crate rust_eterm;
+//! # fn main() -> Result<(), rust_eterm::error::Error> {
+//! use rust_eterm::terms::ETerm;
+//! use rust_eterm::terms::pattern::{ first_match, Pattern };
+//!
+//! let clauses = [
+//!     Pattern::parse("{reply, Ref, {ok, Value}}")?,
+//!     Pattern::parse("{reply, Ref, {error, #{reason := Reason}}}")?,
+//!     Pattern::parse("_")?,
+//! ];
+//!
+//! let mailbox: Vec<Box<dyn ETerm>> = vec![
+//!     Box::new(eterm!({reply, 1, {ok, [300, 400]}})),
+//!     Box::new(eterm!({reply, 2, {error, #{reason => timeout, node => a}}})),
+//!     Box::new(eterm!(unexpected)),
+//! ];
+//!
+//! for message in mailbox {
+//!     match first_match(&clauses, &*message)? {
+//!         Some((0, bindings)) => assert_eq!(vec![300, 400], bindings.get_as::<Vec<i32>>("Value")?),
+//!         Some((1, bindings)) => assert_eq!(2, bindings.get_as::<i32>("Ref")?),
+//!         _ => assert_eq!("unexpected", message.to_string()),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Pattern`]: struct.Pattern.html
+//! [`Bindings`]: struct.Bindings.html
+//! [`parse`]: ../parse/fn.parse.html
+
+use super::{ ENil, ETerm };
+use super::borrowed::BorrowedTerm;
+use super::convert::FromETerm;
+use super::order::{ compare_keys, exactly_equal_terms, with_borrowed };
+use super::parse::{ Parser, TokenKind };
+use super::pretty::single_line;
+use super::super::error::Error;
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Deref;
+use std::slice;
+use std::vec;
+
+/// A pattern that terms can be matched against.
+#[derive(Debug)]
+pub struct Pattern(Node);
+
+/// The parts of a pattern.
+///
+/// Literals are compared to terms through [`with_borrowed`], which views the
+/// terms that parsing gives directly, without encoding or decoding them.
+#[derive(Debug)]
+enum Node {
+    Any,
+    Variable(String),
+    Literal(Box<dyn ETerm>),
+    Tuple(Vec<Node>),
+    /// The patterns of the first elements of a list, and of its tail.
+    List(Vec<Node>, Box<Node>),
+    /// The keys of a map, and the patterns of their values.
+    Map(Vec<(Box<dyn ETerm>, Node)>),
+}
+
+/// The terms that the variables of a pattern are bound to, in the order in
+/// which they were bound.
+///
+/// `Bindings` derefs to a slice of the variables and their terms.
+#[derive(Debug, Default)]
+pub struct Bindings(Vec<(String, Box<dyn ETerm>)>);
+
+impl Bindings {
+    /// The term that the variable `name` is bound to.
+    pub fn get(&self, name: &str) -> Option<&dyn ETerm> {
+        self.0.iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, term)| &**term)
+    }
+
+    /// Converts the term that the variable `name` is bound to.
+    pub fn get_as<T: FromETerm>(&self, name: &str) -> Result<T, Error> {
+        let term = self.get(name)
+            .ok_or_else(|| Error::Message(format!("Variable {} is not bound", name)))?;
+
+        T::from_eterm(term)
+    }
+
+    /// Removes the variable `name`, and returns the term it was bound to.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn ETerm>> {
+        let index = self.0.iter().position(|(variable, _)| variable == name)?;
+
+        Some(self.0.remove(index).1)
+    }
+
+    /// Takes the variables and their terms out of these bindings.
+    pub fn into_inner(self) -> Vec<(String, Box<dyn ETerm>)> {
+        self.0
+    }
+}
+
+impl Deref for Bindings {
+    type Target = [(String, Box<dyn ETerm>)];
+
+    fn deref(&self) -> &[(String, Box<dyn ETerm>)] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Bindings {
+    type Item = (String, Box<dyn ETerm>);
+    type IntoIter = vec::IntoIter<(String, Box<dyn ETerm>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Bindings {
+    type Item = &'a (String, Box<dyn ETerm>);
+    type IntoIter = slice::Iter<'a, (String, Box<dyn ETerm>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Pattern {
+    /// Parses a pattern, which may be followed by a `.`.
+    pub fn parse(text: &str) -> Result<Pattern, Error> {
+        let mut parser = Parser::new(text)?;
+        let node = node(&mut parser)?;
+
+        if parser.peek().kind == TokenKind::Dot {
+            parser.next();
+        }
+
+        if parser.at_end() {
+            Ok(Pattern(node))
+        } else {
+            Err(parser.peek().unexpected("end of input"))
+        }
+    }
+
+    /// Matches `term` against this pattern, and returns the bindings of its
+    /// variables if it matches.
+    pub fn match_term(&self, term: &dyn ETerm) -> Result<Option<Bindings>, Error> {
+        with_borrowed(term, |term| self.match_borrowed(term))?
+    }
+
+    /// Whether `term` matches this pattern.
+    pub fn is_match(&self, term: &dyn ETerm) -> Result<bool, Error> {
+        Ok(self.match_term(term)?.is_some())
+    }
+
+    fn match_borrowed(&self, term: &BorrowedTerm) -> Result<Option<Bindings>, Error> {
+        let mut bound = Vec::new();
+
+        if !matches(&self.0, term, &mut bound)? {
+            return Ok(None);
+        }
+
+        let bindings = bound.into_iter()
+            .map(|(name, term)| Ok((name.to_string(), term.to_owned()?)))
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Some(Bindings(bindings)))
+    }
+}
+
+/// Matches `term` against each of `patterns` in turn, like the clauses of a
+/// `receive` or `case`, and returns the index of the first pattern that
+/// matches with its bindings.
+///
+/// `term` is only converted for matching once, however many patterns there
+/// are.
+pub fn first_match(patterns: &[Pattern], term: &dyn ETerm) -> Result<Option<(usize, Bindings)>, Error> {
+    with_borrowed(term, |term| {
+        for (i, pattern) in patterns.iter().enumerate() {
+            if let Some(bindings) = pattern.match_borrowed(term)? {
+                return Ok(Some((i, bindings)));
+            }
+        }
+
+        Ok(None)
+    })?
+}
+
+/// A literal, which must be a term that can be encoded.
+fn literal(term: Box<dyn ETerm>) -> Result<Node, Error> {
+    with_borrowed(&*term, |_| ())?;
+
+    Ok(Node::Literal(term))
+}
+
+fn node(parser: &mut Parser) -> Result<Node, Error> {
+    parser.nested(unnested_node)
+}
+
+fn unnested_node(parser: &mut Parser) -> Result<Node, Error> {
+    let kind = parser.peek().kind.clone();
+
+    match kind {
+        TokenKind::Variable(name) => {
+            parser.next();
+
+            Ok(if name == "_" { Node::Any } else { Node::Variable(name) })
+        },
+        TokenKind::Punctuation("{") => {
+            parser.next();
+
+            if parser.accept("}") {
+                return Ok(Node::Tuple(vec![]));
+            }
+
+            let elements = nodes(parser)?;
+            parser.expect("}")?;
+
+            Ok(Node::Tuple(elements))
+        },
+        TokenKind::Punctuation("[") if !matches!(parser.peek_at(1).kind, TokenKind::Punctuation("]")) => {
+            parser.next();
+
+            let elements = nodes(parser)?;
+            let tail = if parser.accept("|") { node(parser)? } else { literal(Box::new(ENil))? };
+            parser.expect("]")?;
+
+            Ok(Node::List(elements, Box::new(tail)))
+        },
+        TokenKind::Punctuation("#") if matches!(parser.peek_at(1).kind, TokenKind::Punctuation("{")) => {
+            parser.next();
+            parser.next();
+
+            map(parser)
+        },
+        _ => literal(parser.term()?),
+    }
+}
+
+/// Parses one or more patterns separated by commas.
+fn nodes(parser: &mut Parser) -> Result<Vec<Node>, Error> {
+    let mut nodes = Vec::new();
+
+    loop {
+        nodes.push(node(parser)?);
+
+        if !parser.accept(",") {
+            return Ok(nodes);
+        }
+    }
+}
+
+/// Parses the associations of a map pattern after `#{`.
+fn map(parser: &mut Parser) -> Result<Node, Error> {
+    let mut entries = Vec::new();
+
+    if parser.accept("}") {
+        return Ok(Node::Map(entries));
+    }
+
+    loop {
+        if let TokenKind::Variable(_) = parser.peek().kind {
+            return Err(parser.peek().error("The keys of a map pattern must be literals"));
+        }
+
+        let key = parser.term()?;
+        with_borrowed(&*key, |_| ())?;
+
+        if matches!(parser.peek().kind, TokenKind::Punctuation("=>")) {
+            return Err(parser.peek().error("Map patterns associate keys with ':=', not '=>'"));
+        }
+        parser.expect(":=")?;
+
+        entries.push((key, node(parser)?));
+
+        if !parser.accept(",") {
+            break;
+        }
+    }
+    parser.expect("}")?;
+
+    Ok(Node::Map(entries))
+}
+
+/// Matches `term` against `node`, and adds the variables that it binds to
+/// `bound`.
+fn matches<'p, 'a>(node: &'p Node, term: &BorrowedTerm<'a>, bound: &mut Vec<(&'p str, BorrowedTerm<'a>)>) -> Result<bool, Error> {
+    Ok(match node {
+        Node::Any => true,
+        Node::Variable(name) => match bound.iter().find(|(variable, _)| variable == name) {
+            Some((_, value)) => exactly_equal_terms(value, term),
+            None => {
+                bound.push((name, term.clone()));
+                true
+            },
+        },
+        Node::Literal(literal) => with_borrowed(&**literal, |literal| exactly_equal_terms(literal, term))?,
+        Node::Tuple(nodes) => match term {
+            BorrowedTerm::Tuple(elements) if elements.len() == nodes.len() => {
+                for (node, element) in nodes.iter().zip(elements) {
+                    if !matches(node, element, bound)? {
+                        return Ok(false);
+                    }
+                }
+
+                true
+            },
+            _ => false,
+        },
+        Node::List(nodes, tail) => {
+            let mut list = ListCursor { list: term, index: 0 };
+
+            for node in nodes {
+                match list.next() {
+                    Some(head) if matches(node, &head, bound)? => {},
+                    _ => return Ok(false),
+                }
+            }
+
+            matches(tail, &list.rest(), bound)?
+        },
+        Node::Map(entries) => match term {
+            BorrowedTerm::Map(pairs) => {
+                for (key, node) in entries {
+                    let value = with_borrowed(&**key, |key| {
+                        pairs.iter().find(|(k, _)| compare_keys(k, key) == Ordering::Equal).map(|(_, value)| value)
+                    })?;
+
+                    match value {
+                        Some(value) if matches(node, value, bound)? => {},
+                        _ => return Ok(false),
+                    }
+                }
+
+                true
+            },
+            _ => false,
+        },
+    })
+}
+
+/// A position in a (possibly improper) list, which follows the tails of
+/// lists whose elements have all been taken.
+struct ListCursor<'t, 'a> {
+    list: &'t BorrowedTerm<'a>,
+    index: usize,
+}
+
+impl<'t, 'a> ListCursor<'t, 'a> {
+    /// Takes the next element, if the list has one.
+    fn next(&mut self) -> Option<Cow<'t, BorrowedTerm<'a>>> {
+        loop {
+            match self.list {
+                BorrowedTerm::List(elements, tail) if self.index == elements.len() => {
+                    self.list = tail;
+                    self.index = 0;
+                },
+                BorrowedTerm::List(elements, _) => {
+                    self.index += 1;
+                    return Some(Cow::Borrowed(&elements[self.index - 1]));
+                },
+                BorrowedTerm::String(bytes) if self.index < bytes.len() => {
+                    self.index += 1;
+                    return Some(Cow::Owned(BorrowedTerm::Integer(bytes[self.index - 1].into())));
+                },
+                _ => return None,
+            }
+        }
+    }
+
+    /// The rest of the list, after the elements that were taken.
+    fn rest(&self) -> Cow<'t, BorrowedTerm<'a>> {
+        match self.list {
+            _ if self.index == 0 => Cow::Borrowed(self.list),
+            BorrowedTerm::String(bytes) if self.index == bytes.len() => Cow::Owned(BorrowedTerm::Nil),
+            BorrowedTerm::String(bytes) => Cow::Owned(BorrowedTerm::String(&bytes[self.index..])),
+            BorrowedTerm::List(elements, tail) if self.index == elements.len() => Cow::Borrowed(&**tail),
+            BorrowedTerm::List(elements, tail) => Cow::Owned(BorrowedTerm::List(elements[self.index..].to_vec(), tail.clone())),
+            _ => unreachable!("Elements are only taken from lists"),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn join(f: &mut fmt::Formatter, nodes: &[Node]) -> fmt::Result {
+            for (i, node) in nodes.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}", node)?;
+            }
+
+            Ok(())
+        }
+
+        match self {
+            Node::Any => write!(f, "_"),
+            Node::Variable(name) => write!(f, "{}", name),
+            Node::Literal(term) => write!(f, "{}", single_line(&**term)),
+            Node::Tuple(nodes) => {
+                write!(f, "{{")?;
+                join(f, nodes)?;
+                write!(f, "}}")
+            },
+            Node::List(nodes, tail) => {
+                write!(f, "[")?;
+                join(f, nodes)?;
+
+                match &**tail {
+                    Node::Literal(term) if term.is::<ENil>() => {},
+                    tail => write!(f, "|{}", tail)?,
+                }
+
+                write!(f, "]")
+            },
+            Node::Map(entries) => {
+                write!(f, "#{{")?;
+                for (i, (key, node)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{} := {}", single_line(&**key), node)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ first_match, Pattern };
+    use super::super::{ EAtom, EString, ETerm };
+    use super::super::pretty::single_line;
+
+    fn bind(pattern: &str, term: &dyn ETerm) -> Option<String> {
+        Pattern::parse(pattern).unwrap().match_term(term).unwrap().map(|bindings| {
+            let bindings: Vec<String> = bindings.iter().map(|(name, term)| format!("{}={}", name, single_line(&**term))).collect();
+            bindings.join(" ")
+        })
+    }
+
+    fn some(bindings: &str) -> Option<String> {
+        Some(bindings.to_string())
+    }
+
+    #[test]
+    fn tuples() {
+        let term = eterm!({reply, 7, {ok, [1, 2]}});
+
+        assert_eq!(some("Ref=7 Value=[1,2]"), bind("{reply, Ref, {ok, Value}}", &term));
+        assert_eq!(some(""), bind("{reply, _, {ok, _}}.", &term));
+        assert_eq!(None, bind("{reply, _, {error, _}}", &term));
+        assert_eq!(None, bind("{reply, _}", &term));
+        assert_eq!(None, bind("{}", &term));
+        assert_eq!(some(""), bind("{}", &eterm!({})));
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(some(""), bind("1", &1u8));
+        assert_eq!(None, bind("1", &1.0f64));
+        assert_eq!(some(""), bind("-1.5", &-1.5f64));
+        assert_eq!(some(""), bind("\"ab\"", &eterm!([97, 98])));
+        assert_eq!(some(""), bind("<<\"ok\">>", &eterm!(<<"ok">>)));
+        assert_eq!(None, bind("<<\"ok\">>", &EString::new("ok")));
+        assert_eq!(some(""), bind("'hello world'", &EAtom::new("hello world")));
+        assert_eq!(None, bind("ok", &EAtom::new("error")));
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(some("H=1 T=[{2},3]"), bind("[H|T]", &eterm!([1, {2}, 3])));
+        assert_eq!(some("A=1 B={2} T=[3]"), bind("[A, B | T]", &eterm!([1, {2}, 3])));
+        assert_eq!(some("H=104 T=\"ello\""), bind("[H|T]", &EString::new("hello")));
+        assert_eq!(some("T=\"llo\""), bind("[$h, _ | T]", &EString::new("hello")));
+        assert_eq!(some("X=3"), bind("[1, 2, X]", &eterm!([1, 2, 3])));
+        assert_eq!(None, bind("[1, 2]", &eterm!([1, 2, 3])));
+        assert_eq!(some("T=c"), bind("[_, _ | T]", &eterm!([a, b | c])));
+        assert_eq!(None, bind("[H|T]", &eterm!([])));
+        assert_eq!(some(""), bind("[]", &EString::new("")));
+        assert_eq!(some("A=1 B=2 T=[3]"), bind("[A, B | T]", &eterm!([1 | [2, 3]])));
+        assert_eq!(some("T=[]"), bind("[1, 2 | T]", &eterm!([1 | [2]])));
+        assert_eq!(None, bind("[_, _, _]", &eterm!([1 | [2]])));
+    }
+
+    #[test]
+    fn maps() {
+        let term = eterm!(#{status => ok, "name" => <<"joe">>, 1 => one});
+
+        assert_eq!(some("S=ok"), bind("#{status := S}", &term));
+        assert_eq!(some("N=<<\"joe\">> O=one"), bind("#{\"name\" := N, 1 := O}", &term));
+        assert_eq!(some(""), bind("#{}", &term));
+        assert_eq!(None, bind("#{missing := _}", &term));
+        assert_eq!(None, bind("#{1.0 := _}", &term));
+        assert_eq!(None, bind("#{status := error}", &term));
+        assert_eq!(None, bind("#{}", &eterm!({})));
+    }
+
+    #[test]
+    fn repeated_variables() {
+        assert_eq!(some("X=1"), bind("{X, X}", &eterm!({1, 1})));
+        assert_eq!(None, bind("{X, X}", &eterm!({1, 1.0})));
+        assert_eq!(some(""), bind("{_, _}", &eterm!({1, 2})));
+    }
+
+    #[test]
+    fn bindings() {
+        let pattern = Pattern::parse("{point, X, Y}").unwrap();
+        let mut bindings = pattern.match_term(&eterm!({point, 3, -4})).unwrap().unwrap();
+
+        assert_eq!(3, bindings.get_as::<i32>("X").unwrap());
+        assert!(bindings.get_as::<i32>("Z").is_err());
+        assert_eq!("-4", bindings.remove("Y").unwrap().to_string());
+        assert_eq!(1, bindings.len());
+        assert!(pattern.is_match(&eterm!({point, a, b})).unwrap());
+
+        let names: Vec<String> = bindings.into_iter().map(|(name, _)| name).collect();
+        assert_eq!(vec!["X"], names);
+    }
+
+    #[test]
+    fn clauses() {
+        let clauses = [Pattern::parse("{a, X}").unwrap(), Pattern::parse("{_, X}").unwrap()];
+
+        assert_eq!(0, first_match(&clauses, &eterm!({a, 1})).unwrap().unwrap().0);
+        assert_eq!(1, first_match(&clauses, &eterm!({b, 1})).unwrap().unwrap().0);
+        assert!(first_match(&clauses, &eterm!({b})).unwrap().is_none());
+    }
+
+    #[test]
+    fn display() {
+        let text = "{reply,Ref,[H,_|T],[1],\"ab\",#{key := [_],{a} := 1.0}}";
+        assert_eq!(text, Pattern::parse(text).unwrap().to_string());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text| Pattern::parse(text).unwrap_err().to_string();
+
+        assert_eq!("The keys of a map pattern must be literals at line 1, column 3", error("#{K := 1}"));
+        assert_eq!("Expected ':=', found '}' at line 1, column 4", error("#{a}"));
+        assert_eq!("Map patterns associate keys with ':=', not '=>' at line 1, column 5", error("#{a => 1}"));
+        assert_eq!("Expected '}', found end of input at line 1, column 6", error("{a, X"));
+        assert_eq!("Expected end of input, found atom b at line 1, column 3", error("a b"));
+        assert!(error(&"{[".repeat(1_000_000)).starts_with("Terms are nested more than"));
+    }
+}
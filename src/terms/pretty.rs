@@ -0,0 +1,657 @@
+//! A pretty printer that formats terms like `io_lib:format("~p", [Term])`.
+//!
+//! Terms that fit in the remaining width of the line are written on one line;
+//! lists, tuples, maps and binaries that do not are split over several lines,
+//! with their elements aligned after the opening bracket.
+//! Tuples that start with an atom, like records and tagged values, keep the
+//! atom on the first line and align the other elements after it.
+//! Lists of printable characters are written as strings and binaries of
+//! printable characters as `<<"text">>`, and maps are written with their keys
+//! in term order.
+//!
+//! Just like `~P`, a depth can be given to truncate large terms: elements
+//! beyond the depth are replaced by `...`.
+//!
+//! ```
+//! use rust_eterm::terms::{ EAtom, EBinary, EList, ETerm, ETuple };
+//! use rust_eterm::terms::pretty::{ pretty, PrettyOptions };
+//!
+//! let name: Vec<Box<dyn ETerm>> = "Joe".bytes().map(|b| Box::new(b) as Box<dyn ETerm>).collect();
+//! let person = ETuple::from((EAtom::new("person"), EList::new(name), EBinary::from(&b"erlang"[..])));
+//! assert_eq!("{person,\"Joe\",<<\"erlang\">>}", pretty(&person, &PrettyOptions::default()));
+//!
+//! assert_eq!(
+//!     "{person,\"Joe\",\n        <<\"erlang\">>}",
+//!     pretty(&person, &PrettyOptions::default().with_width(20)),
+//! );
+//!
+//! let numbers: EList = (1..=5u8).map(|i| Box::new(i) as Box<dyn ETerm>).collect();
+//! assert_eq!("[1,2|...]", pretty(&numbers, &PrettyOptions::default().with_depth(3)));
+//! ```
+//!
+//! Terms of types that this module does not know, like the ones that are
+//! derived with `#[derive(ToETerm)]`, are encoded and decoded to find out what
+//! they look like.
+
+use super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, ETerm, ETuple, EString };
+use super::convert::{ integer, FromETerm };
+use super::printing::{ code_points, reencode, sorted_entries };
+
+use std::str;
+
+/// The characters that are considered printable in strings and binaries,
+/// like the `+pc` flag of `erl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Printable {
+    /// Only the printable characters of ISO LATIN-1.
+    Latin1,
+    /// All printable Unicode characters, like `~tp`.
+    ///
+    /// Binaries that are printable UTF-8 but not printable LATIN-1 are
+    /// written as `<<"text"/utf8>>`.
+    Unicode,
+}
+
+/// Options that influence how terms are pretty printed, mirroring the
+/// arguments of `io_lib_pretty:print/4`.
+#[derive(Clone, Debug)]
+pub struct PrettyOptions {
+    width: usize,
+    indent: usize,
+    depth: Option<usize>,
+    printable: Printable,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> PrettyOptions {
+        PrettyOptions {
+            width: 80,
+            indent: 0,
+            depth: None,
+            printable: Printable::Latin1,
+        }
+    }
+}
+
+impl PrettyOptions {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The maximum length of a line, which is only exceeded by terms that
+    /// can not be split, such as long atoms.
+    pub fn with_width(mut self, width: usize) -> PrettyOptions {
+        self.width = width;
+        self
+    }
+
+    pub fn indent(&self) -> usize {
+        self.indent
+    }
+
+    /// The column at which the term starts.
+    ///
+    /// The first line is not indented, as it is expected to follow whatever
+    /// was written before the term; the other lines are indented to align
+    /// with it.
+    pub fn with_indent(mut self, indent: usize) -> PrettyOptions {
+        self.indent = indent;
+        self
+    }
+
+    pub fn depth(&self) -> Option<usize> {
+        self.depth
+    }
+
+    /// Truncates the term at `depth`, like `~P`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is 0.
+    pub fn with_depth(mut self, depth: usize) -> PrettyOptions {
+        assert!(depth > 0, "The depth must be at least 1");
+
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn printable(&self) -> Printable {
+        self.printable
+    }
+
+    pub fn with_printable(mut self, printable: Printable) -> PrettyOptions {
+        self.printable = printable;
+        self
+    }
+}
+
+/// Pretty prints `term`.
+pub fn pretty(term: &dyn ETerm, options: &PrettyOptions) -> String {
+    let doc = Printer { options }.doc(term, options.depth, false);
+
+    // The indentation is only written to find the column of the text that
+    // follows it.
+    let mut result = " ".repeat(options.indent);
+    doc.layout(options.indent, 0, options.width, &mut result);
+
+    result.split_off(options.indent)
+}
+
+/// A term that is ready to be laid out.
+enum Doc {
+    /// Text that is never split.
+    Text(String),
+    Seq {
+        open: &'static str,
+        items: Vec<Doc>,
+        /// The tail of an improper or truncated list.
+        tail: Option<Box<Doc>>,
+        close: &'static str,
+        /// Whether the first item is a tag that stays on the first line.
+        tagged: bool,
+    },
+    /// A key and value of a map.
+    Assoc(Box<Doc>, Box<Doc>),
+}
+
+impl Doc {
+    fn flat(&self, out: &mut String) {
+        match self {
+            Doc::Text(text) => out.push_str(text),
+            Doc::Seq { open, items, tail, close, .. } => {
+                out.push_str(open);
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.flat(out);
+                }
+
+                if let Some(tail) = tail {
+                    out.push('|');
+                    tail.flat(out);
+                }
+
+                out.push_str(close);
+            },
+            Doc::Assoc(key, value) => {
+                key.flat(out);
+                out.push_str(" => ");
+                value.flat(out);
+            },
+        }
+    }
+
+    /// Writes this doc starting at `column`, followed by `trailing`
+    /// characters of closing brackets and separators.
+    fn layout(&self, column: usize, trailing: usize, width: usize, out: &mut String) {
+        let mut flat = String::new();
+        self.flat(&mut flat);
+
+        let fits = column + flat.chars().count() + trailing <= width;
+
+        match self {
+            Doc::Seq { open, items, tail, close, tagged } if !fits && !items.is_empty() => {
+                out.push_str(open);
+                let mut column = column + open.chars().count();
+
+                let mut items = items.iter().peekable();
+                if *tagged {
+                    let tag = items.next().expect("Tagged sequences have a tag");
+                    let start = out.len();
+                    tag.flat(out);
+                    out.push(',');
+                    column += out[start..].chars().count();
+                }
+
+                let mut first = true;
+                while let Some(item) = items.next() {
+                    if !first {
+                        out.push_str(",\n");
+                        out.push_str(&" ".repeat(column));
+                    }
+                    first = false;
+
+                    let trailing = match (items.peek(), tail) {
+                        (Some(_), _) | (None, Some(_)) => 1,
+                        (None, None) => close.chars().count() + trailing,
+                    };
+                    item.layout(column, trailing, width, out);
+                }
+
+                if let Some(tail) = tail {
+                    out.push('|');
+                    let last_line = out.rsplit('\n').next().unwrap_or("").chars().count();
+                    tail.layout(last_line, close.chars().count() + trailing, width, out);
+                }
+
+                out.push_str(close);
+            },
+            Doc::Assoc(key, value) if !fits => {
+                let start = out.len();
+                key.flat(out);
+                out.push_str(" => ");
+                let column = column + out[start..].chars().count();
+
+                value.layout(column, trailing, width, out);
+            },
+            _ => out.push_str(&flat),
+        }
+    }
+}
+
+struct Printer<'a> {
+    options: &'a PrettyOptions,
+}
+
+impl Printer<'_> {
+    /// Builds the doc for `term`, truncated at `depth`, with `decoded` as
+    /// described for [`reencode`].
+    fn doc(&self, term: &dyn ETerm, depth: Option<usize>, decoded: bool) -> Doc {
+        if depth == Some(0) {
+            return Doc::Text("...".to_string());
+        }
+
+        if let Some(list) = term.downcast_ref::<EList>() {
+            self.list(list.as_slice(), None, depth)
+        } else if let Some(list) = term.downcast_ref::<ENonProperList>() {
+            self.list(list.data(), Some(list.tail()), depth)
+        } else if let Some(string) = term.downcast_ref::<EString>() {
+            if string.as_str().is_empty() {
+                Doc::Text("[]".to_string())
+            } else {
+                self.list(&code_points(string), None, depth)
+            }
+        } else if let Some(tuple) = term.downcast_ref::<ETuple>() {
+            self.tuple(tuple.as_slice(), depth)
+        } else if let Some(map) = term.downcast_ref::<EMap>() {
+            self.map(map, depth)
+        } else if let Some(binary) = term.downcast_ref::<EBinary>() {
+            self.binary(binary.as_bytes(), depth)
+        } else if let Some(export) = term.downcast_ref::<EExport>() {
+            Doc::Text(format!("fun {}:{}/{}", export.module(), export.function(), export.arity()))
+        } else if let Some(&value) = term.downcast_ref::<f64>() {
+            Doc::Text(format_float(value))
+        } else if let Some(&value) = term.downcast_ref::<f32>() {
+            Doc::Text(format_float(value.into()))
+        } else if decoded || integer(term).is_some() || term.is::<EAtom>() || term.is::<ENil>() {
+            Doc::Text(term.to_string())
+        } else {
+            // Derived types and other Rust values look like whatever they
+            // are encoded as.
+            match reencode(term) {
+                Some(decoded_term) => self.doc(&*decoded_term, depth, true),
+                None => Doc::Text(term.to_string()),
+            }
+        }
+    }
+
+    fn list(&self, elements: &[Box<dyn ETerm>], tail: Option<&dyn ETerm>, depth: Option<usize>) -> Doc {
+        if tail.is_none() {
+            let chars: Option<Vec<u32>> = elements.iter()
+                .map(|element| u32::from_eterm(&**element).ok())
+                .collect();
+
+            match chars {
+                Some(ref chars) if !chars.is_empty() && self.is_printable(chars) => return self.string(chars, depth),
+                _ => {},
+            }
+        }
+
+        if depth == Some(1) {
+            return Doc::Text("[...]".to_string());
+        }
+
+        let mut items = Vec::new();
+        let mut depth = depth.map(|d| d - 1);
+        let mut truncated = false;
+
+        for (i, element) in elements.iter().enumerate() {
+            if i > 0 {
+                if depth == Some(1) {
+                    truncated = true;
+                    break;
+                }
+                depth = depth.map(|d| d - 1);
+            }
+
+            items.push(self.doc(&**element, depth, false));
+        }
+
+        let tail = if truncated {
+            Some(Box::new(Doc::Text("...".to_string())))
+        } else {
+            tail.map(|tail| match depth {
+                Some(1) => Box::new(Doc::Text("...".to_string())),
+                _ => Box::new(self.doc(tail, depth.map(|d| d - 1), false)),
+            })
+        };
+
+        Doc::Seq { open: "[", items, tail, close: "]", tagged: false }
+    }
+
+    fn tuple(&self, elements: &[Box<dyn ETerm>], depth: Option<usize>) -> Doc {
+        if elements.is_empty() {
+            return Doc::Text("{}".to_string());
+        }
+
+        if depth == Some(1) {
+            return Doc::Text("{...}".to_string());
+        }
+
+        let mut items = Vec::new();
+        let mut depth = depth.map(|d| d - 1);
+
+        for (i, element) in elements.iter().enumerate() {
+            if i > 0 {
+                if depth == Some(1) {
+                    items.push(Doc::Text("...".to_string()));
+                    break;
+                }
+                depth = depth.map(|d| d - 1);
+            }
+
+            items.push(self.doc(&**element, depth, false));
+        }
+
+        let tagged = elements.len() > 1 && elements[0].is::<EAtom>();
+
+        Doc::Seq { open: "{", items, tail: None, close: "}", tagged }
+    }
+
+    fn map(&self, map: &EMap, depth: Option<usize>) -> Doc {
+        if map.is_empty() {
+            return Doc::Text("#{}".to_string());
+        }
+
+        if depth == Some(1) {
+            return Doc::Text("#{...}".to_string());
+        }
+
+        let entries = sorted_entries(map);
+
+        let mut items = Vec::new();
+        let mut remaining = depth;
+        let depth = depth.map(|d| d - 1);
+
+        for (key, value) in entries {
+            if remaining == Some(1) {
+                items.push(Doc::Text("...".to_string()));
+                break;
+            }
+            remaining = remaining.map(|d| d - 1);
+
+            items.push(Doc::Assoc(Box::new(self.doc(&**key, depth, false)), Box::new(self.doc(&**value, depth, false))));
+        }
+
+        Doc::Seq { open: "#{", items, tail: None, close: "}", tagged: false }
+    }
+
+    fn binary(&self, bytes: &[u8], depth: Option<usize>) -> Doc {
+        if bytes.is_empty() {
+            return Doc::Text("<<>>".to_string());
+        }
+
+        if depth == Some(1) {
+            return Doc::Text("<<...>>".to_string());
+        }
+
+        let limit = depth.map(|d| d - 1).unwrap_or(usize::MAX);
+
+        if self.options.printable == Printable::Unicode {
+            if let Ok(text) = str::from_utf8(bytes) {
+                let chars: Vec<u32> = text.chars().map(|c| c as u32).collect();
+
+                if is_printable(&chars, Printable::Unicode) {
+                    let text = quote(&chars, limit);
+
+                    return Doc::Text(match (text.is_ascii(), text.strip_suffix("...")) {
+                        (true, _) => format!("<<{}>>", text),
+                        (false, Some(text)) => format!("<<{}/utf8...>>", text),
+                        (false, None) => format!("<<{}/utf8>>", text),
+                    });
+                }
+            }
+        }
+
+        let latin1: Vec<u32> = bytes.iter().map(|&b| b.into()).collect();
+        if is_printable(&latin1, Printable::Latin1) {
+            return Doc::Text(format!("<<{}>>", quote(&latin1, limit)));
+        }
+
+        let mut items: Vec<Doc> = bytes.iter().take(limit).map(|b| Doc::Text(b.to_string())).collect();
+        if bytes.len() > limit {
+            items.push(Doc::Text("...".to_string()));
+        }
+
+        Doc::Seq { open: "<<", items, tail: None, close: ">>", tagged: false }
+    }
+
+    fn string(&self, chars: &[u32], depth: Option<usize>) -> Doc {
+        Doc::Text(quote(chars, depth.map(|d| d - 1).unwrap_or(usize::MAX)))
+    }
+
+    fn is_printable(&self, chars: &[u32]) -> bool {
+        is_printable(chars, self.options.printable)
+    }
+}
+
+/// Prints `term` on a single line, however long it is.
+pub(crate) fn single_line(term: &dyn ETerm) -> String {
+    pretty(term, &PrettyOptions::default().with_width(usize::MAX))
+}
+
+fn is_printable(chars: &[u32], printable: Printable) -> bool {
+    chars.iter().all(|&c| match c {
+        0x08..=0x0d | 0x1b | 0x20..=0x7e | 0xa0..=0xff => true,
+        0x100..=0xd7ff | 0xe000..=0xfffd | 0x10000..=0x10ffff => printable == Printable::Unicode,
+        _ => false,
+    })
+}
+
+/// Quotes the first `limit` printable `chars`, followed by `...` if there
+/// are more.
+fn quote(chars: &[u32], limit: usize) -> String {
+    let mut result = "\"".to_string();
+
+    for &c in chars.iter().take(limit) {
+        match c {
+            0x08 => result.push_str("\\b"),
+            0x09 => result.push_str("\\t"),
+            0x0a => result.push_str("\\n"),
+            0x0b => result.push_str("\\v"),
+            0x0c => result.push_str("\\f"),
+            0x0d => result.push_str("\\r"),
+            0x1b => result.push_str("\\e"),
+            0x22 => result.push_str("\\\""),
+            0x5c => result.push_str("\\\\"),
+            _ => result.push(std::char::from_u32(c).expect("Printable characters are valid")),
+        }
+    }
+
+    result.push('"');
+    if chars.len() > limit {
+        result.push_str("...");
+    }
+
+    result
+}
+
+/// Formats a float the way Erlang does: with the shortest representation
+/// that reads back as the same float, and in scientific notation only when
+/// that is shorter.
+pub(crate) fn format_float(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value == 0.0 {
+        return format!("{}0.0", sign);
+    }
+
+    // `{:e}` writes the shortest digits, like `1.2345e-3`.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').expect("Scientific notation has an exponent"));
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let exponent: i64 = exponent[1..].parse().expect("The exponent is an integer");
+
+    // The value is 0.DIGITS * 10^place.
+    let place = exponent + 1;
+    let length = digits.len() as i64;
+
+    let with_exponent = || {
+        let (first, rest) = digits.split_at(1);
+        format!("{}.{}e{}", first, if rest.is_empty() { "0" } else { rest }, exponent)
+    };
+
+    let formatted = if place == 0 {
+        format!("0.{}", digits)
+    } else if place < 0 || place >= length {
+        let exponent_cost = exponent.to_string().len() as i64 + 1 + if length == 1 { 2 } else { 1 };
+
+        if place < 0 {
+            if 2 - place <= exponent_cost {
+                format!("0.{}{}", "0".repeat(-place as usize), digits)
+            } else {
+                with_exponent()
+            }
+        } else if place - length + 2 <= exponent_cost {
+            format!("{}{}.0", digits, "0".repeat((place - length) as usize))
+        } else {
+            with_exponent()
+        }
+    } else {
+        let (whole, fraction) = digits.split_at(place as usize);
+        format!("{}.{}", whole, fraction)
+    };
+
+    format!("{}{}", sign, formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ format_float, pretty, Printable, PrettyOptions };
+    use super::super::{ EAtom, EBinary, EExport, EList, EMap, ENil, ENonProperList, ETerm, ETuple, EString };
+
+    fn list(elements: Vec<Box<dyn ETerm>>) -> EList {
+        EList::new(elements)
+    }
+
+    fn p(term: &dyn ETerm) -> String {
+        pretty(term, &PrettyOptions::default())
+    }
+
+    fn chars(text: &str) -> EList {
+        text.chars().map(|c| Box::new(c as u32) as Box<dyn ETerm>).collect()
+    }
+
+    #[test]
+    fn floats() {
+        let cases = [
+            (1.0, "1.0"),
+            (-1.5, "-1.5"),
+            (0.1, "0.1"),
+            (100.0, "100.0"),
+            (1000.0, "1.0e3"),
+            (123456.789, "123456.789"),
+            (1e20, "1.0e20"),
+            (1.5e300, "1.5e300"),
+            (0.001, "0.001"),
+            (1.25e-10, "1.25e-10"),
+            (0.0, "0.0"),
+            (-0.0, "-0.0"),
+        ];
+
+        for (value, expected) in cases.iter() {
+            assert_eq!(*expected, format_float(*value));
+        }
+
+        assert_eq!("[0.5,2.5]", p(&list(vec![Box::new(0.5f64), Box::new(2.5f32)])));
+    }
+
+    #[test]
+    fn flat() {
+        assert_eq!("[1,a,[]]", p(&list(vec![Box::new(1u8), Box::new(EAtom::new("a")), Box::new(ENil)])));
+        assert_eq!("[1,2|t]", p(&ENonProperList::new(vec![Box::new(1u8), Box::new(2u8)], Box::new(EAtom::new("t")))));
+        assert_eq!("{}", p(&ETuple::new(vec![])));
+        assert_eq!("#{a => 1,b => {}}", p(&EMap::new(vec![
+            (Box::new(EAtom::new("b")), Box::new(ETuple::new(vec![]))),
+            (Box::new(EAtom::new("a")), Box::new(1u8)),
+        ])));
+        assert_eq!("fun lists:map/2", p(&EExport::new("lists", "map", 2)));
+        assert_eq!("<<1,2,3>>", p(&EBinary::new(vec![1, 2, 3])));
+        assert_eq!("<<>>", p(&EBinary::new(vec![])));
+        assert_eq!("[]", p(&EString::new("")));
+    }
+
+    #[test]
+    fn printable() {
+        assert_eq!("\"hello\\n\\\"world\\\"\"", p(&chars("hello\n\"world\"")));
+        assert_eq!("\"héllo\"", p(&EString::new("héllo")));
+        assert_eq!("[104,1]", p(&EString::new("h\u{1}")));
+        assert_eq!("[1,2,3]", p(&chars("\u{1}\u{2}\u{3}")));
+        assert_eq!("[256]", p(&chars("Ā")));
+        assert_eq!("<<\"text\">>", p(&EBinary::from(&b"text"[..])));
+        assert_eq!("<<\"Ã©\">>", p(&EBinary::from("é".as_bytes())));
+        assert_eq!("<<128>>", p(&EBinary::new(vec![128])));
+
+        let unicode = PrettyOptions::default().with_printable(Printable::Unicode);
+        assert_eq!("\"Ā\"", pretty(&chars("Ā"), &unicode));
+        assert_eq!("<<\"é\"/utf8>>", pretty(&EBinary::from("é".as_bytes()), &unicode));
+        assert_eq!("<<\"\\\\\">>", pretty(&EBinary::from(&b"\\"[..]), &unicode));
+    }
+
+    #[test]
+    fn line_width() {
+        let entries: EList = (0..30u8).map(|i| Box::new(ETuple::from((EAtom::new("key"), i))) as Box<dyn ETerm>).collect();
+        let printed = p(&entries);
+
+        assert!(printed.lines().all(|line| line.len() <= 80), "{}", printed);
+        assert!(printed.starts_with("[{key,0},\n {key,1},\n"));
+        assert!(printed.ends_with(" {key,29}]"));
+
+        let tagged = ETuple::from((EAtom::new("person"), EString::new("Joe"), EString::new("Armstrong")));
+        let options = PrettyOptions::default().with_width(20);
+        assert_eq!("{person,\"Joe\",\n        \"Armstrong\"}", pretty(&tagged, &options));
+        assert_eq!("{person,\"Joe\",\n          \"Armstrong\"}", pretty(&tagged, &options.clone().with_indent(2)));
+
+        let map = EMap::new(vec![
+            (Box::new(EAtom::new("name")), Box::new(EString::new("Joe Armstrong"))),
+            (Box::new(EAtom::new("language")), Box::new(EAtom::new("erlang"))),
+        ]);
+        assert_eq!("#{language => erlang,\n  name => \"Joe Armstrong\"}", pretty(&map, &PrettyOptions::default().with_width(30)));
+
+        let nested = ENonProperList::new(vec![Box::new(chars("aaaaaaaaaa"))], Box::new(chars("bbbbbbbbbb")));
+        assert_eq!("[\"aaaaaaaaaa\"|\"bbbbbbbbbb\"]", pretty(&nested, &PrettyOptions::default().with_width(30)));
+        assert_eq!("[\"aaaaaaaaaa\",\n \"bbbbbbbbbb\"|\"cc\"]", pretty(&ENonProperList::new(vec![Box::new(chars("aaaaaaaaaa")), Box::new(chars("bbbbbbbbbb"))], Box::new(chars("cc"))), &PrettyOptions::default().with_width(20)));
+    }
+
+    #[test]
+    fn depth() {
+        let numbers: EList = (1..=5u8).map(|i| Box::new(i) as Box<dyn ETerm>).collect();
+        let tuple: ETuple = (1..=5u8).map(|i| Box::new(i) as Box<dyn ETerm>).collect();
+        let depth = |d| PrettyOptions::default().with_depth(d);
+
+        assert_eq!("[...]", pretty(&numbers, &depth(1)));
+        assert_eq!("[1|...]", pretty(&numbers, &depth(2)));
+        assert_eq!("[1,2,3,4|...]", pretty(&numbers, &depth(5)));
+        assert_eq!("[1,2,3,4,5]", pretty(&numbers, &depth(6)));
+        assert_eq!("{1,2,...}", pretty(&tuple, &depth(3)));
+        assert_eq!("{a,{...},...}", pretty(&ETuple::from((EAtom::new("a"), ETuple::from((EAtom::new("b"),)), 1u8)), &depth(3)));
+        assert_eq!("[1|...]", pretty(&ENonProperList::new(vec![Box::new(1u8)], Box::new(2u8)), &depth(2)));
+        assert_eq!("[1|2]", pretty(&ENonProperList::new(vec![Box::new(1u8)], Box::new(2u8)), &depth(3)));
+        assert_eq!("\"he\"...", pretty(&chars("hello"), &depth(3)));
+        assert_eq!("<<\"he\"...>>", pretty(&EBinary::from(&b"hello"[..]), &depth(3)));
+        assert_eq!("<<1,2,...>>", pretty(&EBinary::new(vec![1, 2, 3, 4]), &depth(3)));
+
+        let map: EMap = (1..=5u8).map(|i| (Box::new(i) as Box<dyn ETerm>, Box::new(ETuple::from((i,))) as Box<dyn ETerm>)).collect();
+        assert_eq!("#{1 => {1},2 => {2},...}", pretty(&map, &depth(3)));
+    }
+
+    #[test]
+    fn unknown_types() {
+        assert_eq!("[true,\"text\",{ok,97}]", p(&list(vec![Box::new(true), Box::new("text".to_string()), Box::new(ETuple::from((EAtom::new("ok"), 'a')))])));
+    }
+}
@@ -0,0 +1,78 @@
+//! Helpers shared by the printers in [`pretty`], [`elixir`] and [`gleam`].
+//!
+//! [`pretty`]: ../pretty/index.html
+//! [`elixir`]: ../elixir/index.html
+//! [`gleam`]: ../gleam/index.html
+
+use super::{ EMap, ETerm, EString };
+use super::decode::{ decode, DecodeOptions };
+use super::order::compare_map_keys;
+
+/// Encodes and decodes a term of a type that the printers do not know, to
+/// find out which terms it consists of.
+///
+/// The printers pass a `decoded` flag along with the terms this returns, so
+/// that terms of unknown types inside them are not decoded again.
+pub(crate) fn reencode(term: &dyn ETerm) -> Option<Box<dyn ETerm>> {
+    term.to_external_binary()
+        .and_then(|bytes| decode(&mut bytes.as_slice(), &DecodeOptions::default()))
+        .ok()
+}
+
+/// Writes `items` to `out` with `write`, separated by `, `.
+pub(crate) fn write_separated<I, F>(items: I, out: &mut String, mut write: F)
+    where I: IntoIterator, F: FnMut(I::Item, &mut String)
+{
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write(item, out);
+    }
+}
+
+/// The entries of `map`, with their keys in the order in which Erlang
+/// prints them.
+#[allow(clippy::type_complexity)]
+pub(crate) fn sorted_entries(map: &EMap) -> Vec<&(Box<dyn ETerm>, Box<dyn ETerm>)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| compare_map_keys(&**a, &**b));
+
+    entries
+}
+
+/// The elements of the list that `string` stands for, which are its code
+/// points.
+pub(crate) fn code_points(string: &EString) -> Vec<Box<dyn ETerm>> {
+    string.as_str().chars().map(|c| Box::new(c as u32) as Box<dyn ETerm>).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::EAtom;
+
+    #[test]
+    fn map_keys_in_erlang_order() {
+        let map = EMap::new(vec![
+            (Box::new(EAtom::new("a")), Box::new(1u8)),
+            (Box::new(1.0f64), Box::new(2u8)),
+            (Box::new(2u8), Box::new(3u8)),
+            (Box::new(f64::NAN), Box::new(4u8)),
+        ]);
+        // Floats are displayed like `1`, but come after every integer.
+        let keys: Vec<String> = sorted_entries(&map).iter().map(|(key, _)| key.to_string()).collect();
+
+        assert_eq!(vec!["2", "1", "a", "NaN"], keys);
+    }
+
+    #[test]
+    fn strings_are_code_points() {
+        let elements = code_points(&EString::new("hЖ"));
+        assert_eq!("104,1046", elements.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(","));
+
+        let mut out = String::new();
+        write_separated(&elements, &mut out, |element, out| out.push_str(&element.to_string()));
+        assert_eq!("104, 1046", out);
+    }
+}
@@ -0,0 +1,161 @@
+//! Checking whether a buffer contains a well-formed term without decoding it.
+//!
+//! The validator enforces the same rules as [`decode`], but does not build
+//! any terms.
+//! Just like `binary_to_term(Binary, [used])`, it reports the amount of bytes
+//! the term takes, so a term can be checked and delimited before storing it.
+//!
+//! [`decode`]: ../decode/fn.decode.html
+
+use super::{ TermTag, ETF_VERSION };
+use super::decode::{ check_finite, parse_float_ext, DecodeOptions };
+use super::layout::walk;
+use super::super::error::Error;
+
+use std::convert::TryInto;
+use std::io;
+use std::str;
+
+/// Validates the (unversioned) term at the start of `bytes`, as [`decode`]
+/// with `options` would decode it.
+///
+/// Returns the amount of bytes the term takes; any bytes after it are not
+/// inspected.
+///
+/// [`decode`]: ../decode/fn.decode.html
+pub fn validate(bytes: &[u8], options: &DecodeOptions) -> Result<usize, Error> {
+    walk(bytes, |tag, header, leading| validate_term(tag, header, leading, options))
+}
+
+/// Validates a term that starts with the [`ETF_VERSION`] byte, like the
+/// output of `erlang:term_to_binary/1`.
+///
+/// Returns the amount of bytes used, including the version byte.
+///
+/// [`ETF_VERSION`]: ../constant.ETF_VERSION.html
+pub fn validate_versioned(bytes: &[u8], options: &DecodeOptions) -> Result<usize, Error> {
+    match bytes.split_first() {
+        Some((&ETF_VERSION, rest)) => Ok(1 + validate(rest, options)?),
+        Some((version, _)) => Err(Error::Message(format!("Unsupported external term format version {}", version))),
+        None => Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+fn validate_term(tag: TermTag, header: &[u8], leading: &[u8], options: &DecodeOptions) -> Result<(), Error> {
+    match tag {
+        TermTag::SmallInteger
+        | TermTag::Integer
+        | TermTag::Nil
+        | TermTag::List
+        | TermTag::SmallTuple
+        | TermTag::LargeTuple
+        | TermTag::Map
+        | TermTag::Binary
+        | TermTag::SmallAtom
+        | TermTag::Atom => Ok(()),
+        TermTag::String if options.read_string_ext_as_list => validate_utf8(leading),
+        TermTag::String => Ok(()),
+        TermTag::SmallAtomUtf8 | TermTag::AtomUtf8 => validate_utf8(leading),
+        TermTag::NewFloat => {
+            check_finite(f64::from_be_bytes(leading.try_into().expect("NEW_FLOAT_EXT has 8 bytes")))?;
+            Ok(())
+        },
+        TermTag::Float => {
+            parse_float_ext(leading)?;
+            Ok(())
+        },
+        TermTag::SmallBig | TermTag::LargeBig => validate_big(header[header.len() - 1] != 0, leading),
+        _ => Err(Error::Message("Decoding of this term type not yet supported".to_string())),
+    }
+}
+
+fn validate_utf8(bytes: &[u8]) -> Result<(), Error> {
+    str::from_utf8(bytes)
+        .map(|_| ())
+        .map_err(|e| Error::Message(e.to_string()))
+}
+
+#[cfg(feature="bigint")]
+fn validate_big(_negative: bool, _digits: &[u8]) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(not(feature="bigint"))]
+fn validate_big(negative: bool, digits: &[u8]) -> Result<(), Error> {
+    // Without big integer support only values that fit in an i128 or u128
+    // can be decoded.
+    super::decode::num_vec_to_term(negative, digits).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::decode::decode;
+
+    fn check(bytes: &[u8], options: &DecodeOptions) -> Result<usize, Error> {
+        let result = validate(bytes, options);
+        let decoded = decode(&mut &bytes[..], options);
+
+        assert_eq!(result.is_ok(), decoded.is_ok(), "Validation and decoding disagree on {:?}", bytes);
+
+        result
+    }
+
+    #[test]
+    fn valid_terms() {
+        let options = DecodeOptions::default();
+
+        for bytes in &[
+            vec![97, 1],
+            vec![104, 2, 119, 2, b'o', b'k', 70, 63, 224, 0, 0, 0, 0, 0, 0],
+            vec![108, 0, 0, 0, 2, 97, 1, 100, 0, 1, 0xe9, 106],
+            vec![116, 0, 0, 0, 1, 109, 0, 0, 0, 1, 0, 107, 0, 2, 0xff, 0xfe],
+            vec![110, 9, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+        ] {
+            assert_eq!(bytes.len(), check(bytes, &options).unwrap());
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_not_used() {
+        let bytes = [104, 1, 97, 1, 97, 2];
+        assert_eq!(4, check(&bytes, &DecodeOptions::default()).unwrap());
+
+        let versioned = [ETF_VERSION, 104, 1, 97, 1, 97, 2];
+        assert_eq!(5, validate_versioned(&versioned, &DecodeOptions::default()).unwrap());
+        assert!(validate_versioned(&bytes, &DecodeOptions::default()).is_err());
+    }
+
+    #[test]
+    fn invalid_terms() {
+        let options = DecodeOptions::default();
+
+        for bytes in &[
+            // Truncated
+            vec![104, 2, 97, 1],
+            vec![109, 0, 0, 0, 5, 1, 2],
+            vec![],
+            // Unknown tag
+            vec![1],
+            // Invalid UTF-8 in an atom
+            vec![119, 1, 0xff],
+            // Infinite float
+            vec![70, 127, 240, 0, 0, 0, 0, 0, 0],
+            // Not a float
+            [&[99][..], &[b'x'; 31][..]].concat(),
+        ] {
+            assert!(check(bytes, &options).is_err(), "{:?} should be invalid", bytes);
+        }
+    }
+
+    #[test]
+    fn string_ext_as_estring_must_be_utf8() {
+        let options = DecodeOptions {
+            read_string_ext_as_list: true,
+            ..DecodeOptions::default()
+        };
+
+        assert!(check(&[107, 0, 2, 0xff, 0xfe], &options).is_err());
+        assert_eq!(5, check(&[107, 0, 2, b'o', b'k'], &options).unwrap());
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::Write;
+use std::process::{ Command, Output, Stdio };
+
+fn etf(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_etf"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn round_trip() {
+    let encoded = etf(&["--encode"], b"{ok, [1, 300], <<\"hi\">>}.\n#{a => 1.5}.\n");
+    assert!(encoded.status.success());
+    assert_eq!(131, encoded.stdout[0]);
+
+    assert_eq!("{ok,[1,300],<<104,105>>}\n#{a=>1.5}\n", stdout(&etf(&[], &encoded.stdout)));
+    assert_eq!("{:ok, [1, 300], \"hi\"}\n%{a: 1.5}\n", stdout(&etf(&["-f", "elixir"], &encoded.stdout)));
+    assert_eq!("[\n  \"ok\",\n  [\n    1,\n    300\n  ],\n  \"hi\"\n]\n{\n  \"a\": 1.5\n}\n", stdout(&etf(&["--format=json"], &encoded.stdout)));
+}
+
+#[test]
+fn compressed_frames() {
+    let text = format!("{{text, \"{}\"}}.\n[].\n", "a".repeat(1000));
+    let encoded = etf(&["-e", "--compressed=9", "--packet", "2"], text.as_bytes());
+    assert!(encoded.status.success());
+    assert!(encoded.stdout.len() < 100);
+
+    assert_eq!("Term 0 at offset 2: valid, 1011 bytes\nTerm 1 at offset 47: valid, 1 bytes\n", stdout(&etf(&["--validate", "-p", "2"], &encoded.stdout)));
+    assert_eq!("[]\n", stdout(&etf(&["-p", "2"], &encoded.stdout)).lines().nth(1).map(|line| format!("{}\n", line)).unwrap());
+}
+
+#[test]
+fn files() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/conformance/export.etf");
+    let output = etf(&[fixture, "-f", "hex"], b"");
+
+    assert_eq!(
+        "Term 0 at offset 0: EXPORT_EXT, 15 bytes\n\
+         00000000  71                        EXPORT_EXT\n\
+         00000001  77 05 6c 69 73 74 73        SMALL_ATOM_UTF8_EXT len=5 lists\n\
+         00000008  77 03 6d 61 70              SMALL_ATOM_UTF8_EXT len=3 map\n\
+         0000000d  61 02                       SMALL_INTEGER_EXT 2\n",
+        stdout(&output),
+    );
+}
+
+#[test]
+fn errors() {
+    let output = etf(&[], &[131, 104, 2, 97]);
+    assert_eq!(Some(1), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("etf: "));
+
+    assert_eq!(Some(1), etf(&["-e"], b"{unterminated").status.code());
+    assert_eq!(Some(2), etf(&["--format", "yaml"], b"").status.code());
+    assert_eq!(Some(2), etf(&["--compressed"], b"").status.code());
+}